@@ -0,0 +1,261 @@
+//! Capture files store the notifications received by the engine, so that
+//! they can be analyzed later.
+//!
+//! A capture starts with an 8 bytes header, `MQTTCAP` followed by the format
+//! version. Every record is prefixed by its length, so a reader can skip
+//! records of unknown kind and a file truncated in the middle of a record is
+//! still readable up to the last complete one. Records can be appended to an
+//! existing capture.
+//!
+//! ```text
+//! record  := length:u32 secs:u64 nanos:u32 kind:u8 body
+//! publish := qos:u8 retain:u8 topic_length:u16 topic payload
+//! ack     := pkid:u16
+//! ```
+//!
+//! All integers are little endian.
+
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::message::Notification;
+
+pub use self::reader::{decode_record, Reader};
+pub use self::recorder::{Recorder, Rotation};
+
+mod reader;
+mod recorder;
+
+pub const MAGIC: &[u8; 7] = b"MQTTCAP";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 8;
+/// Longest record accepted: the largest MQTT packet, with room for the fields
+/// of the record.
+pub const MAX_RECORD_LEN: u32 = 268_435_455 + 64;
+
+const KIND_PUBLISH: u8 = 0;
+const KIND_RECONNECTION: u8 = 1;
+const KIND_DISCONNECTION: u8 = 2;
+const KIND_PUBACK: u8 = 3;
+const KIND_PUBREC: u8 = 4;
+const KIND_PUBREL: u8 = 5;
+const KIND_PUBCOMP: u8 = 6;
+const KIND_SUBACK: u8 = 7;
+
+/// A notification, with the time it was received.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub timestamp: SystemTime,
+    pub notification: Notification,
+}
+
+impl Record {
+    /// Record `notification`, using the time a message was received or now
+    /// for every other notification.
+    pub fn new(notification: Notification) -> Record {
+        let timestamp = match &notification {
+            Notification::Publish(msg) => msg.timestamp,
+            _ => SystemTime::now(),
+        };
+
+        Record {
+            timestamp,
+            notification,
+        }
+    }
+}
+
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reproduces the timing of a sequence of records.
+pub struct Pacer {
+    speed: f64,
+    start: Option<(SystemTime, Instant)>,
+}
+
+impl Pacer {
+    /// With a `speed` of `1.0` the original timing is preserved, `2.0` is
+    /// twice as fast.
+    pub fn new(speed: f64) -> Pacer {
+        Pacer { speed, start: None }
+    }
+
+    /// When a record with `timestamp` is due, relative to the first record
+    /// seen since the pacer was created or reset.
+    fn due(&mut self, timestamp: SystemTime) -> Instant {
+        let (first_timestamp, started) = *self.start.get_or_insert((timestamp, Instant::now()));
+
+        let offset = timestamp
+            .duration_since(first_timestamp)
+            .unwrap_or_default()
+            .div_f64(self.speed);
+
+        started + offset
+    }
+
+    /// Sleep until a record with `timestamp` is due.
+    pub fn wait(&mut self, timestamp: SystemTime) {
+        let due = self.due(timestamp);
+        if let Some(delay) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(delay);
+        }
+    }
+
+    /// Sleep until a record with `timestamp` is due, unless `stop` is set in
+    /// the meantime: it is checked every 100 ms. Return `false` if it was.
+    pub fn wait_unless(&mut self, timestamp: SystemTime, stop: &AtomicBool) -> bool {
+        let due = self.due(timestamp);
+
+        loop {
+            if stop.load(Ordering::Relaxed) {
+                return false;
+            }
+
+            match due.checked_duration_since(Instant::now()) {
+                Some(delay) if delay > Duration::from_secs(0) => {
+                    thread::sleep(delay.min(STOP_CHECK_INTERVAL))
+                }
+                _ => return true,
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.start = None;
+    }
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// Read the header of a capture from `r`, checking that the format is
+/// supported.
+pub fn read_header<R: Read>(r: &mut R) -> io::Result<()> {
+    let mut header = [0; HEADER_LEN];
+    r.read_exact(&mut header)?;
+
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(invalid_data("not a capture file"));
+    }
+
+    if header[MAGIC.len()] != VERSION {
+        return Err(invalid_data("unsupported capture version"));
+    }
+
+    Ok(())
+}
+
+pub fn write_header<W: Write>(w: &mut W) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])
+}
+
+fn encode_body(notification: &Notification, buf: &mut Vec<u8>) -> bool {
+    let ack = |buf: &mut Vec<u8>, kind: u8, pkid: u16| {
+        buf.push(kind);
+        buf.extend_from_slice(&pkid.to_le_bytes());
+    };
+
+    match notification {
+        Notification::Publish(msg) => {
+            buf.push(KIND_PUBLISH);
+            buf.push(msg.qos.to_u8());
+            buf.push(msg.retain as u8);
+            buf.extend_from_slice(&(msg.topic.len() as u16).to_le_bytes());
+            buf.extend_from_slice(msg.topic.as_bytes());
+            buf.extend_from_slice(&msg.payload);
+        }
+        Notification::Reconnection => buf.push(KIND_RECONNECTION),
+        Notification::Disconnection => buf.push(KIND_DISCONNECTION),
+        Notification::PubAck(pkid) => ack(buf, KIND_PUBACK, *pkid),
+        Notification::PubRec(pkid) => ack(buf, KIND_PUBREC, *pkid),
+        Notification::PubRel(pkid) => ack(buf, KIND_PUBREL, *pkid),
+        Notification::PubComp(pkid) => ack(buf, KIND_PUBCOMP, *pkid),
+        Notification::SubAck(pkid) => ack(buf, KIND_SUBACK, *pkid),
+        Notification::Published(..) | Notification::Error(_) | Notification::EndOfInput => {
+            return false
+        }
+    }
+
+    true
+}
+
+/// Encode `record`, including its length prefix. Returns `None` for
+/// notifications which are not part of the traffic, like engine errors.
+/// See [`decode_record`] for the opposite.
+pub fn encode_record(record: &Record) -> Option<Vec<u8>> {
+    let since_epoch = record
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+
+    let mut buf = vec![0; 4];
+    buf.extend_from_slice(&since_epoch.as_secs().to_le_bytes());
+    buf.extend_from_slice(&since_epoch.subsec_nanos().to_le_bytes());
+
+    if !encode_body(&record.notification, &mut buf) {
+        return None;
+    }
+
+    let len = (buf.len() - 4) as u32;
+    buf[..4].copy_from_slice(&len.to_le_bytes());
+
+    Some(buf)
+}
+
+/// Write `record` to `w`, returning the number of bytes written.
+pub fn write_record<W: Write>(w: &mut W, record: &Record) -> io::Result<usize> {
+    match encode_record(record) {
+        Some(buf) => w.write_all(&buf).map(|_| buf.len()),
+        None => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+
+    #[test]
+    fn header() {
+        let mut buf = Vec::new();
+        write_header(&mut buf).unwrap();
+        assert_eq!(buf, b"MQTTCAP\x01");
+        assert_eq!(buf.len(), HEADER_LEN);
+    }
+
+    #[test]
+    fn stop_waiting() {
+        let stop = AtomicBool::new(false);
+        let mut pacer = Pacer::new(1.0);
+        assert!(pacer.wait_unless(UNIX_EPOCH, &stop));
+
+        let started = Instant::now();
+        thread::scope(|s| {
+            s.spawn(|| {
+                thread::sleep(Duration::from_millis(50));
+                stop.store(true, Ordering::Relaxed);
+            });
+            assert!(!pacer.wait_unless(UNIX_EPOCH + Duration::from_secs(60), &stop));
+        });
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn encode_publish() {
+        let msg = Message::new("a/b", "ciao")
+            .retain(true)
+            .timestamp(UNIX_EPOCH + Duration::new(1, 2));
+        let buf = encode_record(&Record::new(Notification::Publish(msg))).unwrap();
+
+        assert_eq!(&buf[0..4], &24u32.to_le_bytes());
+        assert_eq!(&buf[4..12], &1u64.to_le_bytes());
+        assert_eq!(&buf[12..16], &2u32.to_le_bytes());
+        assert_eq!(&buf[16..19], &[KIND_PUBLISH, 0, 1]);
+        assert_eq!(&buf[19..21], &3u16.to_le_bytes());
+        assert_eq!(&buf[21..], b"a/bciao");
+    }
+}
@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::message::{Message, Notification, QoS};
+
+use super::*;
+
+/// Reads the records of a capture file, in order.
+///
+/// Records of unknown kind, and malformed ones, are skipped. A record
+/// truncated by the end of the file ends the iteration, as if the file ended
+/// just before it. So does an error, as the next records can't be found
+/// anymore.
+pub struct Reader<R: Read> {
+    inner: R,
+    failed: bool,
+}
+
+impl Reader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Reader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Reader<R> {
+    /// Read the capture header from `inner`, checking that the format is
+    /// supported.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        read_header(&mut inner)?;
+        Ok(Reader {
+            inner,
+            failed: false,
+        })
+    }
+
+    /// Read the next record, returning `Ok(None)` at the end of the file.
+    fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut len = [0; 4];
+        match self.inner.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let len = u32::from_le_bytes(len);
+        if len > MAX_RECORD_LEN {
+            return Err(invalid_data("record too long"));
+        }
+
+        // Only allocate what the file holds, in case it is truncated.
+        let mut buf = vec![];
+        (&mut self.inner)
+            .take(u64::from(len))
+            .read_to_end(&mut buf)?;
+
+        match buf.len() == len as usize {
+            true => Ok(Some(buf)),
+            false => Ok(None),
+        }
+    }
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> Option<u16> {
+    buf.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn decode_publish(body: &[u8]) -> Option<Message> {
+    let qos = QoS::from_u8(*body.first()?)?;
+    let retain = *body.get(1)? != 0;
+    let topic_len = u16_at(body, 2)? as usize;
+    let topic = body.get(4..4 + topic_len)?;
+    let payload = &body[4 + topic_len..];
+
+    Some(Message {
+        topic: String::from_utf8_lossy(topic).into_owned(),
+        qos,
+        retain,
+        payload: Arc::new(payload.to_vec()),
+        timestamp: UNIX_EPOCH,
+    })
+}
+
+/// Decode a record, without its length prefix. Returns `None` if the record
+/// kind is unknown or the record is malformed.
+pub fn decode_record(buf: &[u8]) -> Option<Record> {
+    let mut secs = [0; 8];
+    secs.copy_from_slice(buf.get(0..8)?);
+    let mut nanos = [0; 4];
+    nanos.copy_from_slice(buf.get(8..12)?);
+
+    // Checked, as a corrupt record could have any timestamp.
+    let timestamp = Duration::from_secs(u64::from_le_bytes(secs))
+        .checked_add(Duration::from_nanos(u64::from(u32::from_le_bytes(nanos))))
+        .and_then(|elapsed| UNIX_EPOCH.checked_add(elapsed))?;
+
+    let kind = *buf.get(12)?;
+    let body = &buf[13..];
+
+    let notification = match kind {
+        KIND_PUBLISH => Notification::Publish(decode_publish(body)?.timestamp(timestamp)),
+        KIND_RECONNECTION => Notification::Reconnection,
+        KIND_DISCONNECTION => Notification::Disconnection,
+        KIND_PUBACK => Notification::PubAck(u16_at(body, 0)?),
+        KIND_PUBREC => Notification::PubRec(u16_at(body, 0)?),
+        KIND_PUBREL => Notification::PubRel(u16_at(body, 0)?),
+        KIND_PUBCOMP => Notification::PubComp(u16_at(body, 0)?),
+        KIND_SUBACK => Notification::SubAck(u16_at(body, 0)?),
+        _ => return None,
+    };
+
+    Some(Record {
+        timestamp,
+        notification,
+    })
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            match self.read_record() {
+                Ok(Some(buf)) => {
+                    if let Some(record) = decode_record(&buf) {
+                        return Some(Ok(record));
+                    }
+                }
+                Ok(None) => return None,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(records: &[Record]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_header(&mut buf).unwrap();
+        for record in records {
+            write_record(&mut buf, record).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn roundtrip() {
+        let msg = Message::new("a/b", "ciao")
+            .qos(QoS::AtLeastOnce)
+            .retain(true)
+            .timestamp(UNIX_EPOCH + Duration::new(1_500_000_000, 123_456_789));
+        let records = vec![
+            Record::new(Notification::Publish(msg)),
+            Record::new(Notification::SubAck(42)),
+        ];
+
+        let buf = capture(&records);
+        let read: Vec<_> = Reader::new(buf.as_slice())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn truncated_record_ends_the_capture() {
+        let records = vec![
+            Record::new(Notification::Reconnection),
+            Record::new(Notification::Publish(Message::new("a/b", "ciao"))),
+        ];
+
+        let buf = capture(&records);
+        let truncated = &buf[..buf.len() - 2];
+        let read: Vec<_> = Reader::new(truncated).unwrap().collect();
+
+        assert_eq!(read.len(), 1);
+    }
+
+    #[test]
+    fn reject_other_files() {
+        assert!(Reader::new(&b"GARBAGE!"[..]).is_err());
+    }
+
+    #[test]
+    fn reject_huge_records() {
+        let mut buf = capture(&[]);
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        buf.extend_from_slice(&[0; 16]);
+
+        let read: Vec<_> = Reader::new(buf.as_slice()).unwrap().collect();
+        assert_eq!(read.len(), 1);
+        assert!(read[0].is_err());
+    }
+
+    #[test]
+    fn skip_bogus_timestamps() {
+        let mut buf = capture(&[]);
+        let mut record = u64::MAX.to_le_bytes().to_vec();
+        record.extend_from_slice(&2_000_000_000u32.to_le_bytes());
+        record.push(KIND_RECONNECTION);
+        buf.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&record);
+        let valid = Record::new(Notification::Disconnection);
+        write_record(&mut buf, &valid).unwrap();
+
+        let read: Vec<_> = Reader::new(buf.as_slice())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read, vec![valid]);
+    }
+}
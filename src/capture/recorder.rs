@@ -0,0 +1,188 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{read_header, write_header, write_record, Record};
+use crate::engine::Error;
+
+/// When a capture file is closed and a new one is started.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rotation {
+    pub max_size: Option<u64>,
+    pub max_age: Option<Duration>,
+}
+
+/// Writes records to a capture file, rotating it according to a
+/// [`Rotation`] policy.
+///
+/// The capture is always written to the same path. When it is rotated, the
+/// current file is renamed by appending the first free `.N` suffix to it.
+pub struct Recorder {
+    path: PathBuf,
+    rotation: Rotation,
+    file: BufWriter<File>,
+    size: u64,
+    opened: Instant,
+    last_flush: Instant,
+}
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Open the capture at `path` to append records to it, creating it if it
+/// doesn't exist. An existing file must be a capture in the same format.
+fn open(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut size = file.metadata()?.len();
+
+    if size > 0 {
+        read_header(&mut File::open(path)?)?;
+    }
+
+    let mut file = BufWriter::new(file);
+
+    if size == 0 {
+        write_header(&mut file)?;
+        size = super::HEADER_LEN as u64;
+    }
+
+    Ok((file, size))
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    (1..)
+        .map(|n| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+impl Recorder {
+    /// Start recording to `path`. If the file already exists, new records are
+    /// appended to it, as long as it is a capture in the same format.
+    pub fn create<P: AsRef<Path>>(path: P, rotation: Rotation) -> Result<Recorder, Error> {
+        let path = path.as_ref().to_path_buf();
+        let (file, size) =
+            open(&path).map_err(|e| Error::Capture(format!("{}: {}", path.display(), e)))?;
+        let now = Instant::now();
+
+        Ok(Recorder {
+            path,
+            rotation,
+            file,
+            size,
+            opened: now,
+            last_flush: now,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn needs_rotation(&self) -> bool {
+        let too_big = self
+            .rotation
+            .max_size
+            .is_some_and(|max_size| self.size >= max_size);
+        let too_old = self
+            .rotation
+            .max_age
+            .is_some_and(|max_age| self.opened.elapsed() >= max_age);
+
+        too_big || too_old
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        fs::rename(&self.path, rotated_path(&self.path))?;
+
+        let (file, size) = open(&self.path)?;
+        self.file = file;
+        self.size = size;
+        self.opened = Instant::now();
+
+        Ok(())
+    }
+
+    pub fn record(&mut self, record: &Record) -> io::Result<()> {
+        if self.needs_rotation() {
+            self.rotate()?;
+        }
+
+        self.size += write_record(&mut self.file, record)? as u64;
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, Notification};
+
+    #[test]
+    fn rotate_when_too_big() {
+        let dir = std::env::temp_dir().join(format!("mqtt-analyzer-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("capture");
+
+        let rotation = Rotation {
+            max_size: Some(32),
+            max_age: None,
+        };
+        let mut recorder = Recorder::create(&path, rotation).unwrap();
+        let record = Record::new(Notification::Publish(Message::new("a/b", "ciao")));
+
+        recorder.record(&record).unwrap();
+        assert!(!dir.join("capture.1").exists());
+        recorder.record(&record).unwrap();
+        recorder.flush().unwrap();
+
+        assert_eq!(fs::metadata(dir.join("capture.1")).unwrap().len(), 8 + 28);
+        assert_eq!(fs::metadata(&path).unwrap().len(), 8 + 28);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn append_to_captures_only() {
+        let dir = std::env::temp_dir().join(format!("mqtt-analyzer-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("capture");
+
+        let record = Record::new(Notification::Publish(Message::new("a/b", "ciao")));
+        let mut recorder = Recorder::create(&path, Rotation::default()).unwrap();
+        recorder.record(&record).unwrap();
+        recorder.flush().unwrap();
+        drop(recorder);
+
+        let mut recorder = Recorder::create(&path, Rotation::default()).unwrap();
+        recorder.record(&record).unwrap();
+        recorder.flush().unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 8 + 2 * 28);
+
+        fs::write(&path, "not a capture").unwrap();
+        assert!(matches!(
+            Recorder::create(&path, Rotation::default()),
+            Err(Error::Capture(_))
+        ));
+        fs::write(&path, b"MQTTCAP\x02").unwrap();
+        assert!(Recorder::create(&path, Rotation::default()).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"MQTTCAP\x02");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
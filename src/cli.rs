@@ -1,31 +1,73 @@
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 use rumqtt::{MqttOptions, SecurityOptions};
 use uuid::Uuid;
 
-use crate::format::{MessageFormat, PayloadFormat};
+use mqtt_analyzer::capture::Rotation;
+use mqtt_analyzer::config::Config;
+use mqtt_analyzer::filter::Filter;
+use mqtt_analyzer::format::{MessageFormat, PayloadFormat};
+use mqtt_analyzer::history::Capacity;
+use mqtt_analyzer::pcap;
+use mqtt_analyzer::replay::{self, Rewrite};
+use mqtt_analyzer::stats::SortKey;
 
 fn generate_random_client_id() -> String {
     Uuid::new_v4().to_string()
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Mode {
     Subscriptions,
     Stream,
     Retained,
+    Stats,
+    Tree,
+    Publish,
+    Charts,
+    Dashboard,
+}
+
+impl Mode {
+    /// The modes, in the order of the tabs of the TUI.
+    pub const ALL: &'static [Mode] = &[
+        Mode::Subscriptions,
+        Mode::Stream,
+        Mode::Retained,
+        Mode::Stats,
+        Mode::Tree,
+        Mode::Publish,
+        Mode::Charts,
+        Mode::Dashboard,
+    ];
+
+    /// The name of the mode on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Subscriptions => "subs",
+            Mode::Stream => "stream",
+            Mode::Retained => "retained",
+            Mode::Stats => "stats",
+            Mode::Tree => "tree",
+            Mode::Publish => "publish",
+            Mode::Charts => "charts",
+            Mode::Dashboard => "dashboard",
+        }
+    }
 }
 
 impl FromStr for Mode {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "subs" => Ok(Mode::Subscriptions),
-            "stream" => Ok(Mode::Stream),
-            "retained" => Ok(Mode::Retained),
-            _ => Err(()),
-        }
+        Mode::ALL
+            .iter()
+            .find(|mode| mode.name() == s)
+            .cloned()
+            .ok_or(())
     }
 }
 
@@ -33,8 +75,118 @@ pub struct Options {
     pub mqtt: MqttOptions,
     pub subscriptions: Vec<String>,
     pub format: MessageFormat,
+    pub filter: Option<Filter>,
     pub tui: bool,
     pub mode: Mode,
+    pub sort: SortKey,
+    pub history: Capacity,
+    pub record: Option<String>,
+    pub rotation: Rotation,
+    pub input: Option<String>,
+    pub speed: Option<f64>,
+    pub pcap: pcap::Options,
+    pub replay: Option<Replay>,
+    /// Settings of the TUI, empty without it.
+    pub config: Config,
+    /// Where the settings changed in the TUI, like the pins, are saved.
+    pub config_path: Option<PathBuf>,
+    /// Restore the last session of the TUI with the same broker.
+    pub restore: bool,
+    /// Whether `--mode` and `--format` were given, instead of their default
+    /// values: they win over the restored session.
+    pub mode_given: bool,
+    pub format_given: bool,
+}
+
+/// Publish a capture to the broker, instead of analyzing its messages.
+pub struct Replay {
+    pub path: String,
+    pub options: replay::Options,
+}
+
+fn parse_rewrite(s: &str) -> Option<Rewrite> {
+    let mut parts = s.splitn(2, '=');
+    let from = parts.next()?.to_string();
+    let to = parts.next()?.to_string();
+    Some(Rewrite { from, to })
+}
+
+fn is_rewrite(s: String) -> Result<(), String> {
+    parse_rewrite(&s)
+        .map(|_| ())
+        .ok_or_else(|| format!("{:?} isn't like FROM=TO", s))
+}
+
+fn is_replay_speed(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed >= 0.0 && speed.is_finite() => Ok(()),
+        _ => Err(format!("{:?} isn't a factor, like 0.5 or 2, or 0", s)),
+    }
+}
+
+fn parse_replay(matches: &clap::ArgMatches) -> Replay {
+    let speed = matches
+        .value_of("speed")
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|speed| *speed > 0.0);
+
+    let filters = matches
+        .values_of("topic")
+        .map_or(vec![], |values| values.map(|s| s.to_string()).collect());
+
+    Replay {
+        path: matches.value_of("capture").unwrap().to_string(),
+        options: replay::Options {
+            speed,
+            rewrite: matches.value_of("rewrite").and_then(parse_rewrite),
+            filters,
+            repeat: matches.is_present("loop"),
+        },
+    }
+}
+
+/// Parse a size in bytes, with an optional `K`, `M` or `G` suffix.
+fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, multiplier) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 1 << 10),
+        'M' => (&s[..s.len() - 1], 1 << 20),
+        'G' => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+fn is_count(s: String) -> Result<(), String> {
+    s.parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| format!("{:?} isn't a number", s))
+}
+
+fn is_seconds(s: String) -> Result<(), String> {
+    s.parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| format!("{:?} isn't a number of seconds", s))
+}
+
+fn is_port(s: String) -> Result<(), String> {
+    s.parse::<u16>()
+        .map(|_| ())
+        .map_err(|_| format!("{:?} isn't a TCP port", s))
+}
+
+fn is_speed(s: String) -> Result<(), String> {
+    match s.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(()),
+        _ => Err(format!("{:?} isn't a positive factor, like 0.5 or 2", s)),
+    }
+}
+
+fn is_size(s: String) -> Result<(), String> {
+    parse_size(&s)
+        .map(|_| ())
+        .ok_or_else(|| format!("{:?} isn't a size, like 512K or 64M", s))
 }
 
 pub fn parse_options() -> Options {
@@ -92,9 +244,15 @@ pub fn parse_options() -> Options {
             .value_name("FORMAT")
             .help("The format to use to show payload. If text is non valid utf8, it falls back to hex.")
             .takes_value(true)
-            .possible_values(&["hex", "base64", "text", "escape", "auto"])
+            .possible_values(&["hex", "base64", "text", "escape", "json", "auto"])
             .default_value("auto")
         )
+        .arg(Arg::with_name("filter")
+            .long("filter")
+            .value_name("EXPR")
+            .help("Only show the messages matching EXPR, like 'sensors/# and not (payload~\"^error\" or size>100)'")
+            .takes_value(true)
+        )
         .arg(Arg::with_name("tui")
             .long("tui")
             .help("Enable Text User Interface")
@@ -103,9 +261,127 @@ pub fn parse_options() -> Options {
             .long("mode")
             .help("Enable Text User Interface")
             .takes_value(true)
-            .possible_values(&["subs", "stream", "retained", "stats"])
+            .possible_values(&["subs", "stream", "retained", "stats", "tree", "publish", "charts", "dashboard"])
             .default_value("subs")
         )
+        .arg(Arg::with_name("sort")
+            .long("sort")
+            .value_name("COLUMN")
+            .help("The column the statistics are sorted by, in stats mode")
+            .takes_value(true)
+            .possible_values(&["topic", "count", "rate", "bytes", "size", "last"])
+            .default_value("topic")
+        )
+        .arg(Arg::with_name("history")
+            .long("history")
+            .value_name("COUNT")
+            .help("The number of messages kept in the stream history of the TUI. 0 means unlimited")
+            .takes_value(true)
+            .validator(is_count)
+            .default_value("1000")
+        )
+        .arg(Arg::with_name("history_memory")
+            .long("history-memory")
+            .value_name("SIZE")
+            .help("The memory budget of the stream history of the TUI, in bytes. K, M and G suffixes are accepted")
+            .takes_value(true)
+            .validator(is_size)
+        )
+        .arg(Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .help("Read the settings of the TUI, like the key bindings, from FILE instead of ~/.config/mqtt-analyzer/config.toml")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("no_restore")
+            .long("no-restore")
+            .help("Start the TUI from scratch, instead of restoring the subscriptions, tabs, filters, formats and panes of the last session with the same broker")
+        )
+        .arg(Arg::with_name("record")
+            .long("record")
+            .value_name("FILE")
+            .help("Record every notification to a capture file. If the file exists, new records are appended")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("record_max_size")
+            .long("record-max-size")
+            .value_name("SIZE")
+            .help("Rotate the capture file when it is bigger than SIZE bytes. K, M and G suffixes are accepted")
+            .takes_value(true)
+            .validator(is_size)
+            .requires("record")
+        )
+        .arg(Arg::with_name("record_max_age")
+            .long("record-max-age")
+            .value_name("SECONDS")
+            .help("Rotate the capture file after SECONDS seconds")
+            .takes_value(true)
+            .validator(is_seconds)
+            .requires("record")
+        )
+        .arg(Arg::with_name("input")
+            .long("input")
+            .value_name("FILE")
+            .help("Read notifications from a capture file instead of connecting to the broker. Subscribes to # if no topic is given")
+            .takes_value(true)
+        )
+        .arg(Arg::with_name("speed")
+            .long("speed")
+            .value_name("FACTOR")
+            .help("Pace the notifications read from the input file, 1 is the original speed. Without it, they are read as fast as possible")
+            .takes_value(true)
+            .validator(is_speed)
+            .requires("input")
+        )
+        .arg(Arg::with_name("pcap_port")
+            .long("pcap-port")
+            .value_name("PORT")
+            .help("When the input is a pcap or pcapng file, import the MQTT connections on this TCP port [default: 1883]")
+            .takes_value(true)
+            .validator(is_port)
+            .multiple(true)
+            .requires("input")
+        )
+        .arg(Arg::with_name("pcap_control")
+            .long("pcap-control")
+            .help("When the input is a pcap or pcapng file, import acknowledgments and connections too")
+            .requires("input")
+        )
+        .subcommand(SubCommand::with_name("replay")
+            .about("Publish the messages of a capture file to the broker")
+            .arg(Arg::with_name("capture")
+                .value_name("FILE")
+                .help("The capture file to replay")
+                .required(true)
+            )
+            .arg(Arg::with_name("speed")
+                .long("speed")
+                .value_name("FACTOR")
+                .help("Speed factor, 1 preserves the original timing. 0 publishes as fast as possible")
+                .takes_value(true)
+                .validator(is_replay_speed)
+                .default_value("1")
+            )
+            .arg(Arg::with_name("rewrite")
+                .long("rewrite")
+                .value_name("FROM=TO")
+                .help("Replace the FROM topic prefix with TO. FROM is made of whole topic levels")
+                .takes_value(true)
+                .validator(is_rewrite)
+            )
+            .arg(Arg::with_name("topic")
+                .short("t")
+                .long("topic")
+                .value_name("TOPIC")
+                .help("Only publish messages matching this topic filter")
+                .takes_value(true)
+                .multiple(true)
+            )
+            .arg(Arg::with_name("loop")
+                .long("loop")
+                .help("Start again at the end of the capture, until interrupted")
+            )
+        )
         .get_matches();
 
     let hostname = matches.value_of("hostname").unwrap();
@@ -134,21 +410,99 @@ pub fn parse_options() -> Options {
         .and_then(|s| s.parse::<PayloadFormat>().ok())
         .unwrap_or(PayloadFormat::Hex);
 
+    let filter = matches.value_of("filter").map(|s| {
+        s.parse::<Filter>().unwrap_or_else(|e| {
+            let description = format!("Invalid filter {:?}: {}", s, e);
+            clap::Error::with_description(&description, clap::ErrorKind::InvalidValue).exit()
+        })
+    });
+
     let mode = matches
         .value_of("mode")
         .and_then(|s| s.parse::<Mode>().ok())
         .unwrap_or(Mode::Subscriptions);
 
+    let sort = matches
+        .value_of("sort")
+        .and_then(|s| s.parse::<SortKey>().ok())
+        .unwrap_or(SortKey::Topic);
+
+    let history = Capacity {
+        count: matches
+            .value_of("history")
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|count| *count > 0),
+        bytes: matches
+            .value_of("history_memory")
+            .and_then(parse_size)
+            .map(|size| size as usize),
+    };
+
     let tui = matches.is_present("tui");
 
-    let mut message_format = MessageFormat::default();
-    message_format.payload_format = payload_format;
+    let record = matches.value_of("record").map(str::to_string);
+
+    let config_path = match matches.value_of("config") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Config::default_path(),
+    };
+
+    let config = if tui {
+        let config = match matches.value_of("config") {
+            Some(path) => Config::open(path),
+            None => Config::open_default(),
+        };
+        config.unwrap_or_else(|e| {
+            clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue).exit()
+        })
+    } else {
+        Config::default()
+    };
+
+    let replay = matches.subcommand_matches("replay").map(parse_replay);
+
+    let input = matches.value_of("input").map(str::to_string);
+
+    let speed = matches
+        .value_of("speed")
+        .and_then(|s| s.parse::<f64>().ok());
+
+    let mut pcap_options = pcap::Options {
+        control: matches.is_present("pcap_control"),
+        ..pcap::Options::default()
+    };
+
+    if let Some(ports) = matches.values_of("pcap_port") {
+        pcap_options.ports = ports.filter_map(|s| s.parse::<u16>().ok()).collect();
+    }
+
+    let rotation = Rotation {
+        max_size: matches.value_of("record_max_size").and_then(parse_size),
+        max_age: matches
+            .value_of("record_max_age")
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs),
+    };
 
     Options {
         mqtt: MqttOptions::new(client_id, hostname, port).set_security_opts(security_options),
         subscriptions,
-        format: message_format,
+        format: MessageFormat { payload_format },
+        filter,
         tui,
         mode,
+        sort,
+        history,
+        record,
+        rotation,
+        input,
+        speed,
+        pcap: pcap_options,
+        replay,
+        config,
+        config_path,
+        restore: !matches.is_present("no_restore"),
+        mode_given: matches.occurrences_of("mode") > 0,
+        format_given: matches.occurrences_of("format") > 0,
     }
 }
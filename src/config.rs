@@ -0,0 +1,207 @@
+//! Settings read from the configuration file.
+//!
+//! The file is written in TOML, like:
+//!
+//! ```toml
+//! keymap = "vim"
+//!
+//! [keys.stream]
+//! space = "pause"
+//!
+//! [dashboard]
+//! pins = ["sensors/temp", "sensors/hum"]
+//! stale = 30
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    Io(PathBuf, String),
+    Write(PathBuf, String),
+    Parse(String),
+    InvalidValue(String, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(path, reason) => write!(f, "Can't read {}: {}", path.display(), reason),
+            Error::Write(path, reason) => {
+                write!(f, "Can't write {}: {}", path.display(), reason)
+            }
+            Error::Parse(reason) => write!(f, "Invalid configuration: {}", reason),
+            Error::InvalidValue(field, value) => {
+                write!(f, "Invalid value {:?} for {}", value, field)
+            }
+        }
+    }
+}
+
+impl Fail for Error {}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Preset of the key bindings of the TUI, `default` or `vim`.
+    pub keymap: Option<String>,
+    /// Key bindings replacing the ones of the preset, by context, then by
+    /// key. The value is the name of an action, or `none` to unbind the key.
+    pub keys: BTreeMap<String, BTreeMap<String, String>>,
+    pub dashboard: Dashboard,
+}
+
+/// Settings of the Dashboard tab of the TUI.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dashboard {
+    /// Topics shown in the tab.
+    pub pins: Vec<String>,
+    /// Seconds without a message after which a pinned topic is highlighted.
+    pub stale: u64,
+}
+
+impl Default for Dashboard {
+    fn default() -> Dashboard {
+        Dashboard {
+            pins: vec![],
+            stale: 60,
+        }
+    }
+}
+
+impl Config {
+    /// Where the configuration is looked for when no file is given:
+    /// `$XDG_CONFIG_HOME/mqtt-analyzer/config.toml`, falling back to
+    /// `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(dir.join("mqtt-analyzer").join("config.toml"))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Config, Error> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).map_err(|e| Error::Io(path.to_owned(), e.to_string()))?;
+
+        text.parse()
+    }
+
+    /// Write the pins of the dashboard to the configuration file at `path`.
+    /// Only the `pins` of the `[dashboard]` table change: the rest of the
+    /// file, comments and layout included, stays as it is.
+    pub fn save_pins<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let error = |e: &dyn fmt::Display| Error::Write(path.to_owned(), e.to_string());
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(error(&e)),
+        };
+        let mut document: toml_edit::DocumentMut = text.parse().map_err(|e| error(&e))?;
+
+        let pins: toml_edit::Array = self.dashboard.pins.iter().map(String::as_str).collect();
+        document
+            .entry("dashboard")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| error(&"dashboard is not a table"))?
+            .insert("pins", toml_edit::value(pins));
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| error(&e))?;
+        }
+        fs::write(path, document.to_string()).map_err(|e| error(&e))
+    }
+
+    /// The configuration in the default file, if it exists.
+    pub fn open_default() -> Result<Config, Error> {
+        match Config::default_path() {
+            Some(path) => match Config::open(&path) {
+                Err(Error::Io(_, _)) if !path.exists() => Ok(Config::default()),
+                result => result,
+            },
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+/// Where the state kept across sessions is stored:
+/// `$XDG_STATE_HOME/mqtt-analyzer`, falling back to `~/.local/state`.
+pub fn state_dir() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("state"),
+    };
+
+    Some(dir.join("mqtt-analyzer"))
+}
+
+impl std::str::FromStr for Config {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| Error::Parse(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_bindings() {
+        let config: Config = "keymap = \"vim\"\n[keys.stream]\nspace = \"pause\"\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(config.keymap.as_deref(), Some("vim"));
+        assert_eq!(config.keys["stream"]["space"], "pause");
+
+        assert_eq!("".parse::<Config>().unwrap(), Config::default());
+        assert!("keymaps = 1".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn save_pins() {
+        let path = env::temp_dir()
+            .join(format!("mqtt-analyzer-{}", std::process::id()))
+            .join("config.toml");
+
+        let mut config = Config::default();
+        config.dashboard.pins.push("a/b".to_string());
+        config.save_pins(&path).unwrap();
+        assert_eq!(Config::open(&path).unwrap(), config);
+
+        let text = "# Keys\nkeymap = \"vim\"\n\n[dashboard]\nstale = 5 # seconds\npins = []\n";
+        fs::write(&path, text).unwrap();
+        let mut config = Config::open(&path).unwrap();
+        assert_eq!(config.dashboard.stale, 5);
+        assert!(config.dashboard.pins.is_empty());
+
+        config.dashboard.pins.push("a/b".to_string());
+        config.save_pins(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            text.replace("[]", "[\"a/b\"]")
+        );
+        assert_eq!(Config::open(&path).unwrap(), config);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
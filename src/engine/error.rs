@@ -0,0 +1,46 @@
+use std::fmt;
+
+use failure::Fail;
+
+use crate::message::Message;
+
+/// Failures reported by the engine while it keeps running.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    InvalidFilter(String),
+    InvalidTopic(String),
+    /// Messages can't be published without a broker.
+    Offline,
+    ClientClosed(String),
+    /// A thread panicked while holding the lock of the subscriptions or of
+    /// the recorder.
+    LockPoisoned,
+    Capture(String),
+    /// The engine thread is gone, and can't handle events anymore.
+    Stopped,
+    /// A message sent with `Event::Publish` couldn't be published.
+    Publish(Message, Box<Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidFilter(filter) => write!(f, "Invalid topic filter: {:?}", filter),
+            Error::InvalidTopic(topic) => write!(f, "Invalid topic: {:?}", topic),
+            Error::Offline => write!(f, "Not connected to a broker"),
+            Error::ClientClosed(reason) => write!(f, "Client closed: {}", reason),
+            Error::LockPoisoned => write!(f, "Lock poisoned by a thread that panicked"),
+            Error::Capture(reason) => write!(f, "Capture failed: {}", reason),
+            Error::Stopped => write!(f, "Engine stopped"),
+            Error::Publish(_, reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Fail for Error {}
+
+impl From<rumqtt::ClientError> for Error {
+    fn from(e: rumqtt::ClientError) -> Error {
+        Error::ClientClosed(e.to_string())
+    }
+}
@@ -1,79 +1,598 @@
+//! The engine connects to the broker, keeps track of subscriptions and
+//! forwards everything it receives as [`Notification`]s.
+//!
+//! Instead of a broker, the engine can also read historic notifications from
+//! an offline [`Input`], like a capture file.
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
+use rumqtt::{MqttClient, MqttOptions};
+
+use crate::capture::{Pacer, Record, Recorder};
+use crate::message::{Message, Notification, QoS};
+use crate::topic;
+
+pub use self::error::Error;
+
+mod error;
+
 pub enum Event {
-    Subscribe(String),
+    Subscribe(String, QoS),
     Unsubscribe(String),
+    /// Publish a message to the broker. A `Notification::Published` follows
+    /// when the message has been handed to the client, with the packet
+    /// identifier its acknowledgments will have.
+    Publish(Message),
 }
 
-type Subscriptions = std::collections::BTreeSet<String>;
-type SharedSubscriptions = Arc<RwLock<Subscriptions>>;
+/// Topic filters subscribed to, with their QoS.
+pub type Subscriptions = std::collections::BTreeMap<String, QoS>;
+pub type SharedSubscriptions = Arc<RwLock<Subscriptions>>;
+/// Where the notifications are recorded, if they are.
+pub type SharedRecorder = Arc<Mutex<Option<Recorder>>>;
+
+/// Offline source of records, like a capture file.
+pub type Records = Box<dyn Iterator<Item = io::Result<Record>> + Send>;
+
+/// Where the engine gets its notifications from.
+pub enum Input {
+    /// Connect to a broker.
+    Mqtt(Box<MqttOptions>),
+    /// Read historic records. Only the messages matching a subscription are
+    /// forwarded. With a `speed`, records are paced according to their
+    /// timestamps (`1.0` is the original speed), otherwise they are forwarded
+    /// as fast as possible.
+    Offline {
+        records: Records,
+        speed: Option<f64>,
+    },
+}
 
 pub struct Engine {
-    pub notifications: rumqtt::Receiver<rumqtt::Notification>,
+    pub notifications: crossbeam_channel::Receiver<Notification>,
     pub subscriptions: SharedSubscriptions,
+    recorder: SharedRecorder,
+    dropped: Arc<AtomicU64>,
     tx: Sender<Event>,
     #[allow(dead_code)]
     thread: thread::JoinHandle<()>,
+    #[allow(dead_code)]
+    forward_thread: thread::JoinHandle<()>,
+}
+
+/// Configure and start an [`Engine`].
+///
+/// ```no_run
+/// use mqtt_analyzer::engine::EngineBuilder;
+/// use rumqtt::MqttOptions;
+///
+/// let engine = EngineBuilder::new(MqttOptions::new("analyzer", "localhost", 1883))
+///     .subscribe("#")
+///     .build()
+///     .unwrap();
+///
+/// for notification in engine.notifications.iter() {
+///     println!("{}", notification);
+/// }
+/// ```
+pub struct EngineBuilder {
+    input: Input,
+    subscriptions: Vec<String>,
+    channel_capacity: usize,
+    recorder: Option<Recorder>,
+    drop_when_full: bool,
+}
+
+impl EngineBuilder {
+    pub fn new(mqtt_options: MqttOptions) -> EngineBuilder {
+        EngineBuilder::with_input(Input::Mqtt(Box::new(mqtt_options)))
+    }
+
+    /// Read records from `records` instead of connecting to a broker.
+    ///
+    /// ```no_run
+    /// use mqtt_analyzer::capture::Reader;
+    /// use mqtt_analyzer::engine::EngineBuilder;
+    ///
+    /// let reader = Reader::open("session.mqttcap").unwrap();
+    /// let engine = EngineBuilder::offline(Box::new(reader), None)
+    ///     .subscribe("#")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn offline(records: Records, speed: Option<f64>) -> EngineBuilder {
+        EngineBuilder::with_input(Input::Offline { records, speed })
+    }
+
+    pub fn with_input(input: Input) -> EngineBuilder {
+        EngineBuilder {
+            input,
+            subscriptions: Vec::new(),
+            channel_capacity: 1000,
+            recorder: None,
+            drop_when_full: false,
+        }
+    }
+
+    /// Subscribe to `topic` as soon as the engine is started, with QoS 1.
+    pub fn subscribe<S: Into<String>>(mut self, topic: S) -> EngineBuilder {
+        self.subscriptions.push(topic.into());
+        self
+    }
+
+    /// Subscribe to every topic in `topics` as soon as the engine is started.
+    pub fn subscriptions(mut self, topics: Vec<String>) -> EngineBuilder {
+        self.subscriptions.extend(topics);
+        self
+    }
+
+    /// Capacity of the request and notification channels.
+    pub fn channel_capacity(mut self, capacity: usize) -> EngineBuilder {
+        self.channel_capacity = capacity;
+        self
+    }
+
+    /// Record every notification received with `recorder`.
+    pub fn recorder(mut self, recorder: Recorder) -> EngineBuilder {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Drop the notifications from the broker when the notification channel
+    /// is full, counting them in [`Engine::dropped`], instead of waiting for
+    /// room. The client then keeps up with the broker even when the listener
+    /// is slow, at the cost of missing messages.
+    pub fn drop_when_full(mut self) -> EngineBuilder {
+        self.drop_when_full = true;
+        self
+    }
+
+    /// Connect to the broker, or open the offline input, and start the engine.
+    pub fn build(self) -> Result<Engine, failure::Error> {
+        let (client, source) = match self.input {
+            Input::Mqtt(mqtt_options) => {
+                let mqtt_options = (*mqtt_options)
+                    .set_request_channel_capacity(self.channel_capacity)
+                    .set_notification_channel_capacity(self.channel_capacity);
+
+                let (client, notifications) = MqttClient::start(mqtt_options)?;
+
+                (Some(client), Source::Mqtt(notifications))
+            }
+            Input::Offline { records, speed } => (None, Source::Offline { records, speed }),
+        };
+
+        // Offline inputs are filtered by subscription as soon as they are
+        // started, so valid subscriptions must be in place before that.
+        let subscriptions: Subscriptions = self
+            .subscriptions
+            .iter()
+            .filter(|sub| topic::is_valid_filter(sub))
+            .map(|sub| (sub.clone(), QoS::AtLeastOnce))
+            .collect();
+
+        let recorder = Arc::new(Mutex::new(self.recorder));
+        let engine = Engine::new(
+            source,
+            client,
+            subscriptions,
+            recorder,
+            self.channel_capacity,
+            self.drop_when_full,
+        );
+        engine.subscribe_all(self.subscriptions)?;
+
+        Ok(engine)
+    }
+}
+
+enum Source {
+    Mqtt(rumqtt::Receiver<rumqtt::Notification>),
+    Offline {
+        records: Records,
+        speed: Option<f64>,
+    },
+}
+
+/// Packet identifiers given by the client, which doesn't tell them: they are
+/// counted the same way, each subscription and each QoS 1 or 2 publish taking
+/// the next one, in the order they are handed to the client.
+#[derive(Default)]
+struct PacketIds {
+    last: u16,
+}
+
+impl PacketIds {
+    fn next(&mut self) -> u16 {
+        self.last = self.last % u16::MAX + 1;
+        self.last
+    }
+}
+
+fn is_subscribed(subscriptions: &SharedSubscriptions, topic: &str) -> Result<bool, Error> {
+    subscriptions
+        .read()
+        .map(|subscriptions| subscriptions.keys().any(|sub| topic::matches(sub, topic)))
+        .map_err(|_| Error::LockPoisoned)
 }
 
 impl Engine {
+    fn handle_event(
+        event: Event,
+        client: &mut Option<rumqtt::MqttClient>,
+        pkids: &mut PacketIds,
+        subscriptions: &SharedSubscriptions,
+        notifications: &crossbeam_channel::Sender<Notification>,
+    ) -> Result<(), Error> {
+        match event {
+            Event::Subscribe(sub, qos) => {
+                if !topic::is_valid_filter(&sub) {
+                    return Err(Error::InvalidFilter(sub));
+                }
+
+                if let Some(client) = client {
+                    client.subscribe(sub.as_str(), qos.into())?;
+                    pkids.next();
+                }
+
+                subscriptions
+                    .write()
+                    .map(|mut subscriptions| subscriptions.insert(sub, qos))
+                    .map_err(|_| Error::LockPoisoned)?;
+            }
+            // rumqtt 0.31 can't send an UNSUBSCRIBE: its event loop panics on
+            // it, dropping the connection. Until it can, the subscription is
+            // only forgotten here, and the broker keeps sending its messages.
+            Event::Unsubscribe(sub) => {
+                subscriptions
+                    .write()
+                    .map(|mut subscriptions| subscriptions.remove(sub.as_str()))
+                    .map_err(|_| Error::LockPoisoned)?;
+            }
+            Event::Publish(msg) => match Self::publish(&msg, client, pkids) {
+                Ok(pkid) => {
+                    let _ = notifications.send(Notification::Published(msg, pkid));
+                }
+                Err(e) => return Err(Error::Publish(msg, Box::new(e))),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Hand `msg` to the client, returning the packet identifier of its
+    /// acknowledgments.
+    fn publish(
+        msg: &Message,
+        client: &mut Option<rumqtt::MqttClient>,
+        pkids: &mut PacketIds,
+    ) -> Result<Option<u16>, Error> {
+        if !topic::is_valid_topic(&msg.topic) {
+            return Err(Error::InvalidTopic(msg.topic.clone()));
+        }
+
+        let client = client.as_mut().ok_or(Error::Offline)?;
+        client.publish(
+            msg.topic.as_str(),
+            msg.qos.into(),
+            msg.retain,
+            msg.payload.to_vec(),
+        )?;
+
+        Ok(match msg.qos {
+            QoS::AtMostOnce => None,
+            _ => Some(pkids.next()),
+        })
+    }
+
     fn listen_events(
         rx: Receiver<Event>,
-        mut client: rumqtt::MqttClient,
+        mut client: Option<rumqtt::MqttClient>,
+        subscriptions: SharedSubscriptions,
+        notifications: crossbeam_channel::Sender<Notification>,
+    ) {
+        let mut pkids = PacketIds::default();
+
+        // Stops when every sender has been dropped, that is when the engine
+        // itself is dropped.
+        for event in rx.iter() {
+            if let Err(e) = Self::handle_event(
+                event,
+                &mut client,
+                &mut pkids,
+                &subscriptions,
+                &notifications,
+            ) {
+                if notifications.send(Notification::Error(e)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn record(recorder: &SharedRecorder, notification: &Notification) -> Result<(), Error> {
+        match recorder.lock().map_err(|_| Error::LockPoisoned)?.as_mut() {
+            Some(recorder) => recorder
+                .record(&Record::new(notification.clone()))
+                .map_err(|e| Error::Capture(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Record and send `notification`. Returns `false` when nobody is
+    /// listening anymore.
+    ///
+    /// With a `dropped` counter, the notification is dropped and counted
+    /// when the channel is full, instead of waiting for room.
+    fn forward(
+        tx: &crossbeam_channel::Sender<Notification>,
+        recorder: &SharedRecorder,
+        dropped: Option<&AtomicU64>,
+        notification: Notification,
+    ) -> bool {
+        if let Err(e) = Self::record(recorder, &notification) {
+            if tx.send(Notification::Error(e)).is_err() {
+                return false;
+            }
+        }
+
+        match dropped {
+            Some(dropped) => match tx.try_send(notification) {
+                Ok(()) => true,
+                Err(crossbeam_channel::TrySendError::Full(_)) => {
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(crossbeam_channel::TrySendError::Disconnected(_)) => false,
+            },
+            None => tx.send(notification).is_ok(),
+        }
+    }
+
+    /// With a `dropped` counter, live notifications don't wait for a slow
+    /// listener: they are dropped when the channel is full.
+    fn forward_mqtt(
+        notifications: rumqtt::Receiver<rumqtt::Notification>,
+        tx: crossbeam_channel::Sender<Notification>,
+        recorder: SharedRecorder,
+        dropped: Option<Arc<AtomicU64>>,
+    ) {
+        for notification in notifications {
+            if let Some(notification) = Notification::from_rumqtt(notification) {
+                if !Self::forward(&tx, &recorder, dropped.as_deref(), notification) {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn forward_offline(
+        records: Records,
+        speed: Option<f64>,
+        tx: crossbeam_channel::Sender<Notification>,
+        recorder: SharedRecorder,
         subscriptions: SharedSubscriptions,
     ) {
-        loop {
-            match rx.recv() {
-                Ok(event) => match event {
-                    Event::Subscribe(sub) => {
-                        client
-                            .subscribe(sub.as_str(), rumqtt::QoS::AtLeastOnce)
-                            .unwrap();
-
-                        subscriptions
-                            .write()
-                            .map(|mut subscriptions| subscriptions.insert(sub))
-                            .unwrap();
+        let mut pacer = speed.map(Pacer::new);
+
+        for record in records {
+            let notification = match record {
+                Ok(record) => {
+                    if let Some(pacer) = &mut pacer {
+                        pacer.wait(record.timestamp);
                     }
-                    Event::Unsubscribe(sub) => {
-                        subscriptions
-                            .write()
-                            .map(|mut subscriptions| subscriptions.remove(sub.as_str()))
-                            .unwrap();
 
-                        // TODO client.unsubscribe(sub).unwrap();
+                    let subscribed = match &record.notification {
+                        Notification::Publish(msg) => is_subscribed(&subscriptions, &msg.topic),
+                        _ => Ok(true),
+                    };
+
+                    match subscribed {
+                        Ok(true) => record.notification,
+                        Ok(false) => continue,
+                        Err(e) => Notification::Error(e),
                     }
-                },
-                Err(e) => panic!("{:?}", e),
+                }
+                Err(e) => Notification::Error(Error::Capture(e.to_string())),
+            };
+
+            if !Self::forward(&tx, &recorder, None, notification) {
+                return;
             }
         }
+
+        let _ = tx.send(Notification::EndOfInput);
     }
-    pub fn new(
-        notifications: rumqtt::Receiver<rumqtt::Notification>,
-        client: rumqtt::MqttClient,
+
+    fn new(
+        source: Source,
+        client: Option<rumqtt::MqttClient>,
+        subscriptions: Subscriptions,
+        recorder: SharedRecorder,
+        channel_capacity: usize,
+        drop_when_full: bool,
     ) -> Engine {
         let (tx, rx) = std::sync::mpsc::channel();
-        let subscriptions = SharedSubscriptions::new(RwLock::new(Subscriptions::new()));
-        let subscriptions2 = subscriptions.clone();
-        let thread = thread::spawn(move || Self::listen_events(rx, client, subscriptions2));
+        let (notifications_tx, notifications_rx) = crossbeam_channel::bounded(channel_capacity);
+        let subscriptions = SharedSubscriptions::new(RwLock::new(subscriptions));
+
+        let thread = {
+            let subscriptions = subscriptions.clone();
+            let notifications_tx = notifications_tx.clone();
+            thread::spawn(move || Self::listen_events(rx, client, subscriptions, notifications_tx))
+        };
+
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        let forward_thread = {
+            let recorder = recorder.clone();
+            let subscriptions = subscriptions.clone();
+            let dropped = Some(dropped.clone()).filter(|_| drop_when_full);
+            thread::spawn(move || match source {
+                Source::Mqtt(notifications) => {
+                    Self::forward_mqtt(notifications, notifications_tx, recorder, dropped)
+                }
+                Source::Offline { records, speed } => {
+                    Self::forward_offline(records, speed, notifications_tx, recorder, subscriptions)
+                }
+            })
+        };
 
         Engine {
             subscriptions,
-            notifications,
+            recorder,
+            dropped,
+            notifications: notifications_rx,
             tx,
             thread,
+            forward_thread,
         }
     }
 
-    pub fn subscribe_all(&self, subscriptions: Vec<String>) {
-        subscriptions
-            .into_iter()
-            .for_each(|subscription| self.tx().send(Event::Subscribe(subscription)).unwrap());
+    pub fn subscribe_all(&self, subscriptions: Vec<String>) -> Result<(), Error> {
+        subscriptions.into_iter().try_for_each(|subscription| {
+            self.send(Event::Subscribe(subscription, QoS::AtLeastOnce))
+        })
+    }
+
+    /// Send `event` to the engine thread.
+    pub fn send(&self, event: Event) -> Result<(), Error> {
+        self.tx.send(event).map_err(|_| Error::Stopped)
     }
 
     pub fn tx(&self) -> Sender<Event> {
         self.tx.clone()
     }
+
+    pub fn recorder(&self) -> SharedRecorder {
+        self.recorder.clone()
+    }
+
+    /// Where the notifications are recorded, if they are.
+    pub fn recording(&self) -> Option<PathBuf> {
+        self.recorder
+            .lock()
+            .ok()?
+            .as_ref()
+            .map(|recorder| recorder.path().to_owned())
+    }
+
+    /// Record the next notifications with `recorder`, instead of the current
+    /// one, if any.
+    pub fn start_recording(&self, recorder: Recorder) -> Result<(), Error> {
+        self.stop_recording()?;
+        *self.recorder.lock().map_err(|_| Error::LockPoisoned)? = Some(recorder);
+        Ok(())
+    }
+
+    /// Stop recording, writing what was recorded so far to the capture file.
+    pub fn stop_recording(&self) -> Result<(), Error> {
+        self.flush_recording()?;
+        *self.recorder.lock().map_err(|_| Error::LockPoisoned)? = None;
+        Ok(())
+    }
+
+    /// Notifications dropped because the channel was full, with
+    /// [`EngineBuilder::drop_when_full`].
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Make sure everything recorded so far is written to the capture file.
+    pub fn flush_recording(&self) -> Result<(), Error> {
+        match self
+            .recorder
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .as_mut()
+        {
+            Some(recorder) => recorder.flush().map_err(|e| Error::Capture(e.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.flush_recording();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+
+    #[test]
+    fn offline_input_is_filtered_by_subscriptions() {
+        let records: Vec<io::Result<Record>> = vec!["a/b", "c/d", "a/e"]
+            .into_iter()
+            .map(|topic| Ok(Record::new(Notification::Publish(Message::new(topic, "x")))))
+            .collect();
+
+        let engine = EngineBuilder::offline(Box::new(records.into_iter()), None)
+            .subscribe("a/#")
+            .build()
+            .unwrap();
+
+        let topics: Vec<String> = engine
+            .notifications
+            .iter()
+            .take_while(|notification| *notification != Notification::EndOfInput)
+            .filter_map(|notification| match notification {
+                Notification::Publish(msg) => Some(msg.topic),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(topics, vec!["a/b", "a/e"]);
+    }
+
+    #[test]
+    fn packet_ids_wrap() {
+        let mut pkids = PacketIds::default();
+        assert_eq!(pkids.next(), 1);
+        assert_eq!(pkids.next(), 2);
+
+        pkids.last = u16::MAX - 1;
+        assert_eq!(pkids.next(), u16::MAX);
+        assert_eq!(pkids.next(), 1);
+    }
+
+    #[test]
+    fn publish_needs_a_broker() {
+        let records: Vec<io::Result<Record>> = vec![];
+        let engine = EngineBuilder::offline(Box::new(records.into_iter()), None)
+            .build()
+            .unwrap();
+
+        let invalid = Message::new("a/+", "x");
+        let valid = Message::new("a/b", "x");
+        let tx = engine.tx();
+        tx.send(Event::Publish(invalid.clone())).unwrap();
+        tx.send(Event::Publish(valid.clone())).unwrap();
+
+        let errors: Vec<Error> = engine
+            .notifications
+            .iter()
+            .filter_map(|notification| match notification {
+                Notification::Error(e) => Some(e),
+                _ => None,
+            })
+            .take(2)
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                Error::Publish(invalid, Box::new(Error::InvalidTopic("a/+".to_string()))),
+                Error::Publish(valid, Box::new(Error::Offline)),
+            ]
+        );
+    }
 }
@@ -0,0 +1,469 @@
+//! Expressions selecting messages by topic, payload, size, QoS and retain
+//! flag.
+//!
+//! An expression combines predicates with `and`, `or` and `not` (or `&&`,
+//! `||` and `!`), grouped with parentheses. Predicates next to each other
+//! must all match, as with `and`.
+//!
+//! ```text
+//! sensors/#                 topic matching an MQTT filter, like topic:sensors/#
+//! topic~^sensors/\d+$       topic matching a regular expression
+//! payload:error             payload containing a text
+//! payload~"temp.*[0-9]"     payload matching a regular expression
+//! hex:cafe                  payload containing bytes
+//! size>100                  payload size, with = < > <= >=
+//! qos>=1                    QoS, with the same comparisons as size
+//! retain:true               retain flag
+//! ```
+//!
+//! Values containing spaces or parentheses must be quoted.
+//!
+//! ```
+//! use mqtt_analyzer::filter::Filter;
+//! use mqtt_analyzer::Message;
+//!
+//! let filter: Filter = "sensors/# and not (payload:error or size>100)".parse().unwrap();
+//!
+//! assert!(filter.matches(&Message::new("sensors/1", "21.5")));
+//! assert!(!filter.matches(&Message::new("sensors/1", "error")));
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use failure::Fail;
+use regex::bytes;
+use regex::Regex;
+
+use crate::message::{Message, Notification};
+use crate::topic;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    UnterminatedString,
+    UnknownField(String),
+    InvalidValue(String, String),
+    InvalidRegex(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd => write!(f, "Unexpected end of filter"),
+            ParseError::UnexpectedToken(token) => write!(f, "Unexpected {:?}", token),
+            ParseError::UnterminatedString => write!(f, "Unterminated string"),
+            ParseError::UnknownField(field) => write!(f, "Unknown field {:?}", field),
+            ParseError::InvalidValue(field, value) => {
+                write!(f, "Invalid value {:?} for {}", value, field)
+            }
+            ParseError::InvalidRegex(reason) => write!(f, "Invalid regex: {}", reason),
+        }
+    }
+}
+
+impl Fail for ParseError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn from_operator(op: &str) -> Option<Comparison> {
+        match op {
+            ":" | "=" | "==" => Some(Comparison::Equal),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None,
+        }
+    }
+
+    fn compare(self, a: usize, b: usize) -> bool {
+        match self {
+            Comparison::Equal => a == b,
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Topic(String),
+    TopicRegex(Regex),
+    Payload(Vec<u8>),
+    PayloadRegex(bytes::Regex),
+    Size(Comparison, usize),
+    QoS(Comparison, usize),
+    Retain(bool),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty() || haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+impl Expr {
+    fn matches(&self, msg: &Message) -> bool {
+        match self {
+            Expr::Topic(filter) => topic::matches(filter, &msg.topic),
+            Expr::TopicRegex(re) => re.is_match(&msg.topic),
+            Expr::Payload(bytes) => contains(&msg.payload, bytes),
+            Expr::PayloadRegex(re) => re.is_match(&msg.payload),
+            Expr::Size(cmp, size) => cmp.compare(msg.payload.len(), *size),
+            Expr::QoS(cmp, qos) => cmp.compare(usize::from(msg.qos.to_u8()), *qos),
+            Expr::Retain(retain) => msg.retain == *retain,
+            Expr::Not(expr) => !expr.matches(msg),
+            Expr::And(a, b) => a.matches(msg) && b.matches(msg),
+            Expr::Or(a, b) => a.matches(msg) || b.matches(msg),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Not,
+    And,
+    Or,
+    Word(String),
+    Predicate(String, String, String),
+}
+
+const FIELDS: &[&str] = &["topic", "payload", "hex", "size", "qos", "retain"];
+const OPERATORS: &[char] = &[':', '~', '<', '>', '='];
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')'
+}
+
+/// Read a quoted string, whose opening quote has already been read.
+fn read_quoted(chars: &mut Chars) -> Result<String, ParseError> {
+    let mut value = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(value),
+            Some('\\') => match chars.next() {
+                Some(c) if c == '"' || c == '\\' => value.push(c),
+                Some(c) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => return Err(ParseError::UnterminatedString),
+            },
+            Some(c) => value.push(c),
+            None => return Err(ParseError::UnterminatedString),
+        }
+    }
+}
+
+/// Read a value up to the next separator. Quoted parts can contain
+/// separators.
+fn read_value(chars: &mut Chars) -> Result<String, ParseError> {
+    let mut value = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if is_separator(c) {
+            break;
+        }
+
+        chars.next();
+        if c == '"' {
+            value += &read_quoted(chars)?;
+        } else {
+            value.push(c);
+        }
+    }
+
+    Ok(value)
+}
+
+fn read_while<F: Fn(char) -> bool>(chars: &mut Chars, f: F) -> String {
+    let mut s = String::new();
+    while let Some(&c) = chars.peek() {
+        if !f(c) {
+            break;
+        }
+        s.push(c);
+        chars.next();
+    }
+    s
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '!' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            '"' => tokens.push(Token::Word(read_value(&mut chars)?)),
+            _ => {
+                let field = read_while(&mut chars, |c| c.is_ascii_alphabetic());
+                let op = read_while(&mut chars, |c| OPERATORS.contains(&c));
+
+                let token = if !field.is_empty() && !op.is_empty() {
+                    if !FIELDS.contains(&field.as_str()) {
+                        return Err(ParseError::UnknownField(field));
+                    }
+                    Token::Predicate(field, op, read_value(&mut chars)?)
+                } else {
+                    let word = field + &op + &read_value(&mut chars)?;
+                    match word.as_str() {
+                        "and" | "AND" | "&&" => Token::And,
+                        "or" | "OR" | "||" => Token::Or,
+                        "not" | "NOT" => Token::Not,
+                        _ => Token::Word(word),
+                    }
+                };
+
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(field: &str, value: &str) -> Result<usize, ParseError> {
+    value
+        .parse()
+        .map_err(|_| ParseError::InvalidValue(field.to_string(), value.to_string()))
+}
+
+fn predicate(field: &str, op: &str, value: String) -> Result<Expr, ParseError> {
+    let invalid = || ParseError::InvalidValue(field.to_string(), value.clone());
+    let invalid_regex = |e: regex::Error| ParseError::InvalidRegex(e.to_string());
+
+    let expr = match (field, op) {
+        ("topic", ":") if topic::is_valid_filter(&value) => Expr::Topic(value),
+        ("topic", "~") => Expr::TopicRegex(Regex::new(&value).map_err(invalid_regex)?),
+        ("payload", ":") => Expr::Payload(value.into_bytes()),
+        ("payload", "~") => Expr::PayloadRegex(bytes::Regex::new(&value).map_err(invalid_regex)?),
+        ("hex", ":") => Expr::Payload(hex::decode(&value).map_err(|_| invalid())?),
+        ("size", op) => Expr::Size(
+            Comparison::from_operator(op).ok_or_else(invalid)?,
+            parse_number(field, &value)?,
+        ),
+        ("qos", op) => Expr::QoS(
+            Comparison::from_operator(op).ok_or_else(invalid)?,
+            parse_number(field, &value)?,
+        ),
+        ("retain", ":") => Expr::Retain(value.parse().map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    };
+
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Or) | Some(Token::Close) | None => return Ok(expr),
+                Some(_) => {}
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(token) => Err(ParseError::UnexpectedToken(format!("{:?}", token))),
+                    None => Err(ParseError::UnexpectedEnd),
+                }
+            }
+            Some(Token::Word(word)) => predicate("topic", ":", word),
+            Some(Token::Predicate(field, op, value)) => predicate(&field, &op, value),
+            Some(Token::Close) => Err(ParseError::UnexpectedToken(")".to_string())),
+            Some(Token::And) => Err(ParseError::UnexpectedToken("and".to_string())),
+            Some(Token::Or) => Err(ParseError::UnexpectedToken("or".to_string())),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+}
+
+/// Quote `value`, so that it is read back as a single value.
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A parsed filter expression.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn matches(&self, msg: &Message) -> bool {
+        self.expr.matches(msg)
+    }
+
+    /// Whether `notification` should be shown. Only messages are filtered,
+    /// every other notification is selected.
+    pub fn selects(&self, notification: &Notification) -> bool {
+        match notification {
+            Notification::Publish(msg) => self.matches(msg),
+            _ => true,
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+
+        let expr = parser.or()?;
+
+        match parser.next() {
+            Some(Token::Close) => Err(ParseError::UnexpectedToken(")".to_string())),
+            Some(token) => Err(ParseError::UnexpectedToken(format!("{:?}", token))),
+            None => Ok(Filter {
+                source: s.to_string(),
+                expr,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::QoS;
+
+    fn matches(filter: &str, msg: &Message) -> bool {
+        filter.parse::<Filter>().unwrap().matches(msg)
+    }
+
+    #[test]
+    fn match_predicates() {
+        let msg = Message::new("sensors/1/temp", "temp 21.5")
+            .qos(QoS::AtLeastOnce)
+            .retain(true);
+
+        assert!(matches("sensors/#", &msg));
+        assert!(matches("topic:sensors/+/temp", &msg));
+        assert!(matches(r"topic~^sensors/\d+/", &msg));
+        assert!(matches("payload:21", &msg));
+        assert!(matches(r#"payload~"temp \d+""#, &msg));
+        assert!(matches("hex:3231", &msg));
+        assert!(matches("size=9 size<=9 size>8", &msg));
+        assert!(matches("qos>=1 retain:true", &msg));
+
+        assert!(!matches("other/#", &msg));
+        assert!(!matches("payload:error", &msg));
+        assert!(!matches("qos:2", &msg));
+    }
+
+    #[test]
+    fn combine_predicates() {
+        let msg = Message::new("a/b", "ciao");
+
+        assert!(matches("a/# and not payload:hello", &msg));
+        assert!(matches("c/# or (a/b && size<10)", &msg));
+        assert!(!matches("!a/#", &msg));
+        assert!(!matches("a/# payload:hello", &msg));
+        assert!(matches("not c/# || a/#", &msg));
+
+        let msg = Message::new("a \"b\" (c)", "ciao");
+        assert!(matches(&format!("topic:{}", quote(&msg.topic)), &msg));
+    }
+
+    #[test]
+    fn reject_invalid_filters() {
+        let error = |filter: &str| filter.parse::<Filter>().unwrap_err();
+
+        assert_eq!(error(""), ParseError::UnexpectedEnd);
+        assert_eq!(error("a and"), ParseError::UnexpectedEnd);
+        assert_eq!(error("(a"), ParseError::UnexpectedEnd);
+        assert_eq!(error("a)"), ParseError::UnexpectedToken(")".to_string()));
+        assert_eq!(
+            error("color:red"),
+            ParseError::UnknownField("color".to_string())
+        );
+        assert_eq!(
+            error("size>big"),
+            ParseError::InvalidValue("size".to_string(), "big".to_string())
+        );
+        assert_eq!(error("payload:\"ciao"), ParseError::UnterminatedString);
+        assert!(matches!(
+            error("payload~\"(\""),
+            ParseError::InvalidRegex(_)
+        ));
+    }
+}
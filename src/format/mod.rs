@@ -0,0 +1,519 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::message::{Message, Notification};
+
+pub use registry::{Decoder, Registry};
+
+mod registry;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PayloadFormat {
+    Auto,
+    Text,
+    Hex,
+    Base64,
+    Escape,
+    Json,
+}
+
+impl PayloadFormat {
+    pub const ALL: &'static [PayloadFormat] = &[
+        PayloadFormat::Auto,
+        PayloadFormat::Text,
+        PayloadFormat::Hex,
+        PayloadFormat::Base64,
+        PayloadFormat::Escape,
+        PayloadFormat::Json,
+    ];
+
+    pub fn next(self) -> PayloadFormat {
+        let index = PayloadFormat::ALL
+            .iter()
+            .position(|f| *f == self)
+            .unwrap_or(0);
+        PayloadFormat::ALL[(index + 1) % PayloadFormat::ALL.len()]
+    }
+
+    /// The name accepted by `from_str`. Unlike `Display`, it is defined for
+    /// `Auto` too.
+    pub fn name(self) -> &'static str {
+        match self {
+            PayloadFormat::Auto => "auto",
+            PayloadFormat::Text => "text",
+            PayloadFormat::Hex => "hex",
+            PayloadFormat::Base64 => "base64",
+            PayloadFormat::Escape => "escape",
+            PayloadFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for PayloadFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hex" => Ok(PayloadFormat::Hex),
+            "text" | "txt" => Ok(PayloadFormat::Text),
+            "base64" | "b64" => Ok(PayloadFormat::Base64),
+            "escape" | "esc" => Ok(PayloadFormat::Escape),
+            "json" => Ok(PayloadFormat::Json),
+            "auto" => Ok(PayloadFormat::Auto),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct MessageFormat {
+    pub payload_format: PayloadFormat,
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat {
+            payload_format: PayloadFormat::Text,
+        }
+    }
+}
+
+impl fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadFormat::Hex => write!(f, "HEX"),
+            PayloadFormat::Text => write!(f, "TXT"),
+            PayloadFormat::Base64 => write!(f, "B64"),
+            PayloadFormat::Escape => write!(f, "ESC"),
+            PayloadFormat::Json => write!(f, "JSN"),
+            PayloadFormat::Auto => panic!("Auto"),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Color {
+    Background,
+    OnBackground,
+    Primary,
+    OnPrimary,
+    Secondary,
+    OnSecondary,
+    Error,
+    OnError,
+}
+
+impl From<Color> for colored::Color {
+    fn from(color: Color) -> colored::Color {
+        match color {
+            Color::Background => colored::Color::Black,
+            Color::OnBackground => colored::Color::White,
+            Color::Primary => colored::Color::Blue,
+            Color::OnPrimary => colored::Color::Black,
+            Color::Secondary => colored::Color::Yellow,
+            Color::OnSecondary => colored::Color::Black,
+            Color::Error => colored::Color::Red,
+            Color::OnError => colored::Color::White,
+        }
+    }
+}
+
+impl From<Color> for tui::style::Color {
+    fn from(color: Color) -> tui::style::Color {
+        match color {
+            Color::Background => tui::style::Color::Black,
+            Color::OnBackground => tui::style::Color::White,
+            Color::Primary => tui::style::Color::Blue,
+            Color::OnPrimary => tui::style::Color::Black,
+            Color::Secondary => tui::style::Color::Yellow,
+            Color::OnSecondary => tui::style::Color::Black,
+            Color::Error => tui::style::Color::Red,
+            Color::OnError => tui::style::Color::White,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TokenStyle {
+    color: Color,
+    background: Color,
+}
+
+#[derive(Clone)]
+pub struct FormattedToken {
+    style: TokenStyle,
+    content: String,
+}
+
+impl FormattedToken {
+    fn new(style: TokenStyle, content: String) -> FormattedToken {
+        FormattedToken { style, content }
+    }
+}
+
+#[derive(Clone)]
+pub struct FormattedString(Vec<FormattedToken>);
+
+impl FormattedString {
+    pub fn to_color_string(&self) -> String {
+        self.0
+            .iter()
+            .map(|tok| {
+                use colored::Colorize;
+                let fg: colored::Color = tok.style.color.into();
+                let bg: colored::Color = tok.style.background.into();
+                format!("{}", tok.content.color(fg).on_color(bg))
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for FormattedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let content = self
+            .0
+            .iter()
+            .map(|tok| tok.content.clone())
+            .collect::<Vec<String>>()
+            .join(" ");
+        write!(f, "{}", content)
+    }
+}
+
+pub fn format_payload_hex(payload: &[u8]) -> String {
+    hex::encode(payload)
+}
+
+fn escape_only_non_printable(text: String) -> String {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c.to_string(),
+            c if c.is_control() => c.escape_default().to_string(),
+            _ => c.escape_debug().to_string(),
+        })
+        .collect()
+}
+
+pub fn format_payload_text(payload: &[u8]) -> (PayloadFormat, String) {
+    match String::from_utf8(payload.to_vec()) {
+        Result::Ok(text) => (PayloadFormat::Text, escape_only_non_printable(text)),
+        _ => (PayloadFormat::Hex, format_payload_hex(payload)),
+    }
+}
+
+pub fn format_payload_base64(payload: &[u8]) -> String {
+    base64::encode(payload)
+}
+
+pub fn format_payload_ascii(payload: &[u8]) -> (PayloadFormat, String) {
+    match String::from_utf8(payload.to_vec()) {
+        Result::Ok(text) => (PayloadFormat::Escape, text.escape_default().to_string()),
+        _ => (PayloadFormat::Hex, format_payload_hex(payload)),
+    }
+}
+
+/// Format a JSON payload on a single line, falling back to text when it is
+/// not valid JSON.
+pub fn format_payload_json(payload: &[u8]) -> (PayloadFormat, String) {
+    match serde_json::from_slice::<serde_json::Value>(payload) {
+        Ok(value) => (PayloadFormat::Json, value.to_string()),
+        Err(_) => format_payload_text(payload),
+    }
+}
+
+/// Format a JSON payload on multiple indented lines.
+pub fn format_payload_json_pretty(payload: &[u8]) -> Option<String> {
+    serde_json::from_slice::<serde_json::Value>(payload)
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&value).ok())
+}
+
+/// Format a payload as an hex dump, 16 bytes per line, with offsets and the
+/// printable characters on the right.
+pub fn format_payload_hex_dump(payload: &[u8]) -> String {
+    payload
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<_> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if is_printable(&[b]) { b as char } else { '.' })
+                .collect();
+            format!("{:08x}  {:<47}  {}", i * 16, hex.join(" "), ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Format the time elapsed from `t` to `now`, in its largest unit.
+pub fn format_age(now: SystemTime, t: SystemTime) -> String {
+    let age = now.duration_since(t).unwrap_or(Duration::from_secs(0));
+
+    match age.as_secs() {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+/// Format a size in bytes, with a `K`, `M` or `G` suffix when it is large.
+pub fn format_size(size: usize) -> String {
+    const UNITS: &[&str] = &["K", "M", "G"];
+
+    if size < 1024 {
+        return format!("{}B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1}{}", value, UNITS[unit])
+}
+
+/// Format a timestamp as UTC date and time, with milliseconds.
+pub fn format_timestamp(timestamp: SystemTime) -> String {
+    let since_epoch = timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    let secs = since_epoch.as_secs();
+    let days = (secs / 86400) as i64;
+
+    // Civil date from days since epoch, see
+    // http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03} UTC",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Format the time of day of a timestamp, in UTC, to the second.
+pub fn format_time(timestamp: SystemTime) -> String {
+    let secs = timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0))
+        .as_secs();
+
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+fn is_printable(payload: &[u8]) -> bool {
+    payload.iter().all(|c| matches!(c, b' '..=b'~'))
+}
+
+fn guess_format(payload: &[u8]) -> PayloadFormat {
+    if is_printable(payload) {
+        return PayloadFormat::Text;
+    }
+
+    PayloadFormat::Escape
+}
+
+pub fn format_payload(format: PayloadFormat, payload: &[u8]) -> (PayloadFormat, String) {
+    match format {
+        PayloadFormat::Hex => (format, format_payload_hex(payload)),
+        PayloadFormat::Text => format_payload_text(payload),
+        PayloadFormat::Base64 => (format, format_payload_base64(payload)),
+        PayloadFormat::Escape => format_payload_ascii(payload),
+        PayloadFormat::Json => format_payload_json(payload),
+        PayloadFormat::Auto => {
+            let actual_format = guess_format(payload);
+            format_payload(actual_format, payload)
+        }
+    }
+}
+
+pub const TOPIC_STYLE: TokenStyle = TokenStyle {
+    color: Color::OnPrimary,
+    background: Color::Primary,
+};
+
+pub const PAYLOAD_STYLE: TokenStyle = TokenStyle {
+    color: Color::OnBackground,
+    background: Color::Background,
+};
+
+pub const FORMAT_STYLE: TokenStyle = TokenStyle {
+    color: Color::OnSecondary,
+    background: Color::Secondary,
+};
+
+pub const NOTIFICATION_STYLE: TokenStyle = TokenStyle {
+    color: Color::OnError,
+    background: Color::Error,
+};
+
+pub fn format_message(format: MessageFormat, msg: &Message) -> FormattedString {
+    let (format, payload) = format_payload(format.payload_format, msg.payload.as_ref());
+
+    FormattedString(vec![
+        FormattedToken::new(FORMAT_STYLE, format.to_string()),
+        FormattedToken::new(TOPIC_STYLE, msg.topic.clone()),
+        FormattedToken::new(PAYLOAD_STYLE, payload),
+    ])
+}
+
+fn format_generic_notification(notification: &Notification) -> FormattedString {
+    FormattedString(vec![FormattedToken::new(
+        NOTIFICATION_STYLE,
+        notification.to_string(),
+    )])
+}
+
+pub fn format_notification(format: MessageFormat, notification: &Notification) -> FormattedString {
+    match notification {
+        Notification::Publish(msg) => format_message(format, msg),
+        notification => format_generic_notification(notification),
+    }
+}
+
+impl From<TokenStyle> for tui::style::Style {
+    fn from(style: TokenStyle) -> tui::style::Style {
+        tui::style::Style::default()
+            .fg(style.color.into())
+            .bg(style.background.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_payload_hex_non_empty() {
+        assert_eq!(format_payload(PayloadFormat::Hex, b"ciao").1, "6369616f");
+    }
+
+    #[test]
+    fn format_payload_text_non_empty() {
+        assert_eq!(format_payload(PayloadFormat::Text, b"ciao").1, "ciao");
+    }
+
+    #[test]
+    #[allow(clippy::print_literal)]
+    fn format_payload_text_with_special_chars_non_empty() {
+        assert_eq!(format_payload(PayloadFormat::Text, b"{ciao?").1, "{ciao?");
+        println!(
+            "{} == {}",
+            format_payload(PayloadFormat::Text, b"{ciao?").1,
+            "{ciao?"
+        );
+    }
+
+    #[test]
+    fn format_payload_text_with_unicode_non_empty() {
+        assert_eq!(
+            format_payload(PayloadFormat::Text, "ciao❤".as_bytes()).1,
+            "ciao❤"
+        );
+    }
+
+    #[test]
+    fn format_payload_text_non_utf8() {
+        assert_eq!(
+            format_payload(PayloadFormat::Text, b"\xf1\xf2\xf4\xf7").1,
+            "f1f2f4f7"
+        );
+    }
+
+    #[test]
+    fn format_payload_text_non_printable() {
+        assert_eq!(
+            format_payload(PayloadFormat::Text, b"\tc\ni\0a\ro").1,
+            "\\tc\\ni\\u{0}a\\ro"
+        );
+    }
+
+    #[test]
+    fn format_payload_base64_non_empty() {
+        assert_eq!(format_payload(PayloadFormat::Base64, b"ciao").1, "Y2lhbw==");
+    }
+
+    #[test]
+    fn format_payload_ascii_non_empty() {
+        assert_eq!(
+            format_payload(PayloadFormat::Escape, "ciao❤".as_bytes()).1,
+            "ciao\\u{2764}"
+        );
+    }
+
+    #[test]
+    fn format_payload_json_compact() {
+        assert_eq!(
+            format_payload(PayloadFormat::Json, b"{ \"a\": [1, 2] }"),
+            (PayloadFormat::Json, "{\"a\":[1,2]}".to_string())
+        );
+        assert_eq!(
+            format_payload(PayloadFormat::Json, b"ciao"),
+            (PayloadFormat::Text, "ciao".to_string())
+        );
+    }
+
+    #[test]
+    fn format_payload_hex_dump_lines() {
+        let dump = format_payload_hex_dump(b"0123456789abcdef\x00!");
+        let lines: Vec<_> = dump.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("00000000  30 31 32"));
+        assert!(lines[0].ends_with("  0123456789abcdef"));
+        assert_eq!(lines[1], format!("00000010  00 21{}  .!", " ".repeat(42)));
+    }
+
+    #[test]
+    fn format_timestamp_utc() {
+        let timestamp = UNIX_EPOCH + Duration::from_millis(1_709_251_199_042);
+        assert_eq!(format_timestamp(timestamp), "2024-02-29 23:59:59.042 UTC");
+        assert_eq!(format_time(timestamp), "23:59:59");
+    }
+
+    #[test]
+    fn payload_format_names_cycle() {
+        let mut format = PayloadFormat::Auto;
+        for _ in PayloadFormat::ALL {
+            assert_eq!(format.name().parse::<PayloadFormat>(), Ok(format));
+            format = format.next();
+        }
+        assert_eq!(format, PayloadFormat::Auto);
+    }
+
+    #[test]
+    fn format_age_and_size() {
+        let now = UNIX_EPOCH + Duration::from_secs(100_000);
+        assert_eq!(format_age(now, now - Duration::from_secs(59)), "59s");
+        assert_eq!(format_age(now, now - Duration::from_secs(7200)), "2h");
+        assert_eq!(format_age(now, now + Duration::from_secs(1)), "0s");
+
+        assert_eq!(format_size(1023), "1023B");
+        assert_eq!(format_size(1536), "1.5K");
+        assert_eq!(format_size(3 << 20), "3.0M");
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+use super::{format_payload, PayloadFormat};
+
+/// Turns a raw payload into something readable.
+pub trait Decoder: Send + Sync {
+    /// Decode `payload`, returning the tag to show next to it and the decoded
+    /// text, or `None` if the payload cannot be decoded by this decoder.
+    fn decode(&self, payload: &[u8]) -> Option<(String, String)>;
+}
+
+impl Decoder for PayloadFormat {
+    fn decode(&self, payload: &[u8]) -> Option<(String, String)> {
+        let (format, text) = format_payload(*self, payload);
+        Some((format.to_string(), text))
+    }
+}
+
+impl<F> Decoder for F
+where
+    F: Fn(&[u8]) -> Option<(String, String)> + Send + Sync,
+{
+    fn decode(&self, payload: &[u8]) -> Option<(String, String)> {
+        self(payload)
+    }
+}
+
+/// Named collection of decoders.
+///
+/// `Registry::default()` contains the built-in payload formats, under the same
+/// names accepted by `PayloadFormat::from_str`. Custom decoders can be added
+/// with `register`:
+///
+/// ```
+/// use mqtt_analyzer::format::Registry;
+///
+/// let mut registry = Registry::default();
+/// registry.register("len", |payload: &[u8]| {
+///     Some(("LEN".to_string(), payload.len().to_string()))
+/// });
+///
+/// assert_eq!(
+///     registry.decode("len", b"ciao"),
+///     Some(("LEN".to_string(), "4".to_string()))
+/// );
+/// ```
+pub struct Registry {
+    decoders: BTreeMap<String, Box<dyn Decoder>>,
+}
+
+impl Registry {
+    /// An empty registry, without the built-in formats.
+    pub fn new() -> Registry {
+        Registry {
+            decoders: BTreeMap::new(),
+        }
+    }
+
+    /// Register `decoder` as `name`, replacing any decoder with the same name.
+    pub fn register<D>(&mut self, name: &str, decoder: D)
+    where
+        D: Decoder + 'static,
+    {
+        self.decoders.insert(name.to_lowercase(), Box::new(decoder));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Decoder> {
+        self.decoders
+            .get(&name.to_lowercase())
+            .map(|decoder| decoder.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.decoders.keys().map(String::as_str)
+    }
+
+    /// Decode `payload` with the decoder registered as `name`.
+    pub fn decode(&self, name: &str, payload: &[u8]) -> Option<(String, String)> {
+        self.get(name).and_then(|decoder| decoder.decode(payload))
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        let mut registry = Registry::new();
+        registry.register("auto", PayloadFormat::Auto);
+        registry.register("text", PayloadFormat::Text);
+        registry.register("hex", PayloadFormat::Hex);
+        registry.register("base64", PayloadFormat::Base64);
+        registry.register("escape", PayloadFormat::Escape);
+        registry.register("json", PayloadFormat::Json);
+        registry
+    }
+}
@@ -0,0 +1,401 @@
+//! Bounded history of the latest notifications.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::mem;
+use std::sync::Arc;
+
+use crate::message::Notification;
+
+/// Limits of a `History`. The oldest notifications are dropped as soon as
+/// any of them is exceeded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capacity {
+    /// Maximum number of notifications.
+    pub count: Option<usize>,
+    /// Maximum memory used by the notifications, in bytes. The estimate
+    /// includes topics and payloads.
+    pub bytes: Option<usize>,
+}
+
+impl Default for Capacity {
+    fn default() -> Capacity {
+        Capacity {
+            count: Some(1000),
+            bytes: None,
+        }
+    }
+}
+
+fn footprint(notification: &Notification) -> usize {
+    let data = match notification {
+        Notification::Publish(msg) => msg.topic.len() + msg.payload.len(),
+        _ => 0,
+    };
+
+    mem::size_of::<Notification>() + data
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    items: VecDeque<Notification>,
+    capacity: Capacity,
+    bytes: usize,
+    pushed: u64,
+    dropped: u64,
+}
+
+impl History {
+    pub fn new(capacity: Capacity) -> History {
+        History {
+            capacity,
+            ..History::default()
+        }
+    }
+
+    /// Add `notification`, and return the oldest ones dropped to stay within
+    /// the capacity, the oldest first.
+    pub fn push(&mut self, notification: Notification) -> Vec<Notification> {
+        self.bytes += footprint(&notification);
+        self.items.push_back(notification);
+        self.pushed += 1;
+
+        let mut dropped = vec![];
+        while self.is_over_capacity() {
+            match self.items.pop_front() {
+                Some(oldest) => {
+                    self.bytes -= footprint(&oldest);
+                    self.dropped += 1;
+                    dropped.push(oldest);
+                }
+                None => break,
+            }
+        }
+
+        dropped
+    }
+
+    fn is_over_capacity(&self) -> bool {
+        self.capacity
+            .count
+            .is_some_and(|count| self.items.len() > count)
+            || self.capacity.bytes.is_some_and(|bytes| self.bytes > bytes)
+    }
+
+    /// Notifications from the newest to the oldest.
+    pub fn iter(&self) -> impl Iterator<Item = &Notification> + Clone {
+        self.items.iter().rev()
+    }
+
+    /// Sequence number of the oldest notification. Each notification pushed
+    /// gets the next one, starting from 0.
+    pub fn first(&self) -> u64 {
+        self.pushed - self.items.len() as u64
+    }
+
+    /// The notification with the sequence number `seq`, if it is still here.
+    pub fn get(&self, seq: u64) -> Option<&Notification> {
+        let index = seq.checked_sub(self.first())?;
+        self.items.get(usize::try_from(index).ok()?)
+    }
+
+    /// Notifications from the oldest to the newest, with their sequence
+    /// numbers.
+    pub fn numbered(&self) -> impl Iterator<Item = (u64, &Notification)> {
+        (self.first()..).zip(self.items.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn capacity(&self) -> Capacity {
+        self.capacity
+    }
+
+    /// Estimated memory used by the notifications, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Notifications pushed since the history was created.
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Notifications dropped to stay within the capacity.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.bytes = 0;
+    }
+}
+
+/// Collapse the repeated messages of `notifications`, given from the newest
+/// to the oldest. A message with the same payload as the next message of its
+/// topic is counted in the line of the latter, whatever the messages of the
+/// other topics in between. Return the lines, the newest first, with the
+/// number of messages each of them stands for.
+pub fn collapse<'a, I>(notifications: I) -> Vec<(&'a Notification, usize)>
+where
+    I: IntoIterator<Item = &'a Notification>,
+{
+    let mut lines: Vec<(&Notification, usize)> = vec![];
+    // The oldest line of each topic so far, with its payload.
+    let mut oldest: HashMap<&str, (usize, &[u8])> = HashMap::new();
+
+    for notification in notifications {
+        if let Notification::Publish(msg) = notification {
+            match oldest.get(msg.topic.as_str()) {
+                Some(&(line, payload)) if payload == msg.payload.as_slice() => {
+                    lines[line].1 += 1;
+                    continue;
+                }
+                _ => {
+                    oldest.insert(&msg.topic, (lines.len(), &msg.payload));
+                }
+            }
+        }
+
+        lines.push((notification, 1));
+    }
+
+    lines
+}
+
+/// The lines of [`collapse`], kept up to date as notifications are pushed
+/// and dropped, instead of collapsing the whole history again.
+#[derive(Clone, Debug, Default)]
+pub struct Collapsed {
+    /// Messages each line stands for, by the sequence number of its newest
+    /// notification.
+    lines: BTreeMap<u64, usize>,
+    /// Lines of each topic, the oldest first, with the payload of the newest
+    /// one.
+    topics: HashMap<String, (VecDeque<u64>, Arc<Vec<u8>>)>,
+}
+
+impl Collapsed {
+    /// Add the newest notification, with the sequence number `seq`.
+    pub fn push(&mut self, seq: u64, notification: &Notification) {
+        let count = match notification {
+            Notification::Publish(msg) => {
+                let (lines, payload) = self.topics.entry(msg.topic.clone()).or_default();
+                match lines.back_mut() {
+                    Some(line) if *payload == msg.payload => {
+                        let count = self.lines.remove(line).unwrap_or(0);
+                        *line = seq;
+                        count + 1
+                    }
+                    _ => {
+                        lines.push_back(seq);
+                        *payload = msg.payload.clone();
+                        1
+                    }
+                }
+            }
+            _ => 1,
+        };
+
+        self.lines.insert(seq, count);
+    }
+
+    /// Remove the oldest notification added, with the sequence number `seq`.
+    pub fn remove(&mut self, seq: u64, notification: &Notification) {
+        let topic = match notification {
+            Notification::Publish(msg) => Some(msg.topic.as_str()),
+            _ => None,
+        };
+        let line = match topic {
+            Some(topic) => match self.topics.get(topic).and_then(|(lines, _)| lines.front()) {
+                Some(line) => *line,
+                None => return,
+            },
+            None => seq,
+        };
+
+        match self.lines.get_mut(&line) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                return;
+            }
+            Some(_) => {
+                self.lines.remove(&line);
+            }
+            None => return,
+        }
+
+        if let Some(topic) = topic {
+            if let Some((lines, _)) = self.topics.get_mut(topic) {
+                lines.pop_front();
+                if lines.is_empty() {
+                    self.topics.remove(topic);
+                }
+            }
+        }
+    }
+
+    /// The lines, the newest first: the sequence number of their newest
+    /// notification, and the number of messages they stand for.
+    pub fn lines(&self) -> impl Iterator<Item = (u64, usize)> + '_ {
+        self.lines.iter().rev().map(|(seq, count)| (*seq, *count))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The line, from the newest, `notification` would be counted in if it
+    /// were pushed, when it repeats the latest message of its topic.
+    pub fn repeated_line(&self, notification: &Notification) -> Option<usize> {
+        match notification {
+            Notification::Publish(msg) => {
+                let (lines, payload) = self.topics.get(msg.topic.as_str())?;
+                let line = *lines.back()?;
+                match *payload == msg.payload {
+                    true => Some(self.lines.range(line + 1..).count()),
+                    false => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+
+    fn publish(payload: &str) -> Notification {
+        Notification::Publish(Message::new("a", payload))
+    }
+
+    fn payloads(history: &History) -> Vec<String> {
+        history
+            .iter()
+            .map(|n| match n {
+                Notification::Publish(msg) => String::from_utf8_lossy(&msg.payload).into_owned(),
+                n => n.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drop_oldest_by_count() {
+        let mut history = History::new(Capacity {
+            count: Some(2),
+            bytes: None,
+        });
+
+        history.push(publish("1"));
+        history.push(publish("2"));
+        history.push(publish("3"));
+
+        assert_eq!(payloads(&history), vec!["3", "2"]);
+        assert_eq!((history.pushed(), history.dropped()), (3, 1));
+    }
+
+    #[test]
+    fn drop_oldest_by_memory() {
+        let big = "x".repeat(1000);
+        let mut history = History::new(Capacity {
+            count: None,
+            bytes: Some(2500),
+        });
+
+        history.push(publish(&big));
+        history.push(publish(&big));
+        assert_eq!(history.len(), 2);
+
+        history.push(publish(&big));
+        assert_eq!(history.len(), 2);
+        assert!(history.bytes() <= 2500);
+
+        history.clear();
+        assert_eq!(history.bytes(), 0);
+    }
+
+    #[test]
+    fn collapse_repeats() {
+        let mut history = History::new(Capacity::default());
+        for (topic, payload) in &[
+            ("a", "1"),
+            ("a", "1"),
+            ("b", "x"),
+            ("a", "1"),
+            ("a", "2"),
+            ("b", "x"),
+            ("a", "2"),
+        ] {
+            history.push(Notification::Publish(Message::new(*topic, *payload)));
+        }
+        history.push(Notification::EndOfInput);
+
+        let lines: Vec<(String, usize)> = collapse(history.iter())
+            .into_iter()
+            .map(|(n, count)| (n.to_string(), count))
+            .collect();
+        let line = |n: Notification, count| (n.to_string(), count);
+
+        assert_eq!(
+            lines,
+            vec![
+                line(Notification::EndOfInput, 1),
+                line(Notification::Publish(Message::new("a", "2")), 2),
+                line(Notification::Publish(Message::new("b", "x")), 2),
+                line(Notification::Publish(Message::new("a", "1")), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn collapse_incrementally() {
+        let mut history = History::new(Capacity {
+            count: Some(4),
+            bytes: None,
+        });
+        let mut collapsed = Collapsed::default();
+
+        for (topic, payload) in &[
+            ("a", "1"),
+            ("a", "1"),
+            ("b", "x"),
+            ("a", "1"),
+            ("a", "2"),
+            ("b", "x"),
+            ("a", "2"),
+            ("a", "1"),
+            ("b", "y"),
+            ("b", "y"),
+        ] {
+            let notification = Notification::Publish(Message::new(*topic, *payload));
+            collapsed.push(history.pushed(), &notification);
+            let first = history.first();
+            for (seq, dropped) in (first..).zip(history.push(notification)) {
+                collapsed.remove(seq, &dropped);
+            }
+
+            let lines: Vec<(String, usize)> = collapsed
+                .lines()
+                .map(|(seq, count)| (history.get(seq).unwrap().to_string(), count))
+                .collect();
+            let expected: Vec<(String, usize)> = collapse(history.iter())
+                .into_iter()
+                .map(|(n, count)| (n.to_string(), count))
+                .collect();
+            assert_eq!(lines, expected);
+        }
+    }
+}
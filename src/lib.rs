@@ -1 +1,38 @@
+//! Building blocks of `mqtt-analyzer`.
+//!
+//! The [`engine`] connects to a broker and produces a stream of
+//! [`message::Notification`]s, which can then be rendered with the
+//! functions in [`format`].
+//!
+//! ```no_run
+//! use mqtt_analyzer::engine::EngineBuilder;
+//! use mqtt_analyzer::format::{format_notification, MessageFormat};
+//! use rumqtt::MqttOptions;
+//!
+//! let engine = EngineBuilder::new(MqttOptions::new("analyzer", "localhost", 1883))
+//!     .subscribe("sensors/#")
+//!     .build()
+//!     .unwrap();
+//!
+//! for notification in engine.notifications.iter() {
+//!     println!("{}", format_notification(MessageFormat::default(), &notification));
+//! }
+//! ```
 
+pub mod capture;
+pub mod config;
+pub mod engine;
+pub mod filter;
+pub mod format;
+pub mod history;
+pub mod message;
+pub mod pcap;
+pub mod replay;
+pub mod series;
+pub mod session;
+pub mod stats;
+pub mod topic;
+pub mod tree;
+
+pub use engine::{Engine, EngineBuilder};
+pub use message::{Message, Notification, QoS};
@@ -1,24 +1,66 @@
 #[macro_use]
 extern crate clap;
-extern crate colored;
-extern crate hex;
 
-use std::io::{self, Write};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use rumqtt::MqttClient;
+use crossbeam_channel::RecvTimeoutError;
+
+use rumqtt::{MqttClient, MqttOptions};
+use unicode_width::UnicodeWidthStr;
+
+use mqtt_analyzer::capture::{Reader, Recorder};
+use mqtt_analyzer::engine::{Engine, EngineBuilder, Records};
+use mqtt_analyzer::filter::Filter;
+use mqtt_analyzer::format::{format_notification, MessageFormat};
+use mqtt_analyzer::message::Notification;
+use mqtt_analyzer::pcap::{self, Importer};
+use mqtt_analyzer::replay::{self, Summary};
+use mqtt_analyzer::session::Session;
+use mqtt_analyzer::stats::{self, SortKey, Stats};
 
 use crate::cli::parse_options;
-use crate::engine::Engine;
-use crate::format::{format_notification, MessageFormat};
-use crate::tui::start_tui;
+use crate::tui::{start_tui, Keymap};
 
 mod cli;
-mod engine;
-mod format;
 mod tui;
 
-fn start_stream(engine: Engine, format_options: MessageFormat) -> Result<(), failure::Error> {
-    for notification in engine.notifications {
+/// Without the TUI, Ctrl-C kills the process: make sure the capture is
+/// complete before exiting.
+fn flush_recording_on_interrupt(engine: &Engine) -> Result<(), failure::Error> {
+    if engine.recording().is_some() {
+        let recorder = engine.recorder();
+        ctrlc::set_handler(move || {
+            if let Ok(mut recorder) = recorder.lock() {
+                if let Some(recorder) = recorder.as_mut() {
+                    let _ = recorder.flush();
+                }
+            }
+            std::process::exit(130);
+        })?;
+    }
+    Ok(())
+}
+
+fn start_stream(
+    engine: Engine,
+    format_options: MessageFormat,
+    filter: Option<Filter>,
+) -> Result<(), failure::Error> {
+    flush_recording_on_interrupt(&engine)?;
+
+    for notification in engine.notifications.iter() {
+        if notification == Notification::EndOfInput {
+            break;
+        }
+
+        if !filter.as_ref().is_none_or(|f| f.selects(&notification)) {
+            continue;
+        }
+
         let line = format_notification(format_options, &notification).to_color_string() + "\n";
         io::stdout().write_all(line.as_bytes()).unwrap();
         io::stdout().flush().unwrap();
@@ -26,28 +68,302 @@ fn start_stream(engine: Engine, format_options: MessageFormat) -> Result<(), fai
     Ok(())
 }
 
+fn format_stats(stats: &Stats, sort: SortKey) -> String {
+    let now = stats.now();
+    let mut rows = vec![stats::COLUMNS.iter().map(|s| s.to_string()).collect()];
+    rows.extend(
+        stats
+            .sorted(sort, false)
+            .into_iter()
+            .map(|(topic, topic_stats)| stats::row(topic, topic_stats, now)),
+    );
+
+    let mut widths = vec![0; stats::COLUMNS.len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(UnicodeWidthStr::width(cell.as_str()));
+        }
+    }
+
+    let mut table = String::new();
+    for row in &rows {
+        let mut cells = row.iter().zip(&widths);
+        if let Some((topic, width)) = cells.next() {
+            let padding = width - UnicodeWidthStr::width(topic.as_str());
+            table += &format!("{}{}", topic, " ".repeat(padding));
+        }
+        for (cell, width) in cells {
+            table += &format!("  {:>width$}", cell, width = width);
+        }
+        table += "\n";
+    }
+    table
+}
+
+fn start_stats(
+    engine: Engine,
+    sort: SortKey,
+    filter: Option<Filter>,
+) -> Result<(), failure::Error> {
+    const REFRESH: Duration = Duration::from_secs(1);
+
+    flush_recording_on_interrupt(&engine)?;
+
+    let tty = termion::is_tty(&io::stdout());
+    let mut stats = Stats::new();
+    let mut last_print = Instant::now();
+
+    let print = |stats: &Stats| {
+        let table = format_stats(stats, sort);
+        let mut stdout = io::stdout();
+        if tty {
+            write!(
+                stdout,
+                "{}{}",
+                termion::clear::All,
+                termion::cursor::Goto(1, 1)
+            )
+            .unwrap();
+        }
+        stdout.write_all(table.as_bytes()).unwrap();
+        if !tty {
+            stdout.write_all(b"\n").unwrap();
+        }
+        stdout.flush().unwrap();
+    };
+
+    loop {
+        let timeout = REFRESH
+            .checked_sub(last_print.elapsed())
+            .unwrap_or_default();
+
+        match engine.notifications.recv_timeout(timeout) {
+            Ok(Notification::Publish(msg)) => {
+                if filter.as_ref().is_none_or(|f| f.matches(&msg)) {
+                    stats.update(&msg);
+                }
+            }
+            Ok(Notification::EndOfInput) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        if last_print.elapsed() >= REFRESH {
+            print(&stats);
+            last_print = Instant::now();
+        }
+    }
+
+    print(&stats);
+    Ok(())
+}
+
+/// Open a capture file, or a pcap or pcapng network capture.
+fn open_input(path: &str, pcap_options: pcap::Options) -> Result<Records, failure::Error> {
+    let mut magic = [0; 4];
+    File::open(path)?.read_exact(&mut magic)?;
+
+    if pcap::is_pcap(&magic) {
+        Ok(Box::new(Importer::open(path, pcap_options)?))
+    } else {
+        Ok(Box::new(Reader::open(path)?))
+    }
+}
+
+fn format_summary(summary: &Summary) -> String {
+    let published = match summary.total {
+        Some(total) => format!("{}/{}", summary.published, total),
+        None => summary.published.to_string(),
+    };
+
+    format!(
+        "{} messages published ({} bytes) in {:.1}s, {} loops, {} skipped, {} failed",
+        published,
+        summary.bytes,
+        summary.elapsed.as_secs_f64(),
+        summary.loops,
+        summary.skipped,
+        summary.failed,
+    )
+}
+
+fn start_replay(mqtt_options: MqttOptions, replay: cli::Replay) -> Result<(), failure::Error> {
+    let (mut client, notifications) = MqttClient::start(mqtt_options)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        ctrlc::set_handler(move || stop.store(true, Ordering::Relaxed))?;
+    }
+
+    let mut last_report = Instant::now();
+    let summary = replay::replay(
+        &replay.path,
+        &replay.options,
+        &mut client,
+        &stop,
+        |summary| {
+            if last_report.elapsed() >= Duration::from_secs(1) {
+                eprint!("\r{}", format_summary(summary));
+                last_report = Instant::now();
+            }
+        },
+    )?;
+
+    // Publishing only queues the messages: wait for the client to send them
+    // before disconnecting.
+    client.shutdown()?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        match notifications.recv_timeout(Duration::from_millis(100)) {
+            Ok(rumqtt::Notification::Disconnection) => break,
+            Err(RecvTimeoutError::Disconnected) => break,
+            _ => {}
+        }
+    }
+
+    eprintln!("\r{}", format_summary(&summary));
+
+    Ok(())
+}
+
+/// Leave out of the restored `session` what the command line sets.
+fn given_options_win(
+    mut session: Session,
+    mode_given: bool,
+    format: Option<MessageFormat>,
+    filter: &Option<Filter>,
+) -> Session {
+    if mode_given {
+        session.tab = None;
+    }
+
+    if let Some(format) = format {
+        let name = format.payload_format.name().to_string();
+        session.formats.clear();
+        for pane in session.stream.panes.iter_mut() {
+            pane.format = name.clone();
+        }
+    }
+
+    if let (Some(filter), Some(pane)) = (filter, session.stream.panes.first_mut()) {
+        pane.filter = filter.to_string();
+    }
+
+    session
+}
+
 fn main() -> Result<(), failure::Error> {
     let cli::Options {
         format: format_options,
+        filter,
         mqtt: mqtt_options,
         subscriptions,
         tui,
         mode,
+        sort,
+        history,
+        record,
+        rotation,
+        input,
+        speed,
+        pcap: pcap_options,
+        replay,
+        config,
+        config_path,
+        restore,
+        mode_given,
+        format_given,
     } = parse_options();
 
-    let mqtt_options = mqtt_options
-        .set_request_channel_capacity(1000)
-        .set_notification_channel_capacity(1000);
+    if let Some(replay) = replay {
+        return start_replay(mqtt_options, replay);
+    }
+
+    let source = match &input {
+        Some(path) => path.clone(),
+        None => {
+            let (host, port) = mqtt_options.broker_address();
+            format!("{}:{}", host, port)
+        }
+    };
+    let client_id = match input {
+        Some(_) => None,
+        None => Some(mqtt_options.client_id()),
+    };
+
+    let keymap = Keymap::from_config(&config).unwrap_or_else(|e| {
+        clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue).exit()
+    });
+
+    let mut builder = match input {
+        Some(path) => {
+            let subscriptions = if subscriptions.is_empty() {
+                vec!["#".to_string()]
+            } else {
+                subscriptions
+            };
 
-    let (client, notifications) = MqttClient::start(mqtt_options).unwrap();
+            EngineBuilder::offline(open_input(&path, pcap_options)?, speed)
+                .subscriptions(subscriptions)
+        }
+        // The TUI must stay responsive: it rather drops messages, and shows
+        // how many, than falls behind the broker.
+        None if tui => EngineBuilder::new(mqtt_options)
+            .subscriptions(subscriptions)
+            .drop_when_full(),
+        None => EngineBuilder::new(mqtt_options).subscriptions(subscriptions),
+    };
 
-    let engine = Engine::new(notifications, client);
+    if let Some(path) = record {
+        builder = builder.recorder(Recorder::create(path, rotation)?);
+    }
+
+    // A session that can't be read is reported, and left as it is instead of
+    // being replaced on exit.
+    let mut session_path = Session::path(&source);
+    let mut session_error = None;
+    let session = match &session_path {
+        Some(path) if path.exists() => match Session::open(path) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                session_error = Some(format!("Session {} not restored: {}", path.display(), e));
+                session_path = None;
+                None
+            }
+        },
+        _ => None,
+    }
+    .map(|session| {
+        given_options_win(
+            session,
+            mode_given,
+            format_given.then_some(format_options),
+            &filter,
+        )
+    });
 
-    engine.subscribe_all(subscriptions);
+    let engine = builder.build()?;
 
     if tui {
-        start_tui(engine, format_options, mode)
+        let options = tui::Options {
+            format: format_options,
+            mode,
+            sort,
+            filter,
+            history,
+            source,
+            client_id,
+            keymap,
+            config,
+            config_path,
+            session: session.filter(|_| restore),
+            session_error,
+            session_path,
+        };
+        start_tui(engine, options)
+    } else if let cli::Mode::Stats = mode {
+        start_stats(engine, sort, filter)
     } else {
-        start_stream(engine, format_options)
+        start_stream(engine, format_options, filter)
     }
 }
@@ -0,0 +1,202 @@
+//! Broker independent representation of what the analyzer receives.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::engine;
+
+/// Quality of service of a published message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QoS {
+    AtMostOnce,
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+impl QoS {
+    pub fn from_u8(value: u8) -> Option<QoS> {
+        match value {
+            0 => Some(QoS::AtMostOnce),
+            1 => Some(QoS::AtLeastOnce),
+            2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            QoS::AtMostOnce => 0,
+            QoS::AtLeastOnce => 1,
+            QoS::ExactlyOnce => 2,
+        }
+    }
+}
+
+impl fmt::Display for QoS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_u8())
+    }
+}
+
+impl From<mqtt311::QoS> for QoS {
+    fn from(qos: mqtt311::QoS) -> QoS {
+        match qos {
+            mqtt311::QoS::AtMostOnce => QoS::AtMostOnce,
+            mqtt311::QoS::AtLeastOnce => QoS::AtLeastOnce,
+            mqtt311::QoS::ExactlyOnce => QoS::ExactlyOnce,
+        }
+    }
+}
+
+impl From<QoS> for mqtt311::QoS {
+    fn from(qos: QoS) -> mqtt311::QoS {
+        match qos {
+            QoS::AtMostOnce => mqtt311::QoS::AtMostOnce,
+            QoS::AtLeastOnce => mqtt311::QoS::AtLeastOnce,
+            QoS::ExactlyOnce => mqtt311::QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// A published message, as seen by the analyzer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub topic: String,
+    pub qos: QoS,
+    pub retain: bool,
+    pub payload: Arc<Vec<u8>>,
+    /// When the message was received by the analyzer.
+    pub timestamp: SystemTime,
+}
+
+impl Message {
+    /// Create a message received now.
+    pub fn new<T, P>(topic: T, payload: P) -> Message
+    where
+        T: Into<String>,
+        P: Into<Vec<u8>>,
+    {
+        Message {
+            topic: topic.into(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+            payload: Arc::new(payload.into()),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    pub fn qos(mut self, qos: QoS) -> Message {
+        self.qos = qos;
+        self
+    }
+
+    pub fn retain(mut self, retain: bool) -> Message {
+        self.retain = retain;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: SystemTime) -> Message {
+        self.timestamp = timestamp;
+        self
+    }
+}
+
+impl From<mqtt311::Publish> for Message {
+    fn from(publish: mqtt311::Publish) -> Message {
+        Message {
+            topic: publish.topic_name,
+            qos: publish.qos.into(),
+            retain: publish.retain,
+            payload: publish.payload,
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+/// Everything the engine forwards to its consumers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Notification {
+    Publish(Message),
+    Reconnection,
+    Disconnection,
+    PubAck(u16),
+    PubRec(u16),
+    PubRel(u16),
+    PubComp(u16),
+    SubAck(u16),
+    /// A message was handed to the client, to be published, with its packet
+    /// identifier when its QoS is 1 or 2.
+    Published(Message, Option<u16>),
+    /// Something went wrong in the engine, which is still running.
+    Error(engine::Error),
+    /// An offline input has no more notifications.
+    EndOfInput,
+}
+
+impl Notification {
+    /// Convert a notification coming from the mqtt client. Returns `None` for
+    /// notifications which carry no information.
+    pub fn from_rumqtt(notification: rumqtt::Notification) -> Option<Notification> {
+        use rumqtt::Notification as N;
+
+        match notification {
+            N::Publish(publish) => Some(Notification::Publish(publish.into())),
+            N::Reconnection => Some(Notification::Reconnection),
+            N::Disconnection => Some(Notification::Disconnection),
+            N::PubAck(pkid) => Some(Notification::PubAck(pkid.0)),
+            N::PubRec(pkid) => Some(Notification::PubRec(pkid.0)),
+            N::PubRel(pkid) => Some(Notification::PubRel(pkid.0)),
+            N::PubComp(pkid) => Some(Notification::PubComp(pkid.0)),
+            N::SubAck(pkid) => Some(Notification::SubAck(pkid.0)),
+            N::None => None,
+        }
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Notification::Publish(msg) => write!(f, "Publish({})", msg.topic),
+            Notification::Reconnection => write!(f, "Reconnection"),
+            Notification::Disconnection => write!(f, "Disconnection"),
+            Notification::PubAck(pkid) => write!(f, "PubAck({})", pkid),
+            Notification::PubRec(pkid) => write!(f, "PubRec({})", pkid),
+            Notification::PubRel(pkid) => write!(f, "PubRel({})", pkid),
+            Notification::PubComp(pkid) => write!(f, "PubComp({})", pkid),
+            Notification::SubAck(pkid) => write!(f, "SubAck({})", pkid),
+            Notification::Published(msg, _) => write!(f, "Published({})", msg.topic),
+            Notification::Error(e) => write!(f, "Error: {}", e),
+            Notification::EndOfInput => write!(f, "End of input"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_from_publish() {
+        let publish = mqtt311::Publish {
+            dup: false,
+            qos: mqtt311::QoS::ExactlyOnce,
+            retain: true,
+            topic_name: "a/b".to_string(),
+            pkid: None,
+            payload: Arc::new(b"ciao".to_vec()),
+        };
+
+        let msg = Message::from(publish);
+
+        assert_eq!(msg.topic, "a/b");
+        assert_eq!(msg.qos, QoS::ExactlyOnce);
+        assert!(msg.retain);
+        assert_eq!(msg.payload.as_slice(), b"ciao");
+    }
+
+    #[test]
+    fn empty_rumqtt_notification_is_dropped() {
+        assert_eq!(Notification::from_rumqtt(rumqtt::Notification::None), None);
+    }
+}
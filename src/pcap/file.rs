@@ -0,0 +1,355 @@
+//! Readers for the classic pcap and the pcapng file formats.
+
+use std::io::{self, Read};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_TSRESOL: u16 = 9;
+
+/// Longest block or frame accepted, far more than any TCP segment.
+const MAX_BLOCK_LEN: usize = 16 << 20;
+
+/// A captured frame, with its data link type.
+pub struct Frame {
+    pub timestamp: SystemTime,
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// Check whether `header`, the first bytes of a file, belongs to a pcap or
+/// pcapng file.
+pub fn is_pcap(header: &[u8]) -> bool {
+    if header.len() < 4 {
+        return false;
+    }
+
+    let magic = [header[0], header[1], header[2], header[3]];
+    let le = u32::from_le_bytes(magic);
+    let be = u32::from_be_bytes(magic);
+
+    [PCAP_MAGIC_MICROS, PCAP_MAGIC_NANOS, PCAPNG_SECTION_HEADER]
+        .iter()
+        .any(|m| *m == le || *m == be)
+}
+
+#[derive(Clone, Copy)]
+enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self {
+            Endianness::Little => u16::from_le_bytes(bytes),
+            Endianness::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// Read exactly `buf.len()` bytes, returning `Ok(false)` at a clean end of
+/// file.
+fn read_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Read a block of `len` bytes, returning `Ok(None)` if the file ends
+/// before. Only what the file holds is allocated, in case it is truncated.
+fn read_block<R: Read>(r: &mut R, len: usize) -> io::Result<Option<Vec<u8>>> {
+    if len > MAX_BLOCK_LEN {
+        return Err(invalid_data("block too long"));
+    }
+
+    let mut buf = vec![];
+    r.take(len as u64).read_to_end(&mut buf)?;
+
+    Ok(Some(buf).filter(|buf| buf.len() == len))
+}
+
+struct Interface {
+    link_type: u32,
+    /// Duration of a timestamp unit, in nanoseconds.
+    resolution: f64,
+}
+
+enum Format {
+    Pcap {
+        endianness: Endianness,
+        link_type: u32,
+        nanos: bool,
+    },
+    Pcapng {
+        endianness: Endianness,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// Reads the frames of a pcap or pcapng file. An error ends the iteration,
+/// as the next frames can't be found anymore.
+pub struct FrameReader<R: Read> {
+    inner: R,
+    format: Format,
+    failed: bool,
+}
+
+/// Read the rest of a section header block, whose type and `len` have
+/// already been read. The byte order magic that follows them tells how to
+/// read the length, and every other field of the section.
+fn read_section_header<R: Read>(r: &mut R, len: [u8; 4]) -> io::Result<Endianness> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+
+    let endianness = if u32::from_le_bytes(magic) == PCAPNG_BYTE_ORDER_MAGIC {
+        Endianness::Little
+    } else if u32::from_be_bytes(magic) == PCAPNG_BYTE_ORDER_MAGIC {
+        Endianness::Big
+    } else {
+        return Err(invalid_data("invalid pcapng byte order magic"));
+    };
+
+    let len = endianness.u32(&len) as usize;
+    if len < 12 {
+        return Err(invalid_data("invalid pcapng section header"));
+    }
+
+    match read_block(r, len - 12)? {
+        Some(_) => Ok(endianness),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+fn parse_tsresol(endianness: Endianness, mut options: &[u8]) -> f64 {
+    while options.len() >= 4 {
+        let code = endianness.u16(&options[0..2]);
+        let len = endianness.u16(&options[2..4]) as usize;
+        let padded = (len + 3) & !3;
+
+        if code == PCAPNG_OPTION_END || options.len() < 4 + len {
+            break;
+        }
+
+        if code == PCAPNG_OPTION_TSRESOL && len >= 1 {
+            let value = options[4];
+            let exponent = i32::from(value & 0x7f);
+            let seconds = if value & 0x80 == 0 {
+                10f64.powi(-exponent)
+            } else {
+                2f64.powi(-exponent)
+            };
+            return seconds * 1e9;
+        }
+
+        options = &options[(4 + padded).min(options.len())..];
+    }
+
+    // Microseconds, the default resolution.
+    1e3
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        inner.read_exact(&mut magic)?;
+
+        let le = u32::from_le_bytes(magic);
+        let be = u32::from_be_bytes(magic);
+
+        let format = if le == PCAPNG_SECTION_HEADER {
+            let mut len = [0; 4];
+            inner.read_exact(&mut len)?;
+
+            Format::Pcapng {
+                endianness: read_section_header(&mut inner, len)?,
+                interfaces: Vec::new(),
+            }
+        } else {
+            let (endianness, nanos) = match (le, be) {
+                (PCAP_MAGIC_MICROS, _) => (Endianness::Little, false),
+                (PCAP_MAGIC_NANOS, _) => (Endianness::Little, true),
+                (_, PCAP_MAGIC_MICROS) => (Endianness::Big, false),
+                (_, PCAP_MAGIC_NANOS) => (Endianness::Big, true),
+                _ => return Err(invalid_data("not a pcap file")),
+            };
+
+            let mut header = [0; 20];
+            inner.read_exact(&mut header)?;
+
+            Format::Pcap {
+                endianness,
+                link_type: endianness.u32(&header[16..20]),
+                nanos,
+            }
+        };
+
+        Ok(FrameReader {
+            inner,
+            format,
+            failed: false,
+        })
+    }
+
+    fn next_pcap(&mut self) -> io::Result<Option<Frame>> {
+        let (endianness, link_type, nanos) = match self.format {
+            Format::Pcap {
+                endianness,
+                link_type,
+                nanos,
+            } => (endianness, link_type, nanos),
+            _ => unreachable!(),
+        };
+
+        let mut header = [0; 16];
+        if !read_or_eof(&mut self.inner, &mut header)? {
+            return Ok(None);
+        }
+
+        let secs = u64::from(endianness.u32(&header[0..4]));
+        let fraction = endianness.u32(&header[4..8]);
+        let captured_len = endianness.u32(&header[8..12]) as usize;
+
+        let data = match read_block(&mut self.inner, captured_len)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        let subsec = if nanos {
+            Duration::from_nanos(u64::from(fraction))
+        } else {
+            Duration::from_micros(u64::from(fraction))
+        };
+
+        Ok(Some(Frame {
+            timestamp: UNIX_EPOCH + Duration::from_secs(secs) + subsec,
+            link_type,
+            data,
+        }))
+    }
+
+    fn next_pcapng(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            let endianness = match self.format {
+                Format::Pcapng { endianness, .. } => endianness,
+                _ => unreachable!(),
+            };
+
+            let mut header = [0; 8];
+            if !read_or_eof(&mut self.inner, &mut header)? {
+                return Ok(None);
+            }
+
+            let block_type = endianness.u32(&header[0..4]);
+
+            if block_type == PCAPNG_SECTION_HEADER {
+                // A new section can change the byte order, and its interfaces
+                // are numbered from zero again.
+                let len = [header[4], header[5], header[6], header[7]];
+                let endianness = read_section_header(&mut self.inner, len)?;
+                self.format = Format::Pcapng {
+                    endianness,
+                    interfaces: Vec::new(),
+                };
+                continue;
+            }
+
+            let len = endianness.u32(&header[4..8]) as usize;
+            if len < 12 {
+                return Err(invalid_data("invalid pcapng block"));
+            }
+
+            let body = match read_block(&mut self.inner, len - 8)? {
+                Some(body) => body,
+                None => return Ok(None),
+            };
+            // Skip the trailing copy of the block length.
+            let body = &body[..body.len() - 4];
+
+            let interfaces = match &mut self.format {
+                Format::Pcapng { interfaces, .. } => interfaces,
+                _ => unreachable!(),
+            };
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION if body.len() >= 8 => {
+                    interfaces.push(Interface {
+                        link_type: u32::from(endianness.u16(&body[0..2])),
+                        resolution: parse_tsresol(endianness, &body[8..]),
+                    });
+                }
+                PCAPNG_ENHANCED_PACKET if body.len() >= 20 => {
+                    let interface = endianness.u32(&body[0..4]) as usize;
+                    let high = u64::from(endianness.u32(&body[4..8]));
+                    let low = u64::from(endianness.u32(&body[8..12]));
+                    let captured_len = endianness.u32(&body[12..16]) as usize;
+                    let data = body.get(20..20 + captured_len).unwrap_or(&body[20..]);
+
+                    if let Some(interface) = interfaces.get(interface) {
+                        let units = (high << 32) | low;
+                        let nanos = (units as f64 * interface.resolution) as u64;
+
+                        return Ok(Some(Frame {
+                            timestamp: UNIX_EPOCH + Duration::from_nanos(nanos),
+                            link_type: interface.link_type,
+                            data: data.to_vec(),
+                        }));
+                    }
+                }
+                PCAPNG_SIMPLE_PACKET if body.len() >= 4 => {
+                    // Simple packets have no timestamp and always belong to
+                    // the first interface.
+                    if let Some(interface) = interfaces.first() {
+                        return Ok(Some(Frame {
+                            timestamp: UNIX_EPOCH,
+                            link_type: interface.link_type,
+                            data: body[4..].to_vec(),
+                        }));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let frame = match self.format {
+            Format::Pcap { .. } => self.next_pcap(),
+            Format::Pcapng { .. } => self.next_pcapng(),
+        };
+        self.failed = frame.is_err();
+
+        frame.transpose()
+    }
+}
@@ -0,0 +1,342 @@
+//! Import MQTT traffic from network captures, like the ones saved by
+//! Wireshark or tcpdump.
+//!
+//! Both pcap and pcapng files are supported. TCP streams on the MQTT ports are
+//! reassembled and decoded, and every PUBLISH packet becomes a record, as if
+//! it were read from a capture file. Control packets can be imported too.
+//! TLS connections cannot be decoded.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::SystemTime;
+
+use mqtt311::Packet;
+
+use crate::capture::Record;
+use crate::message::{Message, Notification};
+
+pub use self::file::is_pcap;
+
+use self::file::{Frame, FrameReader};
+use self::stream::Stream;
+
+mod file;
+mod net;
+mod stream;
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// TCP ports of the MQTT connections to import, on either side.
+    pub ports: Vec<u16>,
+    /// Import acknowledgments and connections too, not only PUBLISH packets.
+    pub control: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            ports: vec![1883],
+            control: false,
+        }
+    }
+}
+
+fn control_notification(packet: Packet) -> Option<Notification> {
+    match packet {
+        Packet::Connack(_) => Some(Notification::Reconnection),
+        Packet::Disconnect => Some(Notification::Disconnection),
+        Packet::Puback(pkid) => Some(Notification::PubAck(pkid.0)),
+        Packet::Pubrec(pkid) => Some(Notification::PubRec(pkid.0)),
+        Packet::Pubrel(pkid) => Some(Notification::PubRel(pkid.0)),
+        Packet::Pubcomp(pkid) => Some(Notification::PubComp(pkid.0)),
+        Packet::Suback(suback) => Some(Notification::SubAck(suback.pkid.0)),
+        _ => None,
+    }
+}
+
+/// Reads the MQTT records of a network capture.
+pub struct Importer<R: Read> {
+    frames: FrameReader<R>,
+    options: Options,
+    streams: HashMap<(SocketAddr, SocketAddr), Stream>,
+    records: VecDeque<Record>,
+}
+
+impl Importer<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P, options: Options) -> io::Result<Self> {
+        Importer::new(BufReader::new(File::open(path)?), options)
+    }
+}
+
+impl<R: Read> Importer<R> {
+    pub fn new(inner: R, options: Options) -> io::Result<Self> {
+        Ok(Importer {
+            frames: FrameReader::new(inner)?,
+            options,
+            streams: HashMap::new(),
+            records: VecDeque::new(),
+        })
+    }
+
+    fn to_record(&self, timestamp: SystemTime, packet: Packet) -> Option<Record> {
+        let notification = match packet {
+            Packet::Publish(publish) => {
+                Notification::Publish(Message::from(publish).timestamp(timestamp))
+            }
+            packet if self.options.control => control_notification(packet)?,
+            _ => return None,
+        };
+
+        Some(Record {
+            timestamp,
+            notification,
+        })
+    }
+
+    fn process(&mut self, frame: Frame) {
+        let segment = match net::decode(frame.link_type, &frame.data) {
+            Some(segment) => segment,
+            None => return,
+        };
+
+        let ports = &self.options.ports;
+        if !ports.contains(&segment.src.port()) && !ports.contains(&segment.dst.port()) {
+            return;
+        }
+
+        let key = (segment.src, segment.dst);
+        let mut stream = self.streams.remove(&key).unwrap_or_default();
+        stream.push(&segment);
+
+        while let Some(packet) = stream.next_packet() {
+            if let Some(record) = self.to_record(frame.timestamp, packet) {
+                self.records.push_back(record);
+            }
+        }
+
+        if !segment.fin && !segment.rst {
+            self.streams.insert(key, stream);
+        }
+    }
+}
+
+impl<R: Read> Iterator for Importer<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(record) = self.records.pop_front() {
+                return Some(Ok(record));
+            }
+
+            match self.frames.next()? {
+                Ok(frame) => self.process(frame),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mqtt311::{MqttWrite, PacketIdentifier, Publish, QoS};
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn encode(packet: &Packet) -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        buf.write_packet(packet).unwrap();
+        buf.into_inner()
+    }
+
+    fn publish(topic: &str, payload: &str) -> Vec<u8> {
+        encode(&Packet::Publish(Publish {
+            dup: false,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            topic_name: topic.to_string(),
+            pkid: None,
+            payload: Arc::new(payload.as_bytes().to_vec()),
+        }))
+    }
+
+    fn ethernet_frame(
+        src_port: u16,
+        dst_port: u16,
+        seq: u32,
+        flags: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+
+        let total_len = (20 + 20 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+        frame.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+
+        frame.extend_from_slice(&src_port.to_be_bytes());
+        frame.extend_from_slice(&dst_port.to_be_bytes());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        buf.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0]);
+        buf.extend_from_slice(&1u32.to_le_bytes());
+
+        for (i, frame) in frames.iter().enumerate() {
+            buf.extend_from_slice(&(1000 + i as u32).to_le_bytes());
+            buf.extend_from_slice(&500u32.to_le_bytes());
+            buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            buf.extend_from_slice(frame);
+        }
+
+        buf
+    }
+
+    fn import(buf: &[u8], options: Options) -> Vec<Record> {
+        Importer::new(buf, options)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn reassemble_out_of_order_segments() {
+        let mut data = publish("a/b", "ciao");
+        data.extend(publish("c/d", "hello"));
+        let (first, second) = data.split_at(7);
+
+        let buf = pcap(&[
+            ethernet_frame(50000, 1883, 99, 0x02, &[]),
+            ethernet_frame(50000, 1883, 100 + first.len() as u32, 0x18, second),
+            ethernet_frame(50000, 1883, 100, 0x18, first),
+        ]);
+
+        let records = import(&buf, Options::default());
+        let topics: Vec<_> = records
+            .iter()
+            .map(|record| match &record.notification {
+                Notification::Publish(msg) => msg.topic.as_str(),
+                _ => "",
+            })
+            .collect();
+
+        assert_eq!(topics, vec!["a/b", "c/d"]);
+        assert_eq!(
+            records[0].timestamp,
+            UNIX_EPOCH + Duration::new(1002, 500_000)
+        );
+    }
+
+    #[test]
+    fn ignore_other_ports() {
+        let buf = pcap(&[ethernet_frame(50000, 8080, 100, 0x18, &publish("a", "b"))]);
+
+        assert!(import(&buf, Options::default()).is_empty());
+    }
+
+    #[test]
+    fn import_control_packets_on_request() {
+        let puback = encode(&Packet::Puback(PacketIdentifier(7)));
+        let buf = pcap(&[ethernet_frame(1883, 50000, 100, 0x18, &puback)]);
+
+        assert!(import(&buf, Options::default()).is_empty());
+
+        let options = Options {
+            control: true,
+            ..Options::default()
+        };
+        let records = import(&buf, options);
+        assert_eq!(records[0].notification, Notification::PubAck(7));
+    }
+
+    fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let len = (12 + body.len()) as u32;
+        let mut block = Vec::new();
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&len.to_le_bytes());
+        block.extend_from_slice(body);
+        block.extend_from_slice(&len.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn read_pcapng() {
+        let frame = ethernet_frame(1883, 50000, 100, 0x18, &publish("a/b", "ciao"));
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.extend_from_slice(&1_000_000u32.to_le_bytes());
+        packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        packet.extend_from_slice(&frame);
+        packet.resize((packet.len() + 3) & !3, 0);
+
+        let mut buf = pcapng_block(
+            0x0a0d_0d0a,
+            &[
+                0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+        );
+        buf.extend(pcapng_block(1, &[1, 0, 0, 0, 0, 0, 0, 0]));
+        buf.extend(pcapng_block(6, &packet));
+
+        let records = import(&buf, Options::default());
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].timestamp, UNIX_EPOCH + Duration::from_secs(1));
+    }
+
+    #[test]
+    fn skip_undecodable_packets() {
+        // A PUBLISH with QoS 3, and a SUBSCRIBE without packet identifier.
+        let buf = pcap(&[
+            ethernet_frame(50000, 1883, 100, 0x18, &[0x36, 5, 0, 3, b'a', b'/', b'b']),
+            ethernet_frame(50000, 1883, 107, 0x18, &[0x82, 1, 0]),
+            ethernet_frame(50000, 1883, 110, 0x18, &publish("c/d", "ciao")),
+        ]);
+
+        let records = import(&buf, Options::default());
+
+        assert_eq!(records.len(), 1);
+        match &records[0].notification {
+            Notification::Publish(msg) => assert_eq!(msg.topic, "c/d"),
+            notification => panic!("unexpected {}", notification),
+        }
+    }
+
+    #[test]
+    fn reject_huge_frames() {
+        let mut buf = pcap(&[]);
+        buf.extend_from_slice(&[0; 8]);
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        buf.extend_from_slice(&[0; 16]);
+
+        let records: Vec<_> = Importer::new(buf.as_slice(), Options::default())
+            .unwrap()
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].is_err());
+    }
+
+    #[test]
+    fn detect_pcap_files() {
+        assert!(is_pcap(&pcap(&[])));
+        assert!(is_pcap(&[0x0a, 0x0d, 0x0d, 0x0a]));
+        assert!(!is_pcap(b"MQTTCAP\x01"));
+    }
+}
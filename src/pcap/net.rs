@@ -0,0 +1,129 @@
+//! Just enough of the link, network and transport layers to get to the TCP
+//! payload of a frame.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+
+const PROTOCOL_TCP: u8 = 6;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+
+pub struct Segment<'a> {
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub seq: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub payload: &'a [u8],
+}
+
+fn be16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Find the IP packet carried by a frame.
+fn ip_packet(link_type: u32, data: &[u8]) -> Option<&[u8]> {
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = be16(data, 12)?;
+            let mut offset = 14;
+            while ethertype == ETHERTYPE_VLAN {
+                ethertype = be16(data, offset + 2)?;
+                offset += 4;
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => data.get(offset..),
+                _ => None,
+            }
+        }
+        // The address family is in host byte order for NULL, but the IP
+        // version in the packet itself is enough to tell them apart.
+        LINKTYPE_NULL | LINKTYPE_LOOP => data.get(4..),
+        LINKTYPE_RAW => Some(data),
+        LINKTYPE_LINUX_SLL => data.get(16..),
+        LINKTYPE_LINUX_SLL2 => data.get(20..),
+        _ => None,
+    }
+}
+
+/// Find the TCP segment carried by an IP packet, with its addresses.
+fn tcp_segment(packet: &[u8]) -> Option<(IpAddr, IpAddr, &[u8])> {
+    match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            let total_len = usize::from(be16(packet, 2)?);
+            let fragment = be16(packet, 6)?;
+
+            // Fragmented packets are not reassembled.
+            if fragment & 0x3fff != 0 || *packet.get(9)? != PROTOCOL_TCP {
+                return None;
+            }
+
+            let src = Ipv4Addr::from(be32(packet, 12)?);
+            let dst = Ipv4Addr::from(be32(packet, 16)?);
+            let end = total_len.min(packet.len());
+
+            Some((src.into(), dst.into(), packet.get(header_len..end)?))
+        }
+        6 => {
+            // Extension headers are not supported.
+            if *packet.get(6)? != PROTOCOL_TCP {
+                return None;
+            }
+
+            let payload_len = usize::from(be16(packet, 4)?);
+            let mut src = [0; 16];
+            src.copy_from_slice(packet.get(8..24)?);
+            let mut dst = [0; 16];
+            dst.copy_from_slice(packet.get(24..40)?);
+            let end = (40 + payload_len).min(packet.len());
+
+            Some((
+                Ipv6Addr::from(src).into(),
+                Ipv6Addr::from(dst).into(),
+                packet.get(40..end)?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Decode the TCP segment carried by a frame, if any.
+pub fn decode(link_type: u32, data: &[u8]) -> Option<Segment<'_>> {
+    let (src_ip, dst_ip, tcp) = tcp_segment(ip_packet(link_type, data)?)?;
+
+    let src_port = be16(tcp, 0)?;
+    let dst_port = be16(tcp, 2)?;
+    let seq = be32(tcp, 4)?;
+    let header_len = usize::from(tcp.get(12)? >> 4) * 4;
+    let flags = *tcp.get(13)?;
+
+    Some(Segment {
+        src: SocketAddr::new(src_ip, src_port),
+        dst: SocketAddr::new(dst_ip, dst_port),
+        seq,
+        syn: flags & TCP_SYN != 0,
+        fin: flags & TCP_FIN != 0,
+        rst: flags & TCP_RST != 0,
+        payload: tcp.get(header_len..)?,
+    })
+}
@@ -0,0 +1,159 @@
+//! Reassembly of a TCP stream, split into MQTT packets.
+
+use std::collections::BTreeMap;
+
+use mqtt311::{MqttRead, Packet};
+
+use super::net::Segment;
+
+/// Segments waiting for a missing one before the stream is considered to
+/// have a hole, which is then skipped.
+const MAX_PENDING_SEGMENTS: usize = 64;
+
+/// One direction of a TCP connection.
+#[derive(Default)]
+pub struct Stream {
+    next_seq: Option<u32>,
+    pending: BTreeMap<u32, Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+enum Frame {
+    /// The whole length of the packet, and the length after its fixed
+    /// header.
+    Complete(usize, usize),
+    Incomplete,
+    Invalid,
+}
+
+/// Length of the MQTT packet at the beginning of `buf`, fixed header
+/// included.
+fn frame_len(buf: &[u8]) -> Frame {
+    let mut remaining_len = 0;
+
+    for (i, byte) in buf.iter().skip(1).take(4).enumerate() {
+        remaining_len |= usize::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Frame::Complete(1 + i + 1 + remaining_len, remaining_len);
+        }
+    }
+
+    if buf.len() >= 5 {
+        Frame::Invalid
+    } else {
+        Frame::Incomplete
+    }
+}
+
+/// Whether a packet with the first byte `first` and `remaining_len` bytes
+/// after its fixed header can be handed to mqtt311, which panics on a
+/// PUBLISH with QoS 3 and on packets too short for their packet identifier.
+fn is_decodable(first: u8, remaining_len: usize) -> bool {
+    let qos = (first >> 1) & 0b11;
+    let min_len = match first >> 4 {
+        // PUBLISH: the length of the topic, and the packet identifier.
+        3 if qos == 3 => return false,
+        3 if qos > 0 => 4,
+        3 => 2,
+        // SUBSCRIBE, SUBACK and UNSUBSCRIBE: the packet identifier.
+        8..=10 => 2,
+        _ => 0,
+    };
+
+    remaining_len >= min_len
+}
+
+impl Stream {
+    pub fn push(&mut self, segment: &Segment) {
+        if segment.syn {
+            self.next_seq = Some(segment.seq.wrapping_add(1));
+            self.pending.clear();
+            self.buffer.clear();
+        }
+
+        // Without the handshake, the stream starts at the first segment seen.
+        self.next_seq.get_or_insert(segment.seq);
+
+        if !segment.payload.is_empty() {
+            self.pending.insert(segment.seq, segment.payload.to_vec());
+        }
+
+        self.reassemble();
+    }
+
+    fn reassemble(&mut self) {
+        while let Some(next_seq) = self.next_seq {
+            // How many bytes of each pending segment have already been
+            // received, negative for segments beyond a hole.
+            let overlapping = self
+                .pending
+                .keys()
+                .map(|seq| (*seq, next_seq.wrapping_sub(*seq) as i32))
+                .find(|(_, received)| *received >= 0);
+
+            let (seq, received) = match overlapping {
+                Some(overlapping) => overlapping,
+                None => break,
+            };
+
+            let data = self.pending.remove(&seq).unwrap();
+            let received = received as usize;
+
+            if received < data.len() {
+                self.buffer.extend_from_slice(&data[received..]);
+                self.next_seq = Some(next_seq.wrapping_add((data.len() - received) as u32));
+            }
+        }
+
+        if self.pending.len() > MAX_PENDING_SEGMENTS {
+            self.skip_hole();
+        }
+    }
+
+    /// Give up waiting for a missing segment: restart from the first segment
+    /// after it. The packet being reassembled is lost.
+    fn skip_hole(&mut self) {
+        if let Some(next_seq) = self.next_seq {
+            let first = self
+                .pending
+                .keys()
+                .min_by_key(|seq| seq.wrapping_sub(next_seq))
+                .cloned();
+
+            self.buffer.clear();
+            self.next_seq = first;
+            self.reassemble();
+        }
+    }
+
+    /// Decode the next complete MQTT packet. Malformed packets are dropped.
+    pub fn next_packet(&mut self) -> Option<Packet> {
+        loop {
+            let len = match frame_len(&self.buffer) {
+                Frame::Complete(_, remaining_len)
+                    if !is_decodable(self.buffer[0], remaining_len) =>
+                {
+                    // Not a packet, so not at the beginning of one either.
+                    self.buffer.clear();
+                    return None;
+                }
+                Frame::Complete(len, _) if len <= self.buffer.len() => len,
+                Frame::Complete(..) | Frame::Incomplete => return None,
+                Frame::Invalid => {
+                    // Not at the beginning of a packet, probably because the
+                    // capture started in the middle of the connection.
+                    self.buffer.clear();
+                    return None;
+                }
+            };
+
+            let packet = (&self.buffer[..len]).read_packet();
+            self.buffer.drain(..len);
+
+            if let Ok(packet) = packet {
+                return Some(packet);
+            }
+        }
+    }
+}
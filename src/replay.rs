@@ -0,0 +1,380 @@
+//! Publish the messages of a capture file again, to reproduce a recorded
+//! session on another broker.
+
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::capture::{Pacer, Reader, Record};
+use crate::message::{Message, Notification};
+use crate::topic;
+
+/// Replace the `from` prefix of topics with `to`. The prefix is made of whole
+/// levels: `a/b` rewrites `a/b` and `a/b/c`, but not `a/bc`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rewrite {
+    pub from: String,
+    pub to: String,
+}
+
+impl Rewrite {
+    pub fn apply(&self, topic: &str) -> String {
+        match topic.strip_prefix(self.from.as_str()) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') || self.from.ends_with('/') => {
+                format!("{}{}", self.to, rest)
+            }
+            _ => topic.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Speed factor, `1.0` preserves the original timing. Without it, messages
+    /// are published as fast as possible.
+    pub speed: Option<f64>,
+    pub rewrite: Option<Rewrite>,
+    /// Only the messages matching one of these filters are published. All of
+    /// them if empty.
+    pub filters: Vec<String>,
+    /// Start again from the beginning at the end of the capture, until
+    /// stopped.
+    pub repeat: bool,
+}
+
+impl Options {
+    fn is_selected(&self, msg: &Message) -> bool {
+        self.filters.is_empty()
+            || self
+                .filters
+                .iter()
+                .any(|filter| topic::matches(filter, &msg.topic))
+    }
+}
+
+/// How far a replay got.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    /// Selected messages in a single pass over the capture, known once the
+    /// first pass is complete.
+    pub total: Option<u64>,
+    pub published: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub bytes: u64,
+    /// Passes completed over the whole capture.
+    pub loops: u64,
+    pub elapsed: Duration,
+}
+
+/// Something that can publish messages, usually a `rumqtt::MqttClient`.
+pub trait Publisher {
+    fn publish(&mut self, msg: &Message) -> Result<(), failure::Error>;
+}
+
+impl Publisher for rumqtt::MqttClient {
+    fn publish(&mut self, msg: &Message) -> Result<(), failure::Error> {
+        rumqtt::MqttClient::publish(
+            self,
+            msg.topic.as_str(),
+            msg.qos.into(),
+            msg.retain,
+            msg.payload.to_vec(),
+        )?;
+        Ok(())
+    }
+}
+
+fn messages<P: AsRef<Path>>(
+    path: P,
+) -> Result<impl Iterator<Item = io::Result<Message>>, failure::Error> {
+    let messages = Reader::open(path)?.filter_map(|record| match record {
+        Ok(Record {
+            notification: Notification::Publish(msg),
+            ..
+        }) => Some(Ok(msg)),
+        Ok(_) => None,
+        Err(err) => Some(Err(err)),
+    });
+
+    Ok(messages)
+}
+
+/// Publish every selected message of the capture at `path` with `publisher`.
+///
+/// `progress` is called after every message. The replay ends at the end of
+/// the capture, or as soon as `stop` is set, even while waiting for the next
+/// message. It fails if the capture can't be read.
+pub fn replay<P, F>(
+    path: P,
+    options: &Options,
+    publisher: &mut dyn Publisher,
+    stop: &AtomicBool,
+    mut progress: F,
+) -> Result<Summary, failure::Error>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Summary),
+{
+    let path = path.as_ref();
+    let started = Instant::now();
+    let mut pacer = options.speed.map(Pacer::new);
+
+    let mut summary = Summary::default();
+
+    loop {
+        let mut selected = 0;
+
+        for msg in messages(path)? {
+            let mut msg = msg?;
+
+            if stop.load(Ordering::Relaxed) {
+                summary.elapsed = started.elapsed();
+                return Ok(summary);
+            }
+
+            if !options.is_selected(&msg) {
+                summary.skipped += 1;
+                continue;
+            }
+            selected += 1;
+
+            if let Some(pacer) = &mut pacer {
+                if !pacer.wait_unless(msg.timestamp, stop) {
+                    summary.elapsed = started.elapsed();
+                    return Ok(summary);
+                }
+            }
+
+            if let Some(rewrite) = &options.rewrite {
+                msg.topic = rewrite.apply(&msg.topic);
+            }
+
+            match publisher.publish(&msg) {
+                Ok(()) => {
+                    summary.published += 1;
+                    summary.bytes += msg.payload.len() as u64;
+                }
+                Err(_) => summary.failed += 1,
+            }
+
+            summary.elapsed = started.elapsed();
+            progress(&summary);
+        }
+
+        summary.loops += 1;
+        summary.total = Some(selected);
+
+        if !options.repeat || selected == 0 || stop.load(Ordering::Relaxed) {
+            break;
+        }
+
+        if let Some(pacer) = &mut pacer {
+            pacer.reset();
+        }
+    }
+
+    summary.elapsed = started.elapsed();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::capture::{write_header, write_record};
+
+    /// Publishes to a list, failing for the topics starting with `fail`.
+    #[derive(Default)]
+    struct MockPublisher {
+        topics: Vec<String>,
+    }
+
+    impl Publisher for MockPublisher {
+        fn publish(&mut self, msg: &Message) -> Result<(), failure::Error> {
+            if msg.topic.starts_with("fail") {
+                return Err(failure::err_msg("refused"));
+            }
+            self.topics.push(msg.topic.clone());
+            Ok(())
+        }
+    }
+
+    /// Write a capture of `topics`, one every `interval`, to a new file.
+    fn capture(topics: &[&str], interval: Duration) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("mqtt-analyzer-{}", uuid::Uuid::new_v4()));
+        let mut buf = Vec::new();
+        write_header(&mut buf).unwrap();
+        for (i, &topic) in topics.iter().enumerate() {
+            let msg = Message::new(topic, "ciao").timestamp(UNIX_EPOCH + interval * i as u32);
+            write_record(&mut buf, &Record::new(Notification::Publish(msg))).unwrap();
+        }
+        fs::write(&path, buf).unwrap();
+        path
+    }
+
+    #[test]
+    fn rewrite_prefix() {
+        let rewrite = Rewrite {
+            from: "site/".to_string(),
+            to: "lab/".to_string(),
+        };
+
+        assert_eq!(rewrite.apply("site/a/b"), "lab/a/b");
+        assert_eq!(rewrite.apply("other/site/a"), "other/site/a");
+
+        let rewrite = Rewrite {
+            from: "a/b".to_string(),
+            to: "c".to_string(),
+        };
+
+        assert_eq!(rewrite.apply("a/b"), "c");
+        assert_eq!(rewrite.apply("a/b/d"), "c/d");
+        assert_eq!(rewrite.apply("a/bc/d"), "a/bc/d");
+    }
+
+    #[test]
+    fn select_by_filters() {
+        let mut options = Options::default();
+        let msg = Message::new("a/b", "ciao");
+
+        assert!(options.is_selected(&msg));
+
+        options.filters = vec!["c/#".to_string()];
+        assert!(!options.is_selected(&msg));
+
+        options.filters.push("a/+".to_string());
+        assert!(options.is_selected(&msg));
+    }
+
+    #[test]
+    fn replay_once() {
+        let path = capture(&["a/b", "fail/c", "a/d", "e"], Duration::from_secs(0));
+        let options = Options {
+            rewrite: Some(Rewrite {
+                from: "a".to_string(),
+                to: "z".to_string(),
+            }),
+            filters: vec!["a/#".to_string(), "fail/#".to_string()],
+            ..Options::default()
+        };
+        let mut publisher = MockPublisher::default();
+
+        let summary = replay(
+            &path,
+            &options,
+            &mut publisher,
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(publisher.topics, vec!["z/b", "z/d"]);
+        assert_eq!(summary.total, Some(3));
+        assert_eq!(summary.published, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.bytes, 8);
+        assert_eq!(summary.loops, 1);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replay_with_speed() {
+        let path = capture(&["a", "b", "c"], Duration::from_millis(100));
+        let options = Options {
+            speed: Some(2.0),
+            ..Options::default()
+        };
+
+        let summary = replay(
+            &path,
+            &options,
+            &mut MockPublisher::default(),
+            &AtomicBool::new(false),
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(summary.published, 3);
+        assert!(summary.elapsed >= Duration::from_millis(100));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn repeat_until_stopped() {
+        let path = capture(&["a", "b"], Duration::from_secs(0));
+        let options = Options {
+            repeat: true,
+            ..Options::default()
+        };
+        let stop = AtomicBool::new(false);
+        let mut publisher = MockPublisher::default();
+
+        let summary = replay(&path, &options, &mut publisher, &stop, |summary| {
+            if summary.published == 5 {
+                stop.store(true, Ordering::Relaxed);
+            }
+        })
+        .unwrap();
+
+        assert_eq!(publisher.topics, vec!["a", "b", "a", "b", "a"]);
+        assert_eq!(summary.total, Some(2));
+        assert_eq!(summary.loops, 2);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn stop_while_waiting() {
+        let path = capture(&["a", "b"], Duration::from_secs(60));
+        let options = Options {
+            speed: Some(1.0),
+            ..Options::default()
+        };
+        let stop = AtomicBool::new(false);
+
+        let summary = replay(
+            &path,
+            &options,
+            &mut MockPublisher::default(),
+            &stop,
+            |_| stop.store(true, Ordering::Relaxed),
+        )
+        .unwrap();
+
+        assert_eq!(summary.published, 1);
+        assert!(summary.elapsed < Duration::from_secs(1));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn fail_on_read_errors() {
+        let path = capture(&["a"], Duration::from_secs(0));
+        let mut buf = fs::read(&path).unwrap();
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        buf.extend_from_slice(&[0; 16]);
+        fs::write(&path, buf).unwrap();
+        let mut publisher = MockPublisher::default();
+
+        let result = replay(
+            &path,
+            &Options::default(),
+            &mut publisher,
+            &AtomicBool::new(false),
+            |_| {},
+        );
+
+        assert!(result.is_err());
+        assert_eq!(publisher.topics, vec!["a"]);
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,301 @@
+//! Numeric values of topics over time, for charts.
+//!
+//! The value of a message is its payload read as a number, or the value at a
+//! path in its JSON payload. A path names the keys of the objects and the
+//! indexes of the arrays to go through, like `sensors[0].temp` or
+//! `$.sensors.0.temp`.
+//!
+//! ```
+//! use mqtt_analyzer::series::ValuePath;
+//!
+//! let path: ValuePath = "sensors[1].temp".parse().unwrap();
+//!
+//! assert_eq!(path.value(br#"{"sensors": [{}, {"temp": 21.5}]}"#), Some(21.5));
+//! assert_eq!(path.value(b"21.5"), None);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use failure::Fail;
+use serde_json::Value;
+
+use crate::message::Message;
+
+/// How long the values are kept, the longest window of a chart.
+pub const RETENTION: Duration = Duration::from_secs(3600);
+
+/// Values kept for each series, whatever their age.
+const MAX_POINTS: usize = 10_000;
+
+fn secs(t: SystemTime) -> f64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid JSON path: {:?}", self.0)
+    }
+}
+
+impl Fail for ParseError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    /// Key of an object, or index of an array when it is a number.
+    Key(String),
+    Index(usize),
+}
+
+/// Where the value is in a JSON payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValuePath {
+    text: String,
+    steps: Vec<Step>,
+}
+
+impl ValuePath {
+    /// The number at the path in `payload`. Strings holding a number and
+    /// booleans count as numbers too.
+    pub fn value(&self, payload: &[u8]) -> Option<f64> {
+        let root: Value = serde_json::from_slice(payload).ok()?;
+        let value = self
+            .steps
+            .iter()
+            .try_fold(&root, |value, step| match (step, value) {
+                (Step::Key(key), Value::Object(map)) => map.get(key),
+                (Step::Key(key), Value::Array(items)) => items.get(key.parse::<usize>().ok()?),
+                (Step::Index(i), Value::Array(items)) => items.get(*i),
+                _ => None,
+            })?;
+
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            _ => None,
+        }
+        .filter(|v| v.is_finite())
+    }
+}
+
+impl FromStr for ValuePath {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseError(s.to_string());
+        let mut steps = vec![];
+        let mut rest = s.strip_prefix('$').unwrap_or(s);
+        let mut first = !s.starts_with('$');
+
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('[') {
+                let end = tail.find(']').ok_or_else(error)?;
+                let index = tail[..end].trim().parse().map_err(|_| error())?;
+                steps.push(Step::Index(index));
+                rest = &tail[end + 1..];
+            } else {
+                let tail = if first {
+                    rest
+                } else {
+                    rest.strip_prefix('.').ok_or_else(error)?
+                };
+                let end = tail.find(['.', '[']).unwrap_or(tail.len());
+                if end == 0 {
+                    return Err(error());
+                }
+                steps.push(Step::Key(tail[..end].to_string()));
+                rest = &tail[end..];
+            }
+            first = false;
+        }
+
+        Ok(ValuePath {
+            text: s.to_string(),
+            steps,
+        })
+    }
+}
+
+impl fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// The value of a message: the number at `path` in the payload, or the whole
+/// payload read as a number.
+pub fn value(payload: &[u8], path: Option<&ValuePath>) -> Option<f64> {
+    match path {
+        Some(path) => path.value(payload),
+        None => std::str::from_utf8(payload)
+            .ok()?
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite()),
+    }
+}
+
+/// Lowest, highest, average and latest values of a series.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub last: f64,
+}
+
+/// Values of a topic, in the order they were received.
+#[derive(Clone, Debug)]
+pub struct Series {
+    pub topic: String,
+    pub path: Option<ValuePath>,
+    /// Timestamps, in seconds since the epoch, with the values.
+    points: VecDeque<(f64, f64)>,
+}
+
+impl Series {
+    pub fn new(topic: &str, path: Option<ValuePath>) -> Series {
+        Series {
+            topic: topic.to_string(),
+            path,
+            points: VecDeque::new(),
+        }
+    }
+
+    /// The topic, followed by the path if there is one.
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => format!("{} {}", self.topic, path),
+            None => self.topic.clone(),
+        }
+    }
+
+    /// Add the value of `msg`, if it is about the topic and has a value.
+    /// Return whether it had one.
+    pub fn update(&mut self, msg: &Message) -> bool {
+        if msg.topic != self.topic {
+            return false;
+        }
+
+        let value = match value(&msg.payload, self.path.as_ref()) {
+            Some(value) => value,
+            None => return false,
+        };
+
+        let t = secs(msg.timestamp);
+        self.points.push_back((t, value));
+
+        let oldest = t - RETENTION.as_secs_f64();
+        while self
+            .points
+            .front()
+            .is_some_and(|(t, _)| *t < oldest || self.points.len() > MAX_POINTS)
+        {
+            self.points.pop_front();
+        }
+
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// The values of the `window` before `now`, with their age in seconds
+    /// as a negative number.
+    pub fn window(&self, now: SystemTime, window: Duration) -> Vec<(f64, f64)> {
+        let now = secs(now);
+        let start = now - window.as_secs_f64();
+
+        self.points
+            .iter()
+            .filter(|(t, _)| *t >= start && *t <= now)
+            .map(|(t, value)| (t - now, *value))
+            .collect()
+    }
+
+    /// The summary of the values of the `window` before `now`, if there is
+    /// any.
+    pub fn summary(&self, now: SystemTime, window: Duration) -> Option<Summary> {
+        let points = self.window(now, window);
+        let (_, last) = *points.last()?;
+        let values = points.iter().map(|(_, value)| *value);
+
+        Some(Summary {
+            min: values.clone().fold(f64::INFINITY, f64::min),
+            max: values.clone().fold(f64::NEG_INFINITY, f64::max),
+            avg: values.sum::<f64>() / points.len() as f64,
+            last,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(topic: &str, payload: &str, secs: u64) -> Message {
+        let mut msg = Message::new(topic, payload);
+        msg.timestamp = UNIX_EPOCH + Duration::from_secs(secs);
+        msg
+    }
+
+    #[test]
+    fn parse_value_paths() {
+        let payload = br#"{"a": {"b": [1, {"c": "2.5"}]}, "on": true}"#;
+        let value = |path: &str| path.parse::<ValuePath>().unwrap().value(payload);
+
+        assert_eq!(value("a.b[0]"), Some(1.0));
+        assert_eq!(value("$.a.b.1.c"), Some(2.5));
+        assert_eq!(value("on"), Some(1.0));
+        assert_eq!(value("a.b"), None);
+        assert_eq!(value("a.x"), None);
+
+        assert!("a..b".parse::<ValuePath>().is_err());
+        assert!("a[x]".parse::<ValuePath>().is_err());
+        assert!("a[1".parse::<ValuePath>().is_err());
+    }
+
+    #[test]
+    fn plain_values() {
+        assert_eq!(value(b" 21.5\n", None), Some(21.5));
+        assert_eq!(value(b"-3", None), Some(-3.0));
+        assert_eq!(value(b"NaN", None), None);
+        assert_eq!(value(b"warm", None), None);
+    }
+
+    #[test]
+    fn summarize_the_window() {
+        let mut series = Series::new("t", None);
+
+        assert!(series.update(&message("t", "4", 100)));
+        assert!(series.update(&message("t", "1", 150)));
+        assert!(series.update(&message("t", "7", 170)));
+        assert!(!series.update(&message("t", "x", 175)));
+        assert!(!series.update(&message("u", "1", 175)));
+
+        let now = UNIX_EPOCH + Duration::from_secs(180);
+        let window = Duration::from_secs(60);
+
+        assert_eq!(series.window(now, window), vec![(-30.0, 1.0), (-10.0, 7.0)]);
+        assert_eq!(
+            series.summary(now, window),
+            Some(Summary {
+                min: 1.0,
+                max: 7.0,
+                avg: 4.0,
+                last: 7.0
+            })
+        );
+        assert_eq!(series.summary(now, Duration::from_secs(5)), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! State of the TUI saved when it exits, to be restored when it is started
+//! again with the same broker.
+//!
+//! Each broker, or input file, has its own session in the state directory,
+//! written in TOML, like:
+//!
+//! ```toml
+//! tab = "stream"
+//!
+//! [subscriptions]
+//! "sensors/#" = 1
+//!
+//! [formats]
+//! retained = "json"
+//!
+//! [stream]
+//! split = "horizontal"
+//! focus = 0
+//!
+//! [[stream.panes]]
+//! filter = "sensors/#"
+//! format = "text"
+//! compact = true
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{state_dir, Error};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Session {
+    /// Mode of the current tab, like `stream`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tab: Option<String>,
+    /// QoS of the subscriptions, by filter.
+    pub subscriptions: BTreeMap<String, u8>,
+    /// Payload format of the tabs, by mode.
+    pub formats: BTreeMap<String, String>,
+    /// Payload formats chosen for some topics.
+    pub topic_formats: BTreeMap<String, String>,
+    pub stream: Stream,
+}
+
+/// Layout of the panes of the stream tab.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Stream {
+    /// `horizontal` for panes side by side, `vertical` for panes one above
+    /// the other.
+    pub split: String,
+    /// Index of the focused pane.
+    pub focus: usize,
+    pub panes: Vec<Pane>,
+}
+
+impl Default for Stream {
+    fn default() -> Stream {
+        Stream {
+            split: "horizontal".to_string(),
+            focus: 0,
+            panes: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pane {
+    pub filter: String,
+    pub format: String,
+    pub compact: bool,
+}
+
+/// FNV-1a hash of `bytes`, stable across versions and platforms, unlike the
+/// hashers of the standard library.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The name of the file of the session with `profile`, keeping only the
+/// characters safe in a file name. The hash of the whole `profile` tells
+/// apart the profiles only differing by the characters replaced.
+fn file_name(profile: &str) -> String {
+    let name: String = profile
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+
+    format!("{}-{:016x}.toml", name, fnv1a(profile.as_bytes()))
+}
+
+impl Session {
+    /// Where the session with `profile`, the broker address or the input
+    /// file, is saved: in the `sessions` directory of the state directory.
+    pub fn path(profile: &str) -> Option<PathBuf> {
+        Some(state_dir()?.join("sessions").join(file_name(profile)))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Session, Error> {
+        let path = path.as_ref();
+        let text =
+            fs::read_to_string(path).map_err(|e| Error::Io(path.to_owned(), e.to_string()))?;
+
+        toml::from_str(&text).map_err(|e| Error::Parse(e.to_string()))
+    }
+
+    /// Write the session to `path`, replacing the file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let error = |e: &dyn fmt::Display| Error::Write(path.to_owned(), e.to_string());
+        let text = toml::to_string(self).map_err(|e| error(&e))?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| error(&e))?;
+        }
+        fs::write(path, text).map_err(|e| error(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn file_names() {
+        assert!(file_name("localhost:1883").starts_with("localhost_1883-"));
+        assert!(file_name("/tmp/a b.mqttcap").starts_with("_tmp_a_b.mqttcap-"));
+        assert!(file_name("a:b").ends_with(".toml"));
+        assert_ne!(file_name("a:b"), file_name("a_b"));
+        assert_eq!(file_name("a:b"), file_name("a:b"));
+    }
+
+    #[test]
+    fn save_and_open() {
+        let path = env::temp_dir()
+            .join(format!("mqtt-analyzer-session-{}", std::process::id()))
+            .join("session.toml");
+
+        let mut session = Session {
+            tab: Some("stream".to_string()),
+            ..Session::default()
+        };
+        session.subscriptions.insert("a/#".to_string(), 2);
+        session
+            .formats
+            .insert("retained".to_string(), "json".to_string());
+        session.stream.panes = vec![
+            Pane {
+                filter: "a/#".to_string(),
+                format: "text".to_string(),
+                compact: true,
+            },
+            Pane::default(),
+        ];
+        session.stream.focus = 1;
+
+        session.save(&path).unwrap();
+        assert_eq!(Session::open(&path).unwrap(), session);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(Session::open(&path).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Per topic statistics of the received messages.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::format::format_age;
+use crate::message::Message;
+
+/// Time constant of the exponentially weighted moving average rate.
+const EWMA_TAU: f64 = 10.0;
+
+/// Seconds covered by the one minute rate.
+const WINDOW_SECS: u64 = 60;
+
+fn secs(t: SystemTime) -> f64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[derive(Clone, Debug)]
+pub struct TopicStats {
+    pub count: u64,
+    pub total_bytes: u64,
+    pub min_size: usize,
+    pub max_size: usize,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    /// Whether the broker holds a retained message for the topic, as far as
+    /// the received messages tell.
+    pub retained: bool,
+    /// Messages received in each of the last seconds, oldest first.
+    buckets: VecDeque<(u64, u64)>,
+    ewma: f64,
+}
+
+impl TopicStats {
+    fn new(msg: &Message) -> TopicStats {
+        let size = msg.payload.len();
+
+        TopicStats {
+            count: 0,
+            total_bytes: 0,
+            min_size: size,
+            max_size: size,
+            first_seen: msg.timestamp,
+            last_seen: msg.timestamp,
+            retained: false,
+            buckets: VecDeque::new(),
+            ewma: 0.0,
+        }
+    }
+
+    fn update(&mut self, msg: &Message) {
+        let size = msg.payload.len();
+
+        self.ewma = self.ewma_at(msg.timestamp) + 1.0 / EWMA_TAU;
+        self.count += 1;
+        self.total_bytes += size as u64;
+        self.min_size = self.min_size.min(size);
+        self.max_size = self.max_size.max(size);
+        self.first_seen = self.first_seen.min(msg.timestamp);
+        self.last_seen = self.last_seen.max(msg.timestamp);
+
+        if msg.retain {
+            // An empty retained message clears the retained one.
+            self.retained = size > 0;
+        }
+
+        let second = secs(msg.timestamp) as u64;
+        match self.buckets.back_mut() {
+            Some((s, count)) if *s == second => *count += 1,
+            _ => self.buckets.push_back((second, 1)),
+        }
+
+        while self.buckets.len() as u64 > WINDOW_SECS + 1 {
+            self.buckets.pop_front();
+        }
+    }
+
+    fn ewma_at(&self, now: SystemTime) -> f64 {
+        let elapsed = now
+            .duration_since(self.last_seen)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        self.ewma * (-elapsed / EWMA_TAU).exp()
+    }
+
+    fn count_between(&self, from: u64, to: u64) -> u64 {
+        self.buckets
+            .iter()
+            .filter(|(s, _)| *s >= from && *s < to)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    pub fn average_size(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total_bytes as f64 / self.count as f64
+        }
+    }
+
+    /// Messages received during the last complete second before `now`.
+    pub fn rate_1s(&self, now: SystemTime) -> f64 {
+        let now = secs(now) as u64;
+        self.count_between(now.saturating_sub(1), now) as f64
+    }
+
+    /// Messages per second, averaged over the last complete minute before
+    /// `now`.
+    pub fn rate_1m(&self, now: SystemTime) -> f64 {
+        let now = secs(now) as u64;
+        self.count_between(now.saturating_sub(WINDOW_SECS), now) as f64 / WINDOW_SECS as f64
+    }
+
+    /// Messages received in each of the `seconds` complete seconds before
+    /// `now`, oldest first. Only the last minute is known.
+    pub fn counts(&self, now: SystemTime, seconds: u64) -> Vec<u64> {
+        let now = secs(now) as u64;
+        let start = now.saturating_sub(seconds.min(WINDOW_SECS));
+
+        (start..now).map(|s| self.count_between(s, s + 1)).collect()
+    }
+
+    /// Messages per second, as an exponentially weighted moving average.
+    pub fn rate_ewma(&self, now: SystemTime) -> f64 {
+        self.ewma_at(now)
+    }
+}
+
+/// Column the statistics are sorted by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Topic,
+    Count,
+    Rate,
+    Bytes,
+    Size,
+    LastSeen,
+}
+
+impl SortKey {
+    pub const ALL: &'static [SortKey] = &[
+        SortKey::Topic,
+        SortKey::Count,
+        SortKey::Rate,
+        SortKey::Bytes,
+        SortKey::Size,
+        SortKey::LastSeen,
+    ];
+
+    pub fn next(self) -> SortKey {
+        let index = SortKey::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        SortKey::ALL[(index + 1) % SortKey::ALL.len()]
+    }
+
+    fn compare(self, a: &TopicStats, b: &TopicStats, now: SystemTime) -> Ordering {
+        let by_f64 = |x: f64, y: f64| x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+
+        match self {
+            SortKey::Topic => Ordering::Equal,
+            SortKey::Count => a.count.cmp(&b.count),
+            SortKey::Rate => by_f64(a.rate_ewma(now), b.rate_ewma(now)),
+            SortKey::Bytes => a.total_bytes.cmp(&b.total_bytes),
+            SortKey::Size => by_f64(a.average_size(), b.average_size()),
+            SortKey::LastSeen => a.last_seen.cmp(&b.last_seen),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "topic" => Ok(SortKey::Topic),
+            "count" => Ok(SortKey::Count),
+            "rate" => Ok(SortKey::Rate),
+            "bytes" => Ok(SortKey::Bytes),
+            "size" => Ok(SortKey::Size),
+            "last" => Ok(SortKey::LastSeen),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            SortKey::Topic => "topic",
+            SortKey::Count => "count",
+            SortKey::Rate => "rate",
+            SortKey::Bytes => "bytes",
+            SortKey::Size => "size",
+            SortKey::LastSeen => "last",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Statistics of every topic.
+///
+/// Rates are measured on the clock of the messages, so that a capture read
+/// at its original speed gives the same rates as the live traffic.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    topics: BTreeMap<String, TopicStats>,
+    /// Timestamp of the latest message, and when it was received.
+    clock: Option<(SystemTime, Instant)>,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    pub fn update(&mut self, msg: &Message) {
+        self.topics
+            .entry(msg.topic.clone())
+            .or_insert_with(|| TopicStats::new(msg))
+            .update(msg);
+
+        if self.clock.is_none_or(|(t, _)| msg.timestamp >= t) {
+            self.clock = Some((msg.timestamp, Instant::now()));
+        }
+    }
+
+    /// The current time on the clock of the messages.
+    pub fn now(&self) -> SystemTime {
+        match self.clock {
+            Some((timestamp, received)) => timestamp + received.elapsed(),
+            None => SystemTime::now(),
+        }
+    }
+
+    pub fn get(&self, topic: &str) -> Option<&TopicStats> {
+        self.topics.get(topic)
+    }
+
+    pub fn len(&self) -> usize {
+        self.topics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.topics.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.topics.clear();
+    }
+
+    /// Topics with their statistics, sorted by `key`. Ties are sorted by
+    /// topic. Numeric columns are sorted from the highest value, unless
+    /// `reverse` is set.
+    pub fn sorted(&self, key: SortKey, reverse: bool) -> Vec<(&str, &TopicStats)> {
+        let now = self.now();
+        let mut topics: Vec<_> = self
+            .topics
+            .iter()
+            .map(|(topic, stats)| (topic.as_str(), stats))
+            .collect();
+
+        topics.sort_by(|(topic_a, a), (topic_b, b)| {
+            let order = match key {
+                SortKey::Topic => topic_a.cmp(topic_b),
+                _ => key.compare(b, a, now).then_with(|| topic_a.cmp(topic_b)),
+            };
+
+            if reverse {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+
+        topics
+    }
+}
+
+/// Titles of the columns of `row`.
+pub const COLUMNS: &[&str] = &[
+    "Topic", "Count", "1s", "1m", "EWMA", "Bytes", "Avg", "Min", "Max", "First", "Last", "R",
+];
+
+/// The statistics of `topic` as text, one entry per column of `COLUMNS`.
+/// Times are shown as the age at `now`.
+pub fn row(topic: &str, stats: &TopicStats, now: SystemTime) -> Vec<String> {
+    vec![
+        topic.to_string(),
+        stats.count.to_string(),
+        format!("{:.0}", stats.rate_1s(now)),
+        format!("{:.2}", stats.rate_1m(now)),
+        format!("{:.2}", stats.rate_ewma(now)),
+        stats.total_bytes.to_string(),
+        format!("{:.0}", stats.average_size()),
+        stats.min_size.to_string(),
+        stats.max_size.to_string(),
+        format_age(now, stats.first_seen),
+        format_age(now, stats.last_seen),
+        if stats.retained { "*" } else { "" }.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn message(topic: &str, payload: &str, secs: f64) -> Message {
+        Message::new(topic, payload).timestamp(UNIX_EPOCH + Duration::from_secs_f64(secs))
+    }
+
+    #[test]
+    fn count_sizes_and_rates() {
+        let mut stats = Stats::new();
+        stats.update(&message("a", "ciao", 100.2));
+        stats.update(&message("a", "hello world", 100.7));
+        stats.update(&message("a", "", 101.5));
+
+        let a = stats.get("a").unwrap();
+        assert_eq!(a.count, 3);
+        assert_eq!(a.total_bytes, 15);
+        assert_eq!((a.min_size, a.max_size), (0, 11));
+        assert_eq!(a.average_size(), 5.0);
+
+        let now = UNIX_EPOCH + Duration::from_secs_f64(101.9);
+        assert_eq!(a.rate_1s(now), 2.0);
+        assert_eq!(a.rate_1m(now), 2.0 / 60.0);
+        assert_eq!(a.counts(now, 3), vec![0, 0, 2]);
+        assert_eq!(a.counts(now + Duration::from_secs(1), 3), vec![0, 2, 1]);
+        assert_eq!(a.counts(now, 1000).len(), 60);
+        assert!(a.rate_ewma(now) > 0.0);
+        assert!(a.rate_ewma(now + Duration::from_secs(60)) < a.rate_ewma(now));
+    }
+
+    #[test]
+    fn track_retained_messages() {
+        let mut stats = Stats::new();
+        stats.update(&message("a", "ciao", 1.0).retain(true));
+        stats.update(&message("a", "hello", 2.0));
+        assert!(stats.get("a").unwrap().retained);
+
+        stats.update(&message("a", "", 3.0).retain(true));
+        assert!(!stats.get("a").unwrap().retained);
+    }
+
+    #[test]
+    fn sort_topics() {
+        let mut stats = Stats::new();
+        stats.update(&message("a", "1", 1.0));
+        stats.update(&message("b", "1", 1.0));
+        stats.update(&message("b", "1", 1.0));
+        stats.update(&message("c", "1", 1.0));
+
+        let topics = |key, reverse| -> Vec<String> {
+            stats
+                .sorted(key, reverse)
+                .into_iter()
+                .map(|(topic, _)| topic.to_string())
+                .collect()
+        };
+
+        assert_eq!(topics(SortKey::Topic, false), vec!["a", "b", "c"]);
+        assert_eq!(topics(SortKey::Count, false), vec!["b", "a", "c"]);
+        assert_eq!(topics(SortKey::Count, true), vec!["c", "a", "b"]);
+    }
+}
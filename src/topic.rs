@@ -0,0 +1,100 @@
+//! MQTT topic names and topic filters.
+
+const SEPARATOR: char = '/';
+
+/// Check that `topic` can be used as a topic name in a PUBLISH packet.
+pub fn is_valid_topic(topic: &str) -> bool {
+    !topic.is_empty() && !topic.contains(['+', '#', '\0'])
+}
+
+/// Check that `filter` can be used as a topic filter in a SUBSCRIBE packet.
+///
+/// Wildcards must occupy a whole level and the multi-level wildcard `#` is
+/// only allowed as the last level.
+pub fn is_valid_filter(filter: &str) -> bool {
+    if filter.is_empty() || filter.contains('\0') {
+        return false;
+    }
+
+    let levels = filter.split(SEPARATOR).count();
+
+    filter
+        .split(SEPARATOR)
+        .enumerate()
+        .all(|(i, level)| match level {
+            "#" => i == levels - 1,
+            "+" => true,
+            level => !level.contains(['+', '#']),
+        })
+}
+
+/// Check whether `topic` matches `filter`.
+///
+/// As required by the specification, topics starting with `$` are not
+/// matched by filters starting with a wildcard.
+pub fn matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && filter.starts_with(['+', '#']) {
+        return false;
+    }
+
+    let mut filter_levels = filter.split(SEPARATOR);
+    let mut topic_levels = topic.split(SEPARATOR);
+
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => continue,
+            (Some(f), Some(t)) if f == t => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_filters() {
+        assert!(is_valid_filter("#"));
+        assert!(is_valid_filter("+"));
+        assert!(is_valid_filter("a/+/c"));
+        assert!(is_valid_filter("a/b/#"));
+        assert!(is_valid_filter("/"));
+    }
+
+    #[test]
+    fn invalid_filters() {
+        assert!(!is_valid_filter(""));
+        assert!(!is_valid_filter("a/#/c"));
+        assert!(!is_valid_filter("a/b#"));
+        assert!(!is_valid_filter("a+/b"));
+    }
+
+    #[test]
+    fn invalid_topics() {
+        assert!(is_valid_topic("a/b"));
+        assert!(!is_valid_topic(""));
+        assert!(!is_valid_topic("a/+"));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("a/b", "a/b"));
+        assert!(matches("a/+", "a/b"));
+        assert!(matches("a/#", "a/b/c"));
+        assert!(matches("a/#", "a"));
+        assert!(matches("#", "a/b"));
+        assert!(!matches("a/+", "a/b/c"));
+        assert!(!matches("a/b", "a/c"));
+        assert!(!matches("a/b/c", "a/b"));
+    }
+
+    #[test]
+    fn wildcards_do_not_match_system_topics() {
+        assert!(!matches("#", "$SYS/uptime"));
+        assert!(!matches("+/uptime", "$SYS/uptime"));
+        assert!(matches("$SYS/#", "$SYS/uptime"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Hierarchy of the topics seen, split at every level.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::message::Message;
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    /// Messages received on this topic and on every topic below it.
+    count: u64,
+    /// Latest message received on this very topic, if any.
+    last: Option<Message>,
+}
+
+/// A visible node of the tree, as returned by `TopicTree::visible`.
+#[derive(Clone, Debug)]
+pub struct Entry<'a> {
+    /// Full topic of the node.
+    pub path: String,
+    /// Last level of the topic.
+    pub name: &'a str,
+    pub depth: usize,
+    pub count: u64,
+    pub has_children: bool,
+    pub expanded: bool,
+    pub last: Option<&'a Message>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TopicTree {
+    root: Node,
+}
+
+impl TopicTree {
+    pub fn new() -> TopicTree {
+        TopicTree::default()
+    }
+
+    pub fn insert(&mut self, msg: &Message) {
+        let mut node = &mut self.root;
+        node.count += 1;
+
+        for level in msg.topic.split('/') {
+            node = node.children.entry(level.to_string()).or_default();
+            node.count += 1;
+        }
+
+        node.last = Some(msg.clone());
+    }
+
+    pub fn clear(&mut self) {
+        self.root = Node::default();
+    }
+
+    /// Latest message received on `topic`, if any.
+    pub fn last(&self, topic: &str) -> Option<&Message> {
+        topic
+            .split('/')
+            .try_fold(&self.root, |node, level| node.children.get(level))?
+            .last
+            .as_ref()
+    }
+
+    /// Total number of messages in the tree.
+    pub fn count(&self) -> u64 {
+        self.root.count
+    }
+
+    /// Nodes in depth first order, skipping the children of nodes that are
+    /// not in `expanded`.
+    pub fn visible(&self, expanded: &BTreeSet<String>) -> Vec<Entry<'_>> {
+        let mut entries = Vec::new();
+        visit(&self.root, None, 0, expanded, &mut entries);
+        entries
+    }
+
+    /// Topics completing the last level of `prefix`, sorted. The levels
+    /// before it name a node, where `+` stands for any node.
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let (parent, partial) = match prefix.rfind('/') {
+            Some(i) => (Some(&prefix[..i]), &prefix[i + 1..]),
+            None => (None, prefix),
+        };
+
+        let mut nodes = vec![&self.root];
+        for level in parent.into_iter().flat_map(|parent| parent.split('/')) {
+            nodes = nodes
+                .into_iter()
+                .flat_map(|node| node.children.iter())
+                .filter(|(name, _)| level == "+" || *name == level)
+                .map(|(_, child)| child)
+                .collect();
+        }
+
+        let names: BTreeSet<&str> = nodes
+            .iter()
+            .flat_map(|node| node.children.keys())
+            .filter(|name| name.starts_with(partial))
+            .map(String::as_str)
+            .collect();
+
+        names
+            .into_iter()
+            .map(|name| match parent {
+                Some(parent) => format!("{}/{}", parent, name),
+                None => name.to_string(),
+            })
+            .collect()
+    }
+}
+
+fn visit<'a>(
+    node: &'a Node,
+    path: Option<&str>,
+    depth: usize,
+    expanded: &BTreeSet<String>,
+    entries: &mut Vec<Entry<'a>>,
+) {
+    for (name, child) in &node.children {
+        let child_path = match path {
+            Some(path) => format!("{}/{}", path, name),
+            None => name.clone(),
+        };
+        let is_expanded = expanded.contains(&child_path);
+
+        entries.push(Entry {
+            path: child_path.clone(),
+            name,
+            depth,
+            count: child.count,
+            has_children: !child.children.is_empty(),
+            expanded: is_expanded,
+            last: child.last.as_ref(),
+        });
+
+        if is_expanded {
+            visit(child, Some(&child_path), depth + 1, expanded, entries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_nodes() {
+        let mut tree = TopicTree::new();
+        tree.insert(&Message::new("a/b", "1"));
+        tree.insert(&Message::new("a/c/d", "2"));
+        tree.insert(&Message::new("a", "3"));
+        tree.insert(&Message::new("e", "4"));
+
+        let paths = |expanded: &[&str]| -> Vec<(String, u64)> {
+            let expanded = expanded.iter().map(|s| s.to_string()).collect();
+            tree.visible(&expanded)
+                .into_iter()
+                .map(|entry| (entry.path, entry.count))
+                .collect()
+        };
+
+        assert_eq!(paths(&[]), vec![("a".to_string(), 3), ("e".to_string(), 1)]);
+        assert_eq!(
+            paths(&["a"]),
+            vec![
+                ("a".to_string(), 3),
+                ("a/b".to_string(), 1),
+                ("a/c".to_string(), 1),
+                ("e".to_string(), 1),
+            ]
+        );
+        assert_eq!(tree.count(), 4);
+
+        let visible = tree.visible(&BTreeSet::new());
+        assert_eq!(visible[0].last.unwrap().payload.as_slice(), b"3");
+        assert_eq!(tree.last("a/c/d").unwrap().payload.as_slice(), b"2");
+        assert!(tree.last("a/c").is_none());
+        assert!(tree.last("x").is_none());
+        assert!(visible[0].has_children);
+        assert!(!visible[1].has_children);
+    }
+
+    #[test]
+    fn complete_topic_levels() {
+        let mut tree = TopicTree::new();
+        tree.insert(&Message::new("a/b", "1"));
+        tree.insert(&Message::new("a/c/d", "2"));
+        tree.insert(&Message::new("e/c/f", "3"));
+
+        assert_eq!(tree.complete(""), vec!["a", "e"]);
+        assert_eq!(tree.complete("a/"), vec!["a/b", "a/c"]);
+        assert_eq!(tree.complete("a/c"), vec!["a/c"]);
+        assert_eq!(tree.complete("+/c/"), vec!["+/c/d", "+/c/f"]);
+        assert!(tree.complete("x/").is_empty());
+    }
+}
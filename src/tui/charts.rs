@@ -0,0 +1,449 @@
+use std::time::Duration;
+
+use termion::event::Key;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Corner, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Axis, Block, Borders, Chart, Dataset, Marker, Paragraph, Row, Table, Widget};
+use tui::Frame;
+
+use crate::tui::keymap::Action;
+use crate::tui::line_editor::LineEditor;
+use crate::tui::mouse::Hit;
+use crate::tui::navigation::BlockId;
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::format::NOTIFICATION_STYLE;
+use mqtt_analyzer::history::History;
+use mqtt_analyzer::message::{Message, Notification};
+use mqtt_analyzer::series::{Series, ValuePath};
+use mqtt_analyzer::topic;
+
+/// Time windows of the charts, up to `series::RETENTION`.
+const WINDOWS: &[Duration] = &[
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+];
+
+const COLORS: &[Color] = &[
+    Color::LightCyan,
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::LightGreen,
+    Color::LightRed,
+    Color::LightBlue,
+];
+
+const COLUMNS: &[&str] = &["Series", "Last", "Min", "Max", "Avg"];
+
+/// Width of every column but the series, which takes the remaining space.
+const WIDTHS: &[u16] = &[10, 10, 10, 10];
+
+/// Rows of the series table, before it scrolls.
+const MAX_ROWS: usize = 6;
+
+/// Numeric values of the topics picked in the Charts tab.
+#[derive(Default)]
+pub struct Charts {
+    /// A topic, optionally followed by a JSON path.
+    input: LineEditor,
+    error: Option<String>,
+    /// Completions of the topic being typed, when there are more than one.
+    candidates: Vec<String>,
+    series: Vec<Series>,
+    /// Index of the time window in `WINDOWS`.
+    window: usize,
+}
+
+impl Charts {
+    pub fn update(&mut self, msg: &Message) {
+        for series in &mut self.series {
+            series.update(msg);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for series in &mut self.series {
+            series.clear();
+        }
+    }
+
+    fn window(&self) -> Duration {
+        WINDOWS[self.window]
+    }
+
+    fn cycle_window(&mut self) {
+        self.window = (self.window + 1) % WINDOWS.len();
+    }
+
+    /// Add the series described by `line`, a topic with an optional JSON
+    /// path, with the values of the messages still in `history`.
+    fn add(&mut self, line: &str, history: &History) -> Result<(), String> {
+        let mut words = line.split_whitespace();
+        let topic = words.next().unwrap_or_default();
+        let path = words.next();
+
+        if words.next().is_some() {
+            return Err("Expected a topic and a JSON path".to_string());
+        }
+        if !topic::is_valid_topic(topic) {
+            return Err(format!("Invalid topic: {:?}", topic));
+        }
+        let path = match path {
+            Some(path) => Some(path.parse::<ValuePath>().map_err(|e| e.to_string())?),
+            None => None,
+        };
+
+        if self
+            .series
+            .iter()
+            .any(|series| series.topic == topic && series.path == path)
+        {
+            return Err(format!("Already charted: {}", line.trim()));
+        }
+
+        let mut series = Series::new(topic, path);
+        let buffered: Vec<&Notification> = history.iter().collect();
+        for notification in buffered.into_iter().rev() {
+            if let Notification::Publish(msg) = notification {
+                series.update(msg);
+            }
+        }
+
+        self.series.push(series);
+        Ok(())
+    }
+}
+
+fn color(index: usize) -> Color {
+    COLORS[index % COLORS.len()]
+}
+
+/// Format a value with up to two decimals.
+fn format_value(value: f64) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+fn format_window(window: Duration) -> String {
+    match window.as_secs() {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s => format!("{}h", s / 3600),
+    }
+}
+
+/// Bounds of the y axis, a little wider than the values.
+fn y_bounds(data: &[Vec<(f64, f64)>]) -> [f64; 2] {
+    let values = data.iter().flatten().map(|(_, value)| *value);
+    let min = values.clone().fold(f64::INFINITY, f64::min);
+    let max = values.fold(f64::NEG_INFINITY, f64::max);
+
+    if !min.is_finite() {
+        return [0.0, 1.0];
+    }
+
+    let margin = if max > min {
+        (max - min) * 0.05
+    } else {
+        min.abs().max(1.0) * 0.1
+    };
+
+    [min - margin, max + margin]
+}
+
+fn draw_chart_input<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let charts = &app.charts;
+    let focused = app.navigation.peek() == BlockId::ChartInput;
+    let highlight_state = (focused, focused);
+
+    let (title, title_style) = match &charts.error {
+        Some(e) => (format!("Chart - {}", e), NOTIFICATION_STYLE.into()),
+        None if !charts.candidates.is_empty() => (
+            format!("Chart - {}", charts.candidates.join(" ")),
+            get_color(highlight_state),
+        ),
+        None => (
+            "Chart - TOPIC [JSON PATH]".to_string(),
+            get_color(highlight_state),
+        ),
+    };
+
+    app.hits.add(area, Hit::Block(BlockId::ChartInput));
+
+    Paragraph::new(charts.input.spans(focused).iter())
+        .style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(title_style)
+                .border_style(get_color(highlight_state)),
+        )
+        .render(f, area);
+}
+
+fn draw_chart<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let charts = &app.charts;
+    let now = app.stats.now();
+    let window = charts.window();
+
+    let names: Vec<String> = charts.series.iter().map(Series::name).collect();
+    let data: Vec<Vec<(f64, f64)>> = charts
+        .series
+        .iter()
+        .map(|series| series.window(now, window))
+        .collect();
+
+    let datasets: Vec<Dataset> = names
+        .iter()
+        .zip(&data)
+        .enumerate()
+        .map(|(i, (name, data))| {
+            Dataset::default()
+                .name(name)
+                .marker(Marker::Braille)
+                .style(Style::default().fg(color(i)))
+                .data(data)
+        })
+        .collect();
+
+    let width = window.as_secs_f64();
+    let x_labels = [
+        format!("-{}", format_window(window)),
+        format!("-{}", format_window(window / 2)),
+        "now".to_string(),
+    ];
+
+    let [low, high] = y_bounds(&data);
+    let y_labels = [
+        format_value(low),
+        format_value((low + high) / 2.0),
+        format_value(high),
+    ];
+
+    let title = format!("Charts - last {}", format_window(window));
+    let axis_style = Style::default().fg(Color::Gray);
+
+    Chart::default()
+        .block(Block::default().borders(Borders::ALL).title(&title))
+        .x_axis(
+            Axis::default()
+                .style(axis_style)
+                .labels_style(axis_style)
+                .bounds([-width, 0.0])
+                .labels(&x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .style(axis_style)
+                .labels_style(axis_style)
+                .bounds([low, high])
+                .labels(&y_labels),
+        )
+        .datasets(&datasets)
+        .render(f, area);
+}
+
+fn draw_series_table<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let charts = &app.charts;
+    let now = app.stats.now();
+
+    let selected = match app.navigation.peek() {
+        BlockId::ChartSeriesItem(i) => Some(i),
+        _ => None,
+    };
+    let highlight_state = (selected.is_some(), selected.is_some());
+
+    // Keep the selected series in view.
+    let visible = area.height.saturating_sub(4) as usize;
+    let offset = selected.map_or(0, |i| (i + 1).saturating_sub(visible));
+
+    let rows: Vec<(Vec<String>, Style)> = charts
+        .series
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .map(|(i, series)| {
+            let mut row = vec![series.name()];
+            match series.summary(now, charts.window()) {
+                Some(s) => row.extend(
+                    [s.last, s.min, s.max, s.avg]
+                        .iter()
+                        .map(|v| format_value(*v)),
+                ),
+                None => row.extend(vec!["-".to_string(); 4]),
+            }
+
+            let mut style = Style::default().fg(color(i));
+            if selected == Some(i) {
+                style = style.modifier(Modifier::REVERSED);
+            }
+
+            (row, style)
+        })
+        .collect();
+
+    let spacing = 1;
+    let fixed: u16 = WIDTHS.iter().map(|w| w + spacing).sum();
+    let name_width = area.width.saturating_sub(fixed + 2 + spacing).max(5);
+    let widths: Vec<u16> = std::iter::once(name_width)
+        .chain(WIDTHS.iter().cloned())
+        .collect();
+
+    // The header takes the first two rows.
+    let rows_area = Rect::new(
+        area.x,
+        area.y + 2,
+        area.width,
+        area.height.saturating_sub(2),
+    );
+    app.hits.add_rows(
+        rows_area,
+        charts.series.len(),
+        selected,
+        Corner::TopLeft,
+        BlockId::ChartSeriesItem,
+    );
+
+    Table::new(
+        COLUMNS.iter(),
+        rows.iter()
+            .map(|(row, style)| Row::StyledData(row.iter(), *style)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Series")
+            .title_style(get_color(highlight_state))
+            .border_style(get_color(highlight_state)),
+    )
+    .header_style(Style::default().fg(Color::Yellow))
+    .widths(&widths)
+    .column_spacing(spacing)
+    .render(f, area);
+}
+
+pub fn draw_charts_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let rows = app.charts.series.len().clamp(1, MAX_ROWS) as u16;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(rows + 4),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    draw_chart_input(f, chunks[0], app);
+    draw_chart(f, chunks[1], app);
+    draw_series_table(f, chunks[2], app);
+}
+
+/// Keys of the Charts tab, on the tab title.
+pub fn handle_input_on_charts(action: Action, app: &mut App) {
+    if let Action::CycleWindow = action {
+        app.charts.cycle_window();
+    }
+}
+
+pub fn handle_input_on_chart_input(input: Key, action: Option<Action>, app: &mut App) {
+    let charts = &mut app.charts;
+
+    match (input, action) {
+        (_, Some(Action::Up)) => {
+            charts.error = None;
+            app.navigation.modify_top(BlockId::TabNav);
+        }
+        (_, Some(Action::Down)) if !charts.series.is_empty() => {
+            app.navigation.modify_top(BlockId::ChartSeriesItem(0));
+        }
+        (_, Some(Action::Complete)) => {
+            let head = charts.input.head();
+            if head.contains(char::is_whitespace) {
+                return;
+            }
+            let candidates = app.topic_tree.complete(head);
+            charts.input.complete(&candidates);
+            charts.candidates = if candidates.len() > 1 {
+                candidates
+            } else {
+                vec![]
+            };
+        }
+        (_, Some(Action::Select)) => {
+            let line = charts.input.text().to_string();
+            if line.trim().is_empty() {
+                return;
+            }
+            match charts.add(&line, &app.notifications) {
+                Ok(()) => {
+                    charts.input.clear();
+                    charts.error = None;
+                }
+                Err(e) => charts.error = Some(e),
+            }
+        }
+        _ => {
+            if charts.input.handle(input, action) {
+                charts.candidates.clear();
+                charts.error = None;
+            }
+        }
+    }
+}
+
+pub fn handle_input_on_chart_series_item(action: Action, app: &mut App, index: usize) {
+    let len = app.charts.series.len();
+
+    if index >= len {
+        app.navigation.modify_top(if len > 0 {
+            BlockId::ChartSeriesItem(len - 1)
+        } else {
+            BlockId::ChartInput
+        });
+        return;
+    }
+
+    match action {
+        Action::Up if index == 0 => app.navigation.modify_top(BlockId::ChartInput),
+        Action::Up => app
+            .navigation
+            .modify_top(BlockId::ChartSeriesItem(index - 1)),
+        Action::Down if index + 1 < len => app
+            .navigation
+            .modify_top(BlockId::ChartSeriesItem(index + 1)),
+        Action::Delete => {
+            app.charts.series.remove(index);
+            let len = app.charts.series.len();
+            app.navigation.modify_top(match len {
+                0 => BlockId::ChartInput,
+                _ => BlockId::ChartSeriesItem(index.min(len - 1)),
+            });
+        }
+        Action::CycleWindow => app.charts.cycle_window(),
+        _ => {}
+    }
+}
@@ -0,0 +1,425 @@
+use std::time::{Duration, Instant};
+
+use termion::event::Key;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::{Paragraph, Text, Widget};
+use tui::Frame;
+
+use crate::tui::dashboard;
+use crate::tui::keymap::Action;
+use crate::tui::line_editor::LineEditor;
+use crate::tui::navigation::BlockId;
+use crate::tui::publish;
+use crate::tui::App;
+use mqtt_analyzer::capture::{Recorder, Rotation};
+use mqtt_analyzer::engine;
+use mqtt_analyzer::format::PayloadFormat;
+use mqtt_analyzer::message::{Message, QoS};
+
+/// How long the result of a command stays in place of the status bar.
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const HISTORY_CAPACITY: usize = 100;
+
+/// Names of the commands, with their arguments.
+const COMMANDS: &[(&str, &str)] = &[
+    ("sub", "TOPIC [QOS]"),
+    ("unsub", "TOPIC"),
+    ("pin", "TOPIC"),
+    ("unpin", "TOPIC"),
+    ("pub", "[-q QOS] [-r] [-x] TOPIC [PAYLOAD]"),
+    ("format", "FORMAT [TOPIC]"),
+    ("filter", "[EXPR]"),
+    ("record", "[FILE]"),
+    ("clear", "[stream|stats|tree|charts]"),
+    ("quit", ""),
+];
+
+const CLEARABLE: &[&str] = &["stream", "stats", "tree", "charts"];
+
+enum Command {
+    Subscribe(String, QoS),
+    Unsubscribe(String),
+    Pin(String),
+    Unpin(String),
+    Publish(Message),
+    Format(PayloadFormat, Option<String>),
+    Filter(String),
+    Record(Option<String>),
+    Clear(String),
+    Quit,
+}
+
+/// The command named `name`, or by a prefix of its name that no other
+/// command has.
+fn find_command(name: &str) -> Result<&'static str, String> {
+    if let Some((command, _)) = COMMANDS.iter().find(|(command, _)| *command == name) {
+        return Ok(command);
+    }
+
+    let mut found = COMMANDS
+        .iter()
+        .filter(|(command, _)| command.starts_with(name));
+
+    match (found.next(), found.next()) {
+        (Some((command, _)), None) => Ok(command),
+        (Some(_), Some(_)) => Err(format!("Ambiguous command: {}", name)),
+        _ => Err(format!("Unknown command: {}", name)),
+    }
+}
+
+fn usage(command: &str) -> String {
+    let arguments = COMMANDS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map_or("", |(_, arguments)| arguments);
+
+    format!("Usage: {} {}", command, arguments)
+}
+
+fn parse_qos(s: &str) -> Result<QoS, String> {
+    s.parse::<u8>()
+        .ok()
+        .and_then(QoS::from_u8)
+        .ok_or_else(|| format!("Invalid QoS: {}", s))
+}
+
+/// Split the first word of `line` from the rest of it.
+fn split_word(line: &str) -> (&str, &str) {
+    let line = line.trim_start();
+    match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], line[i..].trim_start()),
+        None => (line, ""),
+    }
+}
+
+fn parse_publish(mut rest: &str) -> Result<Message, String> {
+    let mut qos = QoS::AtMostOnce;
+    let mut retain = false;
+    let mut hex = false;
+
+    loop {
+        let (word, tail) = split_word(rest);
+        match word {
+            "-q" => {
+                let (value, tail) = split_word(tail);
+                qos = parse_qos(value)?;
+                rest = tail;
+            }
+            "-r" => {
+                retain = true;
+                rest = tail;
+            }
+            "-x" => {
+                hex = true;
+                rest = tail;
+            }
+            "" => return Err(usage("pub")),
+            _ => break,
+        }
+    }
+
+    let (topic, payload) = split_word(rest);
+    let payload = if hex {
+        let digits: String = payload.split_whitespace().collect();
+        hex::decode(digits).map_err(|e| format!("Invalid hex payload: {}", e))?
+    } else {
+        payload.as_bytes().to_vec()
+    };
+
+    let mut msg = Message::new(topic, payload);
+    msg.qos = qos;
+    msg.retain = retain;
+    Ok(msg)
+}
+
+fn parse(line: &str) -> Result<Command, String> {
+    let (name, rest) = split_word(line);
+    let command = find_command(name)?;
+    let arguments: Vec<&str> = rest.split_whitespace().collect();
+
+    match (command, arguments.as_slice()) {
+        ("sub", [topic]) => Ok(Command::Subscribe(topic.to_string(), QoS::AtLeastOnce)),
+        ("sub", [topic, qos]) => Ok(Command::Subscribe(topic.to_string(), parse_qos(qos)?)),
+        ("unsub", [topic]) => Ok(Command::Unsubscribe(topic.to_string())),
+        ("pin", [topic]) => Ok(Command::Pin(topic.to_string())),
+        ("unpin", [topic]) => Ok(Command::Unpin(topic.to_string())),
+        ("pub", _) => parse_publish(rest).map(Command::Publish),
+        ("format", [format]) | ("format", [format, _]) => {
+            let format = format
+                .parse::<PayloadFormat>()
+                .map_err(|_| format!("Unknown format: {}", format))?;
+            let topic = arguments.get(1).map(|topic| topic.to_string());
+            Ok(Command::Format(format, topic))
+        }
+        ("filter", _) => Ok(Command::Filter(rest.to_string())),
+        ("record", []) => Ok(Command::Record(None)),
+        ("record", _) => Ok(Command::Record(Some(rest.to_string()))),
+        ("clear", []) => Ok(Command::Clear("stream".to_string())),
+        ("clear", [what]) if CLEARABLE.contains(what) => Ok(Command::Clear(what.to_string())),
+        ("quit", []) => Ok(Command::Quit),
+        (command, _) => Err(usage(command)),
+    }
+}
+
+/// Run `command`, returning what it did.
+fn run(app: &mut App, command: Command) -> Result<String, String> {
+    match command {
+        Command::Subscribe(topic, qos) => {
+            app.last_error = None;
+            let report = format!("Subscribing to {} with QoS {}", topic, qos);
+            app.engine
+                .send(engine::Event::Subscribe(topic, qos))
+                .map_err(|e| e.to_string())?;
+            Ok(report)
+        }
+        Command::Pin(topic) => dashboard::pin(app, &topic),
+        Command::Unpin(topic) => dashboard::unpin(app, &topic),
+        Command::Unsubscribe(topic) => {
+            let report = format!("Unsubscribing from {}", topic);
+            app.engine
+                .send(engine::Event::Unsubscribe(topic))
+                .map_err(|e| e.to_string())?;
+            Ok(report)
+        }
+        Command::Publish(msg) => {
+            let report = format!("Publishing to {}", msg.topic);
+            publish::send(app, msg);
+            Ok(report)
+        }
+        Command::Format(format, None) => {
+            app.set_view_format(format);
+            Ok(format!("Format {}", format.name()))
+        }
+        Command::Format(format, Some(topic)) => {
+            let report = format!("Format {} for {}", format.name(), topic);
+            app.topic_formats.insert(topic, format);
+            Ok(report)
+        }
+        Command::Filter(expr) => {
+            app.set_filter_input(expr);
+            match &app.panes.focused().filter_error {
+                Some(e) => Err(format!("Invalid filter: {}", e)),
+                None if app.panes.focused().filter_input.is_empty() => {
+                    Ok("Filter cleared".to_string())
+                }
+                None => Ok(format!("Filter {}", app.panes.focused().filter_input)),
+            }
+        }
+        Command::Record(Some(path)) => {
+            let recorder =
+                Recorder::create(&path, Rotation::default()).map_err(|e| e.to_string())?;
+            app.engine
+                .start_recording(recorder)
+                .map_err(|e| e.to_string())?;
+            Ok(format!("Recording to {}", path))
+        }
+        Command::Record(None) => match app.engine.recording() {
+            Some(path) => {
+                app.engine.stop_recording().map_err(|e| e.to_string())?;
+                Ok(format!("Recorded to {}", path.display()))
+            }
+            None => Err("Not recording".to_string()),
+        },
+        Command::Clear(what) => {
+            match what.as_str() {
+                "stats" => app.stats.clear(),
+                "tree" => app.topic_tree.clear(),
+                "charts" => app.charts.clear(),
+                _ => app.clear_stream(),
+            }
+            Ok(format!("Cleared the {}", what))
+        }
+        Command::Quit => {
+            app.quit = true;
+            Ok(String::new())
+        }
+    }
+}
+
+/// Line edited after `:`, with its history.
+#[derive(Default)]
+pub struct CommandLine {
+    editor: LineEditor,
+    history: Vec<String>,
+    /// Entry of the history shown in the input, from the newest.
+    browsing: Option<usize>,
+    /// Completions of the word being typed, when there are more than one.
+    candidates: Vec<String>,
+    /// Outcome of the last command, and when it ran.
+    report: Option<(Result<String, String>, Instant)>,
+}
+
+impl CommandLine {
+    /// What completes the word before the cursor.
+    fn completions(&self, app: &App) -> Vec<String> {
+        let head = self.editor.head();
+        let words: Vec<&str> = head.split_whitespace().collect();
+        let typing_word = !head.is_empty() && !head.ends_with(char::is_whitespace);
+        let (word, previous) = match (typing_word, words.split_last()) {
+            (true, Some((word, previous))) => (*word, previous),
+            _ => ("", words.as_slice()),
+        };
+
+        let candidates: Vec<String> = match previous {
+            [] => COMMANDS.iter().map(|(name, _)| name.to_string()).collect(),
+            [command, arguments @ ..] => match (find_command(command), arguments) {
+                (Ok("sub"), []) | (Ok("pub"), _) | (Ok("format"), [_]) | (Ok("pin"), []) => {
+                    return app.topic_tree.complete(word);
+                }
+                (Ok("unpin"), []) => app.config.dashboard.pins.clone(),
+                (Ok("unsub"), []) => app
+                    .engine
+                    .subscriptions
+                    .read()
+                    .map(|subscriptions| subscriptions.keys().cloned().collect())
+                    .unwrap_or_default(),
+                (Ok("format"), []) => PayloadFormat::ALL
+                    .iter()
+                    .map(|format| format.name().to_string())
+                    .collect(),
+                (Ok("clear"), []) => CLEARABLE.iter().map(|s| s.to_string()).collect(),
+                _ => vec![],
+            },
+        };
+
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect()
+    }
+
+    fn complete(&mut self, candidates: Vec<String>) {
+        let head = self.editor.head();
+        let typed = &head[..head.rfind(char::is_whitespace).map_or(0, |i| i + 1)];
+        let heads: Vec<String> = candidates
+            .iter()
+            .map(|candidate| format!("{}{}", typed, candidate))
+            .collect();
+
+        self.editor.complete(&heads);
+        // Topics may go on with more levels.
+        if let [candidate] = candidates.as_slice() {
+            if !candidate.contains('/') {
+                self.editor.insert(' ');
+            }
+        }
+
+        self.candidates = if candidates.len() > 1 {
+            candidates
+        } else {
+            vec![]
+        };
+    }
+
+    fn browse(&mut self, older: bool) {
+        let len = self.history.len();
+        self.browsing = match (self.browsing, older) {
+            (None, true) if len > 0 => Some(0),
+            (Some(i), true) if i + 1 < len => Some(i + 1),
+            (Some(0), false) => None,
+            (Some(i), false) => Some(i - 1),
+            (browsing, _) => browsing,
+        };
+
+        match self.browsing {
+            Some(i) => self.editor.set(self.history[len - 1 - i].clone()),
+            None => self.editor.clear(),
+        }
+    }
+
+    fn remember(&mut self, line: String) {
+        self.history.retain(|entry| *entry != line);
+        self.history.push(line);
+        if self.history.len() > HISTORY_CAPACITY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Show the outcome of an action in place of the status bar, as for a
+    /// command.
+    pub fn set_report(&mut self, report: Result<String, String>) {
+        self.report = Some((report, Instant::now()));
+    }
+
+    /// The outcome of the last command, if it is recent.
+    pub fn report(&self) -> Option<&Result<String, String>> {
+        match &self.report {
+            Some((report, time)) if time.elapsed() < MESSAGE_TIMEOUT => Some(report),
+            _ => None,
+        }
+    }
+}
+
+pub fn open_command_line(app: &mut App) {
+    let command_line = &mut app.command_line;
+    command_line.editor.clear();
+    command_line.browsing = None;
+    command_line.candidates.clear();
+    app.navigation.push(BlockId::CommandLine);
+}
+
+fn execute(app: &mut App) {
+    app.navigation.pop();
+
+    let line = app.command_line.editor.text().trim().to_string();
+    if line.is_empty() {
+        return;
+    }
+
+    app.command_line.remember(line.clone());
+
+    let report = parse(&line).and_then(|command| run(app, command));
+    app.command_line.set_report(report);
+}
+
+pub fn handle_input_on_command_line(input: Key, action: Option<Action>, app: &mut App) {
+    match (input, action) {
+        (_, Some(Action::Select)) => execute(app),
+        (_, Some(Action::Up)) => app.command_line.browse(true),
+        (_, Some(Action::Down)) => app.command_line.browse(false),
+        (_, Some(Action::Complete)) => {
+            let candidates = app.command_line.completions(app);
+            app.command_line.complete(candidates);
+        }
+        (Key::Backspace, None) if app.command_line.editor.text().is_empty() => app.navigation.pop(),
+        _ => {
+            if app.command_line.editor.handle(input, action) {
+                app.command_line.candidates.clear();
+            }
+        }
+    }
+}
+
+/// The command line, in place of the status bar.
+pub fn draw_command_line<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let command_line = &app.command_line;
+    let candidates = command_line.candidates.join(" ");
+
+    let mut text = vec![Text::raw(":")];
+    text.extend(command_line.editor.spans(true));
+    text.push(Text::styled(
+        format!("  {}", candidates),
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    Paragraph::new(text.iter()).render(f, area);
+}
+
+/// The outcome of the last command, in place of the status bar.
+pub fn draw_report<B>(f: &mut Frame<B>, area: Rect, report: &Result<String, String>)
+where
+    B: Backend,
+{
+    let text = match report {
+        Ok(report) => Text::raw(format!(" {}", report)),
+        Err(e) => Text::styled(format!(" {}", e), Style::default().fg(Color::Red)),
+    };
+
+    Paragraph::new([text].iter()).render(f, area);
+}
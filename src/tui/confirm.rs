@@ -0,0 +1,95 @@
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
+use tui::Frame;
+
+use crate::tui::keymap;
+use crate::tui::navigation::BlockId;
+use crate::tui::popup::{centered, Clear};
+use crate::tui::App;
+use mqtt_analyzer::engine;
+use mqtt_analyzer::message::{Message, QoS};
+
+/// What to do when the user confirms.
+pub enum Action {
+    /// Publish an empty retained message on each topic.
+    ClearRetained(Vec<String>),
+}
+
+pub struct Confirmation {
+    prompt: String,
+    action: Action,
+}
+
+/// Ask the user to confirm `action` before running it.
+pub fn ask(app: &mut App, prompt: String, action: Action) {
+    app.confirmation = Some(Confirmation { prompt, action });
+    app.navigation.push(BlockId::Confirm);
+}
+
+fn run(app: &mut App, action: Action) {
+    match action {
+        Action::ClearRetained(topics) => {
+            for topic in topics {
+                let mut msg = Message::new(topic, Vec::new());
+                msg.qos = QoS::AtLeastOnce;
+                msg.retain = true;
+                if let Err(e) = app.engine.send(engine::Event::Publish(msg)) {
+                    app.last_error = Some(e);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+pub fn draw_confirmation<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let confirmation = match &app.confirmation {
+        Some(confirmation) if app.navigation.peek() == BlockId::Confirm => confirmation,
+        _ => return,
+    };
+
+    let popup = centered(area, 60, 6);
+
+    let (contexts, _) = keymap::bindings_of(BlockId::Confirm, app.tabs.index);
+    let hints = app.keymap.hints(
+        contexts,
+        &[(keymap::Action::Yes, "yes"), (keymap::Action::No, "no")],
+    );
+    let text = [
+        Text::raw(format!("{}\n\n", confirmation.prompt)),
+        Text::styled(hints, Style::default().fg(Color::Yellow)),
+    ];
+
+    Clear.render(f, popup);
+    Paragraph::new(text.iter())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Confirm")
+                .title_style(Style::default().fg(Color::LightCyan))
+                .border_style(Style::default().fg(Color::LightCyan)),
+        )
+        .wrap(true)
+        .render(f, popup);
+}
+
+pub fn handle_input_on_confirm(action: keymap::Action, app: &mut App) {
+    match action {
+        keymap::Action::Yes => {
+            app.navigation.pop();
+            if let Some(confirmation) = app.confirmation.take() {
+                run(app, confirmation.action);
+            }
+        }
+        keymap::Action::No => {
+            app.navigation.pop();
+            app.confirmation = None;
+        }
+        _ => {}
+    }
+}
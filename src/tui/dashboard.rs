@@ -0,0 +1,214 @@
+use std::cell::Cell;
+use std::time::Duration;
+
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Paragraph, Sparkline, Text, Widget};
+use tui::Frame;
+
+use crate::tui::keymap::Action;
+use crate::tui::mouse::Hit;
+use crate::tui::navigation::BlockId;
+use crate::tui::App;
+use mqtt_analyzer::format::{format_age, format_payload, NOTIFICATION_STYLE};
+
+/// Width of a cell, borders included. Cells are stretched to fill the rows.
+const CELL_WIDTH: u16 = 28;
+
+/// Height of a cell: the value, the age and the rate, and the sparkline.
+const CELL_HEIGHT: u16 = 5;
+
+/// Layout of the grid of pinned topics, as last drawn.
+#[derive(Default)]
+pub struct Dashboard {
+    columns: Cell<usize>,
+}
+
+fn save_pins(app: &App) -> Result<(), String> {
+    match &app.config_path {
+        Some(path) => app.config.save_pins(path).map_err(|e| e.to_string()),
+        None => Err("No configuration file to save the pins to".to_string()),
+    }
+}
+
+pub fn pin(app: &mut App, topic: &str) -> Result<String, String> {
+    let pins = &mut app.config.dashboard.pins;
+    if pins.iter().any(|pin| pin == topic) {
+        return Err(format!("Already pinned: {}", topic));
+    }
+
+    pins.push(topic.to_string());
+    save_pins(app)?;
+    Ok(format!("Pinned {}", topic))
+}
+
+pub fn unpin(app: &mut App, topic: &str) -> Result<String, String> {
+    let pins = &mut app.config.dashboard.pins;
+    let len = pins.len();
+    pins.retain(|pin| pin != topic);
+    if pins.len() == len {
+        return Err(format!("Not pinned: {}", topic));
+    }
+
+    save_pins(app)?;
+    Ok(format!("Unpinned {}", topic))
+}
+
+pub fn toggle_pin(app: &mut App, topic: &str) -> Result<String, String> {
+    if app.config.dashboard.pins.iter().any(|pin| pin == topic) {
+        unpin(app, topic)
+    } else {
+        pin(app, topic)
+    }
+}
+
+fn draw_cell<B>(f: &mut Frame<B>, area: Rect, app: &App, topic: &str, selected: bool)
+where
+    B: Backend,
+{
+    let now = app.stats.now();
+    let stale_after = Duration::from_secs(app.config.dashboard.stale);
+    let stats = app.stats.get(topic);
+    let stale = stats
+        .is_none_or(|stats| now.duration_since(stats.last_seen).unwrap_or_default() > stale_after);
+
+    let (border_style, title_style) = match (selected, stale) {
+        (true, _) => (
+            Style::default().fg(Color::LightCyan),
+            Style::default().fg(Color::LightCyan),
+        ),
+        (false, true) => (Style::default().fg(Color::Red), NOTIFICATION_STYLE.into()),
+        (false, false) => (
+            Style::default().fg(Color::Gray),
+            Style::default().fg(Color::Gray),
+        ),
+    };
+
+    Block::default()
+        .borders(Borders::ALL)
+        .title(topic)
+        .title_style(title_style)
+        .border_style(border_style)
+        .render(f, area);
+
+    if area.width < 3 || area.height < CELL_HEIGHT {
+        return;
+    }
+    let inner = Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2);
+
+    let value = match app.topic_tree.last(topic) {
+        Some(msg) => {
+            let (_, text) = format_payload(app.payload_format(topic), &msg.payload);
+            text.lines().next().unwrap_or_default().to_string()
+        }
+        None => "-".to_string(),
+    };
+    let info = match stats {
+        Some(stats) => format!(
+            "{} ago, {:.1}/s",
+            format_age(now, stats.last_seen),
+            stats.rate_1m(now)
+        ),
+        None => "never updated".to_string(),
+    };
+
+    let text = [
+        Text::styled(format!("{}\n", value), Style::default().fg(Color::Yellow)),
+        Text::raw(info),
+    ];
+    Paragraph::new(text.iter()).render(f, Rect::new(inner.x, inner.y, inner.width, 2));
+
+    let counts = stats.map_or(vec![], |stats| stats.counts(now, inner.width as u64));
+    Sparkline::default()
+        .data(&counts)
+        .style(Style::default().fg(Color::Green))
+        .render(f, Rect::new(inner.x, inner.y + 2, inner.width, 1));
+}
+
+pub fn draw_dashboard_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let pins = &app.config.dashboard.pins;
+
+    if pins.is_empty() {
+        let text = [Text::raw(
+            "No pinned topics: press p on a topic of the Topics tab, or type :pin TOPIC",
+        )];
+        Paragraph::new(text.iter())
+            .block(Block::default().borders(Borders::ALL).title("Dashboard"))
+            .render(f, area);
+        return;
+    }
+
+    let columns = (area.width / CELL_WIDTH).max(1) as usize;
+    let rows = (area.height / CELL_HEIGHT).max(1) as usize;
+    let width = area.width / columns as u16;
+    app.dashboard.columns.set(columns);
+
+    let selected = match app.navigation.peek() {
+        BlockId::DashboardItem(i) => Some(i),
+        _ => None,
+    };
+
+    // Keep the row of the selected cell in view.
+    let first = selected.map_or(0, |i| (i / columns + 1).saturating_sub(rows)) * columns;
+
+    for (i, topic) in pins.iter().enumerate().skip(first).take(rows * columns) {
+        let position = i - first;
+        let cell = Rect::new(
+            area.x + (position % columns) as u16 * width,
+            area.y + (position / columns) as u16 * CELL_HEIGHT,
+            width,
+            CELL_HEIGHT.min(area.height),
+        );
+
+        app.hits.add(cell, Hit::Block(BlockId::DashboardItem(i)));
+        draw_cell(f, cell, app, topic, selected == Some(i));
+    }
+}
+
+pub fn handle_input_on_dashboard_item(action: Action, app: &mut App, index: usize) {
+    let len = app.config.dashboard.pins.len();
+
+    if index >= len {
+        app.navigation.modify_top(match len {
+            0 => BlockId::TabNav,
+            _ => BlockId::DashboardItem(len - 1),
+        });
+        return;
+    }
+
+    let columns = app.dashboard.columns.get().max(1);
+    let column = index % columns;
+    let topic = app.config.dashboard.pins[index].clone();
+
+    match action {
+        Action::Up if index < columns => app.navigation.modify_top(BlockId::TabNav),
+        Action::Up => app
+            .navigation
+            .modify_top(BlockId::DashboardItem(index - columns)),
+        // Down from above an empty cell of the last row goes to its last cell.
+        Action::Down if index / columns < (len - 1) / columns => app
+            .navigation
+            .modify_top(BlockId::DashboardItem((index + columns).min(len - 1))),
+        Action::Left if column > 0 => app.navigation.modify_top(BlockId::DashboardItem(index - 1)),
+        Action::Right if index + 1 < len && column + 1 < columns => {
+            app.navigation.modify_top(BlockId::DashboardItem(index + 1))
+        }
+        Action::Delete => {
+            let report = unpin(app, &topic);
+            app.command_line.set_report(report);
+
+            let len = app.config.dashboard.pins.len();
+            app.navigation.modify_top(match len {
+                0 => BlockId::TabNav,
+                _ => BlockId::DashboardItem(index.min(len - 1)),
+            });
+        }
+        Action::CycleFormat => app.cycle_view_format(),
+        Action::CycleTopicFormat => app.cycle_topic_format(&topic),
+        _ => {}
+    }
+}
@@ -0,0 +1,168 @@
+use tui::backend::Backend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
+use tui::Frame;
+use unicode_width::UnicodeWidthChar;
+
+use crate::tui::keymap::Action;
+use crate::tui::mouse::{Hit, Scrollable};
+use crate::tui::navigation::BlockId;
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::format::*;
+use mqtt_analyzer::message::Message;
+
+const PAGE: u16 = 10;
+
+/// Split `area` to make room for the detail pane, when it is open.
+pub fn split_for_detail(area: Rect, app: &App) -> (Rect, Option<Rect>) {
+    if app.detail.is_none() || !app.navigation.contains(BlockId::MessageDetail) {
+        return (area, None);
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+
+    (chunks[0], Some(chunks[1]))
+}
+
+/// `text` with its lines broken to fit in `width` columns. The text is
+/// wrapped here rather than by the paragraph, to know how many rows it takes.
+fn wrap(text: &str, width: usize) -> String {
+    let mut wrapped = String::with_capacity(text.len());
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            wrapped.push('\n');
+        }
+
+        let mut line_width = 0;
+        for c in line.chars() {
+            let c_width = c.width().unwrap_or(0);
+            if line_width + c_width > width && line_width > 0 {
+                wrapped.push('\n');
+                line_width = 0;
+            }
+            wrapped.push(c);
+            line_width += c_width;
+        }
+    }
+
+    wrapped
+}
+
+fn section(title: &str, content: String, width: usize) -> Vec<Text<'static>> {
+    let title_style = Style::default().fg(Color::Yellow).modifier(Modifier::BOLD);
+
+    vec![
+        Text::styled(format!("{}\n", title), title_style),
+        Text::raw(format!("{}\n\n", wrap(&content, width))),
+    ]
+}
+
+/// The text of the detail pane, wrapped to `width` columns.
+fn detail_text(msg: &Message, width: u16) -> Vec<Text<'static>> {
+    let width = usize::from(width.max(1));
+    let payload = msg.payload.as_slice();
+
+    let mut text = section("Topic", msg.topic.clone(), width);
+    text.extend(section(
+        "Metadata",
+        format!(
+            "QoS: {}\nRetain: {}\nTimestamp: {}\nSize: {} bytes",
+            msg.qos,
+            msg.retain,
+            format_timestamp(msg.timestamp),
+            payload.len()
+        ),
+        width,
+    ));
+
+    // Control characters are escaped, to not reach the terminal.
+    if let (PayloadFormat::Text, s) = format_payload_text(payload) {
+        text.extend(section("Text", s, width));
+    }
+    if let (PayloadFormat::Escape, s) = format_payload_ascii(payload) {
+        text.extend(section("Escape", s, width));
+    }
+    if let Some(json) = format_payload_json_pretty(payload) {
+        text.extend(section("JSON", json, width));
+    }
+    text.extend(section("Hex", format_payload_hex_dump(payload), width));
+    text.extend(section("Base64", format_payload_base64(payload), width));
+
+    text
+}
+
+pub fn draw_message_detail<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let msg = match &app.detail {
+        Some(msg) => msg,
+        None => return,
+    };
+
+    let highlight_state = (
+        app.navigation.peek() == BlockId::MessageDetail,
+        app.navigation.peek() == BlockId::MessageDetail,
+    );
+
+    let (width, height) = (area.width.saturating_sub(2), area.height.saturating_sub(2));
+    app.detail_size.set((width, height));
+    let text = detail_text(msg, width);
+
+    app.hits.add(area, Hit::Scroll(Scrollable::Detail));
+
+    Paragraph::new(text.iter())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Message")
+                .title_style(get_color(highlight_state))
+                .border_style(get_color(highlight_state)),
+        )
+        .scroll(app.detail_scroll)
+        .render(f, area);
+}
+
+/// Open the detail pane on `msg`.
+pub fn open_detail(app: &mut App, msg: Message) {
+    app.detail = Some(msg);
+    app.detail_scroll = 0;
+    app.navigation.push(BlockId::MessageDetail);
+}
+
+pub fn handle_input_on_message_detail(action: Action, app: &mut App) {
+    use Action::*;
+
+    // Every row ends with a newline, the text being wrapped beforehand.
+    let (width, height) = app.detail_size.get();
+    let rows = app.detail.as_ref().map_or(0, |msg| {
+        detail_text(msg, width)
+            .iter()
+            .map(|text| match text {
+                Text::Raw(s) | Text::Styled(s, _) => s.matches('\n').count(),
+            })
+            .sum::<usize>()
+    });
+    let max_scroll = rows
+        .saturating_sub(usize::from(height))
+        .min(u16::MAX as usize) as u16;
+
+    let scroll = &mut app.detail_scroll;
+
+    match action {
+        Up => *scroll = scroll.saturating_sub(1),
+        Down => *scroll = scroll.saturating_add(1),
+        PageUp => *scroll = scroll.saturating_sub(PAGE),
+        PageDown => *scroll = scroll.saturating_add(PAGE),
+        Home => *scroll = 0,
+        _ => {}
+    }
+
+    *scroll = (*scroll).min(max_scroll);
+}
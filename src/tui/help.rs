@@ -0,0 +1,97 @@
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
+use tui::Frame;
+
+use crate::tui::keymap::{bindings_of, key_name, Context};
+use crate::tui::navigation::BlockId;
+use crate::tui::popup::{centered, Clear};
+use crate::tui::App;
+
+fn title(block_id: BlockId) -> &'static str {
+    use BlockId::*;
+
+    match block_id {
+        TabNav => "Tabs",
+        SubscribeInput => "Subscribe",
+        SubscriptionsList | SubscriptionsListItem(_) => "Subscriptions",
+        TopicTreeItem(_) => "Topics",
+        FilterInput => "Filter",
+        SearchInput => "Search",
+        StreamItem(_) => "Stream",
+        RetainItem(_) => "Retained",
+        StatsItem(_) => "Stats",
+        MessageDetail => "Message",
+        PublishTopic | PublishPayload | PublishOptions => "Publish",
+        PublishHistoryItem(_) => "Sent",
+        ChartInput | ChartSeriesItem(_) => "Charts",
+        DashboardItem(_) => "Dashboard",
+        Confirm => "Confirm",
+        CommandLine => "Command line",
+        Root | SubscriptionsWindow | Help => "",
+    }
+}
+
+/// The bindings of the block below the help, one per line.
+fn help_text(app: &App) -> Vec<Text<'static>> {
+    let block_id = app.navigation.parent();
+    let (contexts, actions) = bindings_of(block_id, app.tabs.index);
+
+    let mut rows: Vec<(String, &str)> = actions
+        .iter()
+        .filter_map(|action| {
+            let keys = app.keymap.keys(contexts, *action);
+            if keys.is_empty() {
+                return None;
+            }
+            let keys: Vec<String> = keys.into_iter().map(key_name).collect();
+            Some((keys.join(", "), action.description()))
+        })
+        .collect();
+
+    if contexts.contains(&Context::Input) {
+        rows.push(("other keys".to_string(), "edit the text"));
+    }
+    rows.push(("ctrl-c".to_string(), "quit"));
+
+    let width = rows.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+    let key_style = Style::default().fg(Color::Yellow);
+
+    rows.into_iter()
+        .flat_map(|(keys, description)| {
+            vec![
+                Text::styled(format!(" {:>width$}", keys, width = width), key_style),
+                Text::raw(format!("  {}\n", description)),
+            ]
+        })
+        .collect()
+}
+
+pub fn draw_help<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    if app.navigation.peek() != BlockId::Help {
+        return;
+    }
+
+    let text = help_text(app);
+    let lines = text.len() / 2;
+    let popup = centered(area, 64, lines as u16 + 2);
+    let title = format!(
+        "Keys - {} - any key to close",
+        title(app.navigation.parent())
+    );
+
+    Clear.render(f, popup);
+    Paragraph::new(text.iter())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(Style::default().fg(Color::LightCyan))
+                .border_style(Style::default().fg(Color::LightCyan)),
+        )
+        .render(f, popup);
+}
@@ -0,0 +1,867 @@
+use termion::event::Key;
+
+use crate::cli::Mode;
+use crate::tui::navigation::BlockId;
+use mqtt_analyzer::config::{self, Config};
+
+/// What a key does, whatever key it is bound to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Select,
+    Back,
+    Help,
+    CommandLine,
+    Complete,
+    DeleteWord,
+    NextField,
+    CycleFormat,
+    CycleTopicFormat,
+    Delete,
+    Pause,
+    Compact,
+    ClearFilter,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    SplitHorizontal,
+    SplitVertical,
+    ClosePane,
+    NextPane,
+    Subscribe,
+    Unsubscribe,
+    FilterStream,
+    Pin,
+    ClearRetained,
+    ClearRetainedSubtree,
+    Sort,
+    Reverse,
+    ClearStats,
+    CycleWindow,
+    CycleQos,
+    ToggleRetain,
+    ToggleHex,
+    Publish,
+    Edit,
+    Yes,
+    No,
+}
+
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Home,
+        Action::End,
+        Action::Select,
+        Action::Back,
+        Action::Help,
+        Action::CommandLine,
+        Action::Complete,
+        Action::DeleteWord,
+        Action::NextField,
+        Action::CycleFormat,
+        Action::CycleTopicFormat,
+        Action::Delete,
+        Action::Pause,
+        Action::Compact,
+        Action::ClearFilter,
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::SplitHorizontal,
+        Action::SplitVertical,
+        Action::ClosePane,
+        Action::NextPane,
+        Action::Subscribe,
+        Action::Unsubscribe,
+        Action::FilterStream,
+        Action::Pin,
+        Action::ClearRetained,
+        Action::ClearRetainedSubtree,
+        Action::Sort,
+        Action::Reverse,
+        Action::ClearStats,
+        Action::CycleWindow,
+        Action::CycleQos,
+        Action::ToggleRetain,
+        Action::ToggleHex,
+        Action::Publish,
+        Action::Edit,
+        Action::Yes,
+        Action::No,
+    ];
+
+    /// The name of the action in the configuration file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Home => "home",
+            Action::End => "end",
+            Action::Select => "select",
+            Action::Back => "back",
+            Action::Help => "help",
+            Action::CommandLine => "command_line",
+            Action::Complete => "complete",
+            Action::DeleteWord => "delete_word",
+            Action::NextField => "next_field",
+            Action::CycleFormat => "cycle_format",
+            Action::CycleTopicFormat => "cycle_topic_format",
+            Action::Delete => "delete",
+            Action::Pause => "pause",
+            Action::Compact => "compact",
+            Action::ClearFilter => "clear_filter",
+            Action::Search => "search",
+            Action::NextMatch => "next_match",
+            Action::PreviousMatch => "previous_match",
+            Action::SplitHorizontal => "split_horizontal",
+            Action::SplitVertical => "split_vertical",
+            Action::ClosePane => "close_pane",
+            Action::NextPane => "next_pane",
+            Action::Subscribe => "subscribe",
+            Action::Unsubscribe => "unsubscribe",
+            Action::FilterStream => "filter_stream",
+            Action::Pin => "pin",
+            Action::ClearRetained => "clear_retained",
+            Action::ClearRetainedSubtree => "clear_retained_subtree",
+            Action::Sort => "sort",
+            Action::Reverse => "reverse",
+            Action::ClearStats => "clear_stats",
+            Action::CycleWindow => "cycle_window",
+            Action::CycleQos => "cycle_qos",
+            Action::ToggleRetain => "toggle_retain",
+            Action::ToggleHex => "toggle_hex",
+            Action::Publish => "publish",
+            Action::Edit => "edit",
+            Action::Yes => "yes",
+            Action::No => "no",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Up => "move up",
+            Action::Down => "move down",
+            Action::Left => "move left, collapse",
+            Action::Right => "move right, expand",
+            Action::PageUp => "page up",
+            Action::PageDown => "page down",
+            Action::Home => "go to the first item",
+            Action::End => "go to the last item",
+            Action::Select => "select, open",
+            Action::Back => "go back",
+            Action::Help => "show this help",
+            Action::CommandLine => "open the command line",
+            Action::Complete => "complete the word",
+            Action::DeleteWord => "delete the word before the cursor",
+            Action::NextField => "next field",
+            Action::CycleFormat => "cycle the payload format",
+            Action::CycleTopicFormat => "cycle the format of the topic",
+            Action::Delete => "unsubscribe, remove the series, unpin",
+            Action::Pause => "pause or resume the stream",
+            Action::Compact => "collapse the repeated messages",
+            Action::ClearFilter => "clear the filter",
+            Action::Search => "search the stream",
+            Action::NextMatch => "go to the next older match",
+            Action::PreviousMatch => "go to the next newer match",
+            Action::SplitHorizontal => "add a pane on the side",
+            Action::SplitVertical => "add a pane below",
+            Action::ClosePane => "close the pane",
+            Action::NextPane => "focus the next pane",
+            Action::Subscribe => "subscribe to the subtree",
+            Action::Unsubscribe => "unsubscribe from the subtree",
+            Action::FilterStream => "show the subtree in the stream",
+            Action::Pin => "pin or unpin the topic",
+            Action::ClearRetained => "clear the retained message",
+            Action::ClearRetainedSubtree => "clear the retained messages below",
+            Action::Sort => "change the sort column",
+            Action::Reverse => "reverse the order",
+            Action::ClearStats => "clear the statistics",
+            Action::CycleWindow => "change the time window",
+            Action::CycleQos => "change the QoS",
+            Action::ToggleRetain => "toggle the retain flag",
+            Action::ToggleHex => "toggle the hex payload",
+            Action::Publish => "publish",
+            Action::Edit => "edit",
+            Action::Yes => "yes",
+            Action::No => "no",
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .iter()
+            .find(|action| action.name() == s)
+            .cloned()
+            .ok_or(())
+    }
+}
+
+/// Where a binding applies. The bindings of a context hide the ones of the
+/// contexts after it for the focused block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Context {
+    Global,
+    /// Text inputs, where the characters are typed instead.
+    Input,
+    Tabs,
+    Subscriptions,
+    Stream,
+    Retain,
+    Tree,
+    Stats,
+    Charts,
+    Dashboard,
+    Detail,
+    Publish,
+    Confirm,
+    CommandLine,
+}
+
+impl Context {
+    const ALL: &'static [Context] = &[
+        Context::Global,
+        Context::Input,
+        Context::Tabs,
+        Context::Subscriptions,
+        Context::Stream,
+        Context::Retain,
+        Context::Tree,
+        Context::Stats,
+        Context::Charts,
+        Context::Dashboard,
+        Context::Detail,
+        Context::Publish,
+        Context::Confirm,
+        Context::CommandLine,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Context::Global => "global",
+            Context::Input => "input",
+            Context::Tabs => "tabs",
+            Context::Subscriptions => "subscriptions",
+            Context::Stream => "stream",
+            Context::Retain => "retain",
+            Context::Tree => "tree",
+            Context::Stats => "stats",
+            Context::Charts => "charts",
+            Context::Dashboard => "dashboard",
+            Context::Detail => "detail",
+            Context::Publish => "publish",
+            Context::Confirm => "confirm",
+            Context::CommandLine => "command",
+        }
+    }
+}
+
+/// Parse a key name, like `a`, `enter`, `pagedown` or `ctrl-s`.
+pub fn parse_key(s: &str) -> Option<Key> {
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if let Some(c) = s.strip_prefix("ctrl-").and_then(single) {
+        return Some(Key::Ctrl(c));
+    }
+    if let Some(c) = s.strip_prefix("alt-").and_then(single) {
+        return Some(Key::Alt(c));
+    }
+    if let Some(n) = s.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return Some(Key::F(n));
+    }
+
+    let key = match s {
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "insert" => Key::Insert,
+        "esc" => Key::Esc,
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        s => Key::Char(single(s)?),
+    };
+
+    Some(key)
+}
+
+/// The name of `key`, as parsed by `parse_key`.
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Up => "up".to_string(),
+        Key::Down => "down".to_string(),
+        Key::Left => "left".to_string(),
+        Key::Right => "right".to_string(),
+        Key::PageUp => "pageup".to_string(),
+        Key::PageDown => "pagedown".to_string(),
+        Key::Home => "home".to_string(),
+        Key::End => "end".to_string(),
+        Key::Backspace => "backspace".to_string(),
+        Key::Delete => "delete".to_string(),
+        Key::Insert => "insert".to_string(),
+        Key::Esc => "esc".to_string(),
+        Key::Char('\n') => "enter".to_string(),
+        Key::Char('\t') => "tab".to_string(),
+        Key::Char(' ') => "space".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Alt(c) => format!("alt-{}", c),
+        Key::F(n) => format!("f{}", n),
+        _ => "?".to_string(),
+    }
+}
+
+/// Whether `key` is typed in a text input, instead of being looked up in the
+/// keymap.
+pub fn is_text(key: Key) -> bool {
+    match key {
+        Key::Char(c) => c != '\n' && c != '\t',
+        Key::Backspace => true,
+        _ => false,
+    }
+}
+
+pub struct Keymap {
+    /// Later bindings hide the earlier ones for the same context and key.
+    bindings: Vec<(Context, Key, Action)>,
+}
+
+impl Keymap {
+    fn new() -> Keymap {
+        Keymap { bindings: vec![] }
+    }
+
+    fn bind(&mut self, context: Context, key: Key, action: Option<Action>) {
+        self.bindings
+            .retain(|(c, k, _)| !(*c == context && *k == key));
+
+        if let Some(action) = action {
+            self.bindings.push((context, key, action));
+        }
+    }
+
+    fn bind_all(&mut self, context: Context, bindings: &[(Key, Action)]) {
+        for (key, action) in bindings {
+            self.bind(context, *key, Some(*action));
+        }
+    }
+
+    /// Arrows, enter and escape, with a few letters for the actions of the
+    /// tabs.
+    pub fn default() -> Keymap {
+        use Action::*;
+        use Key::*;
+
+        let mut keymap = Keymap::new();
+
+        keymap.bind_all(
+            Context::Global,
+            &[
+                (Key::Up, Action::Up),
+                (Key::Down, Action::Down),
+                (Key::Left, Action::Left),
+                (Key::Right, Action::Right),
+                (Key::PageUp, Action::PageUp),
+                (Key::PageDown, Action::PageDown),
+                (Key::Home, Action::Home),
+                (Key::End, Action::End),
+                (Char('\n'), Select),
+                (Esc, Back),
+                (Char('?'), Help),
+                (Char(':'), CommandLine),
+                (Char('\t'), NextField),
+                (Ctrl('s'), Publish),
+                (Char('f'), CycleFormat),
+                (Char('F'), CycleTopicFormat),
+            ],
+        );
+        keymap.bind_all(
+            Context::Input,
+            &[
+                (Ctrl('a'), Action::Home),
+                (Ctrl('e'), Action::End),
+                (Ctrl('w'), DeleteWord),
+            ],
+        );
+        keymap.bind_all(
+            Context::Subscriptions,
+            &[
+                (Char('\t'), Complete),
+                (Char('d'), Action::Delete),
+                (Key::Delete, Action::Delete),
+                (Backspace, Action::Delete),
+            ],
+        );
+        keymap.bind_all(
+            Context::Stream,
+            &[
+                (Char('p'), Pause),
+                (Char('z'), Compact),
+                (Char('x'), ClearFilter),
+                (Backspace, ClearFilter),
+                (Char('/'), Search),
+                (Char('n'), NextMatch),
+                (Char('N'), PreviousMatch),
+                (Char('|'), SplitHorizontal),
+                (Char('-'), SplitVertical),
+                (Char('c'), ClosePane),
+                (Char('\t'), NextPane),
+            ],
+        );
+        keymap.bind_all(
+            Context::Retain,
+            &[
+                (Char('d'), ClearRetained),
+                (Char('D'), ClearRetainedSubtree),
+            ],
+        );
+        keymap.bind_all(
+            Context::Tree,
+            &[
+                (Char(' '), Select),
+                (Char('s'), Subscribe),
+                (Char('u'), Unsubscribe),
+                (Char('g'), FilterStream),
+                (Char('p'), Pin),
+                (Char('d'), ClearRetainedSubtree),
+            ],
+        );
+        keymap.bind_all(
+            Context::Stats,
+            &[
+                (Char('s'), Sort),
+                (Char('r'), Reverse),
+                (Char('c'), ClearStats),
+            ],
+        );
+        keymap.bind_all(
+            Context::Charts,
+            &[
+                (Char('\t'), Complete),
+                (Char('w'), CycleWindow),
+                (Char('d'), Action::Delete),
+                (Key::Delete, Action::Delete),
+            ],
+        );
+        keymap.bind_all(
+            Context::Dashboard,
+            &[(Char('d'), Action::Delete), (Key::Delete, Action::Delete)],
+        );
+        keymap.bind_all(
+            Context::Detail,
+            &[(Char(' '), Action::PageDown), (Char('q'), Back)],
+        );
+        keymap.bind_all(
+            Context::Publish,
+            &[
+                (Char('q'), CycleQos),
+                (Char('r'), ToggleRetain),
+                (Char('x'), ToggleHex),
+                (Char('e'), Edit),
+                (Char('\n'), Publish),
+            ],
+        );
+        keymap.bind_all(Context::CommandLine, &[(Char('\t'), Complete)]);
+        keymap.bind_all(
+            Context::Confirm,
+            &[
+                (Char('y'), Yes),
+                (Char('Y'), Yes),
+                (Char('n'), No),
+                (Char('N'), No),
+                (Char('q'), No),
+            ],
+        );
+
+        keymap
+    }
+
+    /// The default bindings, with the movements of vim.
+    pub fn vim() -> Keymap {
+        use Key::*;
+
+        let mut keymap = Keymap::default();
+
+        keymap.bind_all(
+            Context::Global,
+            &[
+                (Char('k'), Action::Up),
+                (Char('j'), Action::Down),
+                (Char('h'), Action::Left),
+                (Char('l'), Action::Right),
+                (Ctrl('u'), Action::PageUp),
+                (Ctrl('b'), Action::PageUp),
+                (Ctrl('d'), Action::PageDown),
+                (Ctrl('f'), Action::PageDown),
+                (Char('g'), Action::Home),
+                (Char('G'), Action::End),
+            ],
+        );
+        keymap.bind_all(Context::Subscriptions, &[(Char('x'), Action::Delete)]);
+
+        keymap
+    }
+
+    pub fn preset(name: &str) -> Option<Keymap> {
+        match name {
+            "default" => Some(Keymap::default()),
+            "vim" => Some(Keymap::vim()),
+            _ => None,
+        }
+    }
+
+    /// The preset chosen in the configuration, with the bindings of the
+    /// configuration on top of it.
+    pub fn from_config(config: &Config) -> Result<Keymap, config::Error> {
+        let preset = config.keymap.as_deref().unwrap_or("default");
+        let mut keymap = Keymap::preset(preset)
+            .ok_or_else(|| config::Error::InvalidValue("keymap".into(), preset.into()))?;
+
+        for (context_name, bindings) in &config.keys {
+            let context = Context::ALL
+                .iter()
+                .find(|c| c.name() == context_name)
+                .cloned()
+                .ok_or_else(|| config::Error::InvalidValue("keys".into(), context_name.clone()))?;
+
+            for (key_name, action_name) in bindings {
+                let field = format!("keys.{}", context_name);
+                let key = parse_key(key_name)
+                    .ok_or_else(|| config::Error::InvalidValue(field.clone(), key_name.clone()))?;
+                let action = match action_name.as_str() {
+                    "none" => None,
+                    name => Some(
+                        name.parse::<Action>()
+                            .map_err(|_| config::Error::InvalidValue(field, name.into()))?,
+                    ),
+                };
+
+                keymap.bind(context, key, action);
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// The action bound to `key` in the first of `contexts` binding it.
+    pub fn action(&self, contexts: &[Context], key: Key) -> Option<Action> {
+        contexts.iter().find_map(|context| {
+            self.bindings
+                .iter()
+                .find(|(c, k, _)| c == context && *k == key)
+                .map(|(_, _, action)| *action)
+        })
+    }
+
+    /// The keys doing `action` in `contexts`.
+    pub fn keys(&self, contexts: &[Context], action: Action) -> Vec<Key> {
+        let mut keys = vec![];
+
+        for (context, key, a) in &self.bindings {
+            if *a == action
+                && contexts.contains(context)
+                && !keys.contains(key)
+                && self.action(contexts, *key) == Some(action)
+            {
+                keys.push(*key);
+            }
+        }
+
+        keys
+    }
+
+    /// Hints like `d: clear`, with the first key doing each action in
+    /// `contexts`, for the title of a block. Actions without a key are left
+    /// out.
+    pub fn hints(&self, contexts: &[Context], hints: &[(Action, &str)]) -> String {
+        hints
+            .iter()
+            .filter_map(|(action, description)| {
+                let key = *self.keys(contexts, *action).first()?;
+                Some(format!("{}: {}", key_name(key), description))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The contexts of the bindings of a block, and the actions it handles.
+pub fn bindings_of(block_id: BlockId, tab: usize) -> (&'static [Context], &'static [Action]) {
+    use Action::*;
+
+    const INPUT: &[Context] = &[Context::Input, Context::Global];
+
+    let is = |mode: Mode| tab == mode as usize;
+
+    match block_id {
+        BlockId::TabNav if is(Mode::Stream) => (
+            &[Context::Stream, Context::Tabs, Context::Global],
+            &[
+                Left,
+                Right,
+                Down,
+                PageUp,
+                Home,
+                End,
+                Pause,
+                Compact,
+                ClearFilter,
+                Search,
+                NextMatch,
+                PreviousMatch,
+                SplitHorizontal,
+                SplitVertical,
+                ClosePane,
+                NextPane,
+                CycleFormat,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::TabNav if is(Mode::Stats) => (
+            &[Context::Stats, Context::Tabs, Context::Global],
+            &[
+                Left,
+                Right,
+                Down,
+                Sort,
+                Reverse,
+                ClearStats,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::TabNav if is(Mode::Charts) => (
+            &[Context::Charts, Context::Tabs, Context::Global],
+            &[Left, Right, Down, CycleWindow, Help, CommandLine],
+        ),
+        BlockId::TabNav if is(Mode::Dashboard) => (
+            &[Context::Dashboard, Context::Tabs, Context::Global],
+            &[Left, Right, Down, CycleFormat, Help, CommandLine],
+        ),
+        BlockId::TabNav if is(Mode::Subscriptions) => (
+            &[Context::Tabs, Context::Global],
+            &[Left, Right, Down, Help, CommandLine],
+        ),
+        BlockId::TabNav => (
+            &[Context::Tabs, Context::Global],
+            &[Left, Right, Down, CycleFormat, Help, CommandLine],
+        ),
+        BlockId::SubscribeInput => (
+            &[Context::Subscriptions, Context::Input, Context::Global],
+            &[
+                Up, Down, Left, Right, Home, End, DeleteWord, Complete, Select, Back,
+            ],
+        ),
+        BlockId::SubscriptionsList => (
+            &[Context::Subscriptions, Context::Global],
+            &[Up, Select, Back, Help, CommandLine],
+        ),
+        BlockId::SubscriptionsListItem(_) => (
+            &[Context::Subscriptions, Context::Global],
+            &[Up, Down, Action::Delete, Back, Help, CommandLine],
+        ),
+        BlockId::StatsItem(_) => (
+            &[Context::Stats, Context::Global],
+            &[
+                Up,
+                Down,
+                PageUp,
+                PageDown,
+                Home,
+                End,
+                Sort,
+                Reverse,
+                ClearStats,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::TopicTreeItem(_) => (
+            &[Context::Tree, Context::Global],
+            &[
+                Up,
+                Down,
+                Left,
+                Right,
+                Select,
+                Subscribe,
+                Unsubscribe,
+                FilterStream,
+                Pin,
+                CycleFormat,
+                CycleTopicFormat,
+                ClearRetainedSubtree,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::FilterInput => (
+            &[Context::Stream, Context::Input, Context::Global],
+            &[Up, Down, NextPane, Select, Back],
+        ),
+        BlockId::SearchInput => (INPUT, &[Left, Right, Home, End, DeleteWord, Select, Back]),
+        BlockId::StreamItem(_) => (
+            &[Context::Stream, Context::Global],
+            &[
+                Up,
+                Down,
+                PageUp,
+                PageDown,
+                Home,
+                End,
+                Select,
+                Pause,
+                Compact,
+                Search,
+                NextMatch,
+                PreviousMatch,
+                SplitHorizontal,
+                SplitVertical,
+                ClosePane,
+                NextPane,
+                CycleFormat,
+                CycleTopicFormat,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::RetainItem(_) => (
+            &[Context::Retain, Context::Global],
+            &[
+                Up,
+                Down,
+                Select,
+                CycleFormat,
+                CycleTopicFormat,
+                ClearRetained,
+                ClearRetainedSubtree,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::ChartInput => (
+            &[Context::Charts, Context::Input, Context::Global],
+            &[
+                Up, Down, Left, Right, Home, End, DeleteWord, Complete, Select, Back,
+            ],
+        ),
+        BlockId::ChartSeriesItem(_) => (
+            &[Context::Charts, Context::Global],
+            &[
+                Up,
+                Down,
+                Action::Delete,
+                CycleWindow,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::DashboardItem(_) => (
+            &[Context::Dashboard, Context::Global],
+            &[
+                Up,
+                Down,
+                Left,
+                Right,
+                Action::Delete,
+                CycleFormat,
+                CycleTopicFormat,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::MessageDetail => (
+            &[Context::Detail, Context::Global],
+            &[Up, Down, PageUp, PageDown, Home, Back, Help, CommandLine],
+        ),
+        BlockId::PublishTopic => (
+            INPUT,
+            &[
+                Up, Down, Left, Right, Home, End, DeleteWord, Select, NextField, Publish, Back,
+            ],
+        ),
+        BlockId::PublishPayload => (
+            INPUT,
+            &[
+                Up, Down, Left, Right, Home, End, DeleteWord, NextField, Publish, Back,
+            ],
+        ),
+        BlockId::PublishOptions => (
+            &[Context::Publish, Context::Global],
+            &[
+                Up,
+                Down,
+                NextField,
+                CycleQos,
+                ToggleRetain,
+                ToggleHex,
+                Publish,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::PublishHistoryItem(_) => (
+            &[Context::Publish, Context::Global],
+            &[
+                Up,
+                Down,
+                Publish,
+                Edit,
+                CycleFormat,
+                CycleTopicFormat,
+                Back,
+                Help,
+                CommandLine,
+            ],
+        ),
+        BlockId::CommandLine => (
+            &[Context::CommandLine, Context::Input, Context::Global],
+            &[
+                Up, Down, Left, Right, Home, End, DeleteWord, Complete, Select, Back,
+            ],
+        ),
+        BlockId::Confirm => (&[Context::Confirm, Context::Global], &[Yes, No, Back]),
+        BlockId::Root | BlockId::SubscriptionsWindow | BlockId::Help => (&[Context::Global], &[]),
+    }
+}
@@ -0,0 +1,177 @@
+use termion::event::Key;
+use tui::style::{Color, Style};
+use tui::widgets::Text;
+
+use crate::tui::keymap::Action;
+
+/// A line of text with a cursor.
+#[derive(Clone, Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor in the text.
+    cursor: usize,
+    /// Enter inserts a newline, and home and end move within the current
+    /// line.
+    multiline: bool,
+}
+
+impl LineEditor {
+    /// An editor for text spanning several lines.
+    pub fn multiline() -> LineEditor {
+        LineEditor {
+            multiline: true,
+            ..LineEditor::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The text before the cursor.
+    pub fn head(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    /// Replace the text, with the cursor at its end.
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    /// Replace the text before the cursor.
+    fn set_head(&mut self, head: &str) {
+        self.text.replace_range(..self.cursor, head);
+        self.cursor = head.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Start of the line of the cursor.
+    fn line_start(&self) -> usize {
+        if self.multiline {
+            self.head().rfind('\n').map_or(0, |i| i + 1)
+        } else {
+            0
+        }
+    }
+
+    /// End of the line of the cursor.
+    fn line_end(&self) -> usize {
+        if self.multiline {
+            self.text[self.cursor..]
+                .find('\n')
+                .map_or(self.text.len(), |i| self.cursor + i)
+        } else {
+            self.text.len()
+        }
+    }
+
+    fn previous(&self) -> usize {
+        self.head().char_indices().next_back().map_or(0, |(i, _)| i)
+    }
+
+    fn next(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    pub fn backspace(&mut self) {
+        let previous = self.previous();
+        self.text.replace_range(previous..self.cursor, "");
+        self.cursor = previous;
+    }
+
+    pub fn delete(&mut self) {
+        let next = self.next();
+        self.text.replace_range(self.cursor..next, "");
+    }
+
+    /// Delete the word before the cursor, with the separators after it. The
+    /// levels of a topic are words.
+    pub fn delete_word(&mut self) {
+        let is_separator = |c: char| c.is_whitespace() || c == '/';
+        let head = self.head().trim_end_matches(is_separator);
+        let start = head.rfind(is_separator).map_or(0, |i| i + 1);
+
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Replace the word before the cursor with the only candidate, or with
+    /// the prefix that all the candidates share.
+    pub fn complete(&mut self, candidates: &[String]) {
+        match candidates {
+            [] => {}
+            [candidate] => self.set_head(candidate),
+            _ => {
+                let prefix = common_prefix(candidates);
+                if prefix.len() > self.head().len() {
+                    self.set_head(&prefix);
+                }
+            }
+        }
+    }
+
+    /// Apply a key to the text, if it is an editing key. Return whether the
+    /// key was used.
+    pub fn handle(&mut self, input: Key, action: Option<Action>) -> bool {
+        match (input, action) {
+            (Key::Char('\n'), _) if self.multiline => self.insert('\n'),
+            (_, Some(Action::Left)) => self.cursor = self.previous(),
+            (_, Some(Action::Right)) => self.cursor = self.next(),
+            (_, Some(Action::Home)) => self.cursor = self.line_start(),
+            (_, Some(Action::End)) => self.cursor = self.line_end(),
+            (_, Some(Action::DeleteWord)) => self.delete_word(),
+            (Key::Backspace, None) => self.backspace(),
+            (Key::Delete, None) => self.delete(),
+            (Key::Char(c), None) => self.insert(c),
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// The text, with the cursor shown when `focused`.
+    pub fn spans(&self, focused: bool) -> Vec<Text<'_>> {
+        if !focused {
+            return vec![Text::raw(self.text.as_str())];
+        }
+
+        // At the end of a line, the cursor is shown on a space after it.
+        let next = self.next();
+        let (cursor, tail) = match &self.text[self.cursor..next] {
+            "" | "\n" => (" ", &self.text[self.cursor..]),
+            c => (c, &self.text[next..]),
+        };
+
+        vec![
+            Text::raw(self.head()),
+            Text::styled(cursor, Style::default().fg(Color::Black).bg(Color::Yellow)),
+            Text::raw(tail),
+        ]
+    }
+}
+
+/// The longest prefix shared by every candidate.
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = candidates.first().cloned().unwrap_or_default();
+
+    for candidate in candidates {
+        while !candidate.starts_with(&prefix) {
+            prefix.pop();
+        }
+    }
+
+    prefix
+}
@@ -1,9 +1,12 @@
-use std::collections::BTreeMap;
+use std::cell::{Cell, RefMut};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{self};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
 
-use circular_queue::CircularQueue;
-use rumqtt::Notification;
 use termion::event::Key;
 use termion::input::MouseTerminal;
 use termion::raw::IntoRawMode;
@@ -14,52 +17,487 @@ use tui::style::{Color, Style};
 use tui::widgets::{Block, Borders, Tabs, Widget};
 use tui::{Frame, Terminal};
 
+use charts::{
+    draw_charts_tab, handle_input_on_chart_input, handle_input_on_chart_series_item,
+    handle_input_on_charts, Charts,
+};
+use command::{
+    draw_command_line, draw_report, handle_input_on_command_line, open_command_line, CommandLine,
+};
+use confirm::{draw_confirmation, handle_input_on_confirm, Confirmation};
+use dashboard::{draw_dashboard_tab, handle_input_on_dashboard_item, Dashboard};
+use detail::handle_input_on_message_detail;
+use help::draw_help;
+use keymap::{bindings_of, is_text, Action, Context};
+use mouse::{add_tabs, handle_mouse, Hit, HitMap};
 use navigation::{BlockId, Navigation};
+use panes::{Pane, Panes};
+use publish::{
+    draw_publish_tab, handle_input_on_publish_history_item, handle_input_on_publish_options,
+    handle_input_on_publish_payload, handle_input_on_publish_topic, PublishForm,
+};
 use retain::draw_retain_tab;
-use stream::draw_stream_tab;
+use retain::handle_input_on_retain_item;
+use search::{draw_search_input, handle_input_on_search_input, Search};
+use stats::{draw_stats_tab, handle_input_on_stats, handle_input_on_stats_item};
+use status::{draw_status_bar, Status};
+use stream::{
+    draw_stream_tab, handle_input_on_filter_input, handle_input_on_stream,
+    handle_input_on_stream_item,
+};
 use style::get_color;
 use subscriptions::{
     draw_subscriptions_tab, handle_input_on_subscribe_input, handle_input_on_subscriptions_list,
-    handle_input_on_subscriptions_list_item,
+    handle_input_on_subscriptions_list_item, SubscribeInput,
 };
 use tabs::TabsState;
+use tree::{draw_tree_tab, handle_input_on_tree_item};
 use utils::{Event, Events};
 
+pub use keymap::Keymap;
+
+use mqtt_analyzer::config::Config;
+use mqtt_analyzer::engine::{self, Engine};
+use mqtt_analyzer::filter::Filter;
+use mqtt_analyzer::format::{MessageFormat, PayloadFormat};
+use mqtt_analyzer::history::{Capacity, Collapsed, History};
+use mqtt_analyzer::message::{Message, Notification};
+use mqtt_analyzer::session::Session;
+use mqtt_analyzer::stats::{SortKey, Stats};
+use mqtt_analyzer::topic;
+use mqtt_analyzer::tree::TopicTree;
+
 use super::cli::Mode;
-use super::engine::Engine;
-use super::format::MessageFormat;
 
+mod charts;
+mod command;
+mod confirm;
+mod dashboard;
+mod detail;
+mod help;
+mod keymap;
+mod line_editor;
+mod mouse;
 mod navigation;
 mod notification_list;
+mod panes;
+mod popup;
+mod publish;
 mod retain;
+mod search;
+mod session;
+mod stats;
+mod status;
 mod stream;
 mod style;
 mod subscriptions;
 mod tabs;
+mod tree;
 mod utils;
 
+/// Settings of the TUI.
+pub struct Options {
+    pub format: MessageFormat,
+    pub mode: Mode,
+    pub sort: SortKey,
+    pub filter: Option<Filter>,
+    pub history: Capacity,
+    /// Where the notifications come from, like the broker address.
+    pub source: String,
+    /// Client id used to connect to the broker, if any.
+    pub client_id: Option<String>,
+    pub keymap: Keymap,
+    /// Settings read at startup, saved again when they are changed.
+    pub config: Config,
+    pub config_path: Option<PathBuf>,
+    /// State of the last session, to restore.
+    pub session: Option<Session>,
+    /// Why the last session couldn't be read, to report at startup.
+    pub session_error: Option<String>,
+    /// Where the state of the session is saved on exit.
+    pub session_path: Option<PathBuf>,
+}
+
 type RetainedMessages = BTreeMap<String, Notification>;
+type TopicFormats = BTreeMap<String, PayloadFormat>;
 
 pub struct App {
     engine: Engine,
     tabs: TabsState,
-    subscribe_input: String,
-    notifications: CircularQueue<Notification>,
+    subscribe_input: SubscribeInput,
+    notifications: History,
     retained_messages: RetainedMessages,
     navigation: Navigation,
+    last_error: Option<engine::Error>,
+    stats: Stats,
+    stats_sort: SortKey,
+    stats_reverse: bool,
+    charts: Charts,
+    dashboard: Dashboard,
+    topic_tree: TopicTree,
+    tree_expanded: BTreeSet<String>,
+    /// Lists of the stream tab.
+    panes: Panes,
+    search: Search,
+    /// Message shown in the detail pane.
+    detail: Option<Message>,
+    detail_scroll: u16,
+    /// Width and height of the text of the detail pane, as last drawn.
+    detail_size: Cell<(u16, u16)>,
+    publish: PublishForm,
+    /// Payload format of each tab.
+    view_formats: Vec<PayloadFormat>,
+    /// Formats chosen for some topics, instead of the one of the tab.
+    topic_formats: TopicFormats,
+    /// Action waiting for the user's confirmation.
+    confirmation: Option<Confirmation>,
+    status: Status,
+    /// Areas of the last frame, to find what the mouse points to.
+    hits: HitMap,
+    last_click: Option<(Instant, Hit)>,
+    keymap: Keymap,
+    command_line: CommandLine,
+    config: Config,
+    /// Where the changes of the configuration are saved.
+    config_path: Option<PathBuf>,
+    session_path: Option<PathBuf>,
+    /// Set to leave the TUI.
+    quit: bool,
 }
 
 impl App {
-    fn new(engine: Engine) -> App {
-        App {
+    fn new(engine: Engine, options: Options) -> App {
+        let Options {
+            format,
+            mode,
+            sort: stats_sort,
+            filter,
+            history,
+            source,
+            client_id,
+            keymap,
+            config,
+            config_path,
+            session,
+            session_error,
+            session_path,
+            ..
+        } = options;
+
+        let mut app = App {
             engine,
-            tabs: TabsState::default(),
-            subscribe_input: String::new(),
-            notifications: CircularQueue::with_capacity(100),
+            tabs: TabsState {
+                index: mode as usize,
+            },
+            subscribe_input: SubscribeInput::load(),
+            notifications: History::new(history),
             retained_messages: RetainedMessages::default(),
             navigation: Navigation::default(),
+            last_error: None,
+            stats: Stats::new(),
+            stats_sort,
+            stats_reverse: false,
+            charts: Charts::default(),
+            dashboard: Dashboard::default(),
+            topic_tree: TopicTree::new(),
+            tree_expanded: BTreeSet::new(),
+            panes: Panes::new(Pane::new(filter, format.payload_format)),
+            search: Search::default(),
+            detail: None,
+            detail_scroll: 0,
+            detail_size: Cell::new((0, 0)),
+            publish: PublishForm::new(),
+            view_formats: vec![format.payload_format; TabsState::TITLES.len()],
+            topic_formats: TopicFormats::new(),
+            confirmation: None,
+            status: Status::new(source, client_id),
+            hits: HitMap::default(),
+            last_click: None,
+            keymap,
+            command_line: CommandLine::default(),
+            config,
+            config_path,
+            session_path,
+            quit: false,
+        };
+
+        if let Some(session) = &session {
+            session::restore(&mut app, session);
+        }
+        if let Some(e) = session_error {
+            app.command_line.set_report(Err(e));
+        }
+
+        app
+    }
+
+    /// Notifications received since `pane` was paused, including the ones
+    /// not matching its filter.
+    fn unseen(&self, pane: &Pane) -> usize {
+        pane.paused_at.map_or(0, |paused_at| {
+            (self.notifications.pushed() - paused_at).min(self.notifications.len() as u64) as usize
+        })
+    }
+
+    /// Notifications matching the filter of `pane`, the newest first,
+    /// before the repeated ones are collapsed.
+    fn shown_notifications<'a>(&'a self, pane: &'a Pane) -> impl Iterator<Item = &'a Notification> {
+        self.notifications
+            .iter()
+            .skip(self.unseen(pane))
+            .filter(move |notification| pane.is_shown(notification))
+    }
+
+    /// Lines of `pane` in compact mode. The history is only collapsed again
+    /// when they were reset, like when the filter changes.
+    fn collapsed<'a>(&self, pane: &'a Pane) -> RefMut<'a, Collapsed> {
+        RefMut::map(pane.collapsed.borrow_mut(), |collapsed| {
+            collapsed.get_or_insert_with(|| {
+                let mut collapsed = Collapsed::default();
+                for (seq, notification) in self.notifications.numbered() {
+                    if pane.has(seq, notification) {
+                        collapsed.push(seq, notification);
+                    }
+                }
+                collapsed
+            })
+        })
+    }
+
+    /// Lines of `pane` in compact mode, the newest first, with the number of
+    /// messages each of them stands for.
+    fn collapsed_lines(&self, pane: &Pane) -> Vec<(&Notification, usize)> {
+        self.collapsed(pane)
+            .lines()
+            .filter_map(|(seq, count)| Some((self.notifications.get(seq)?, count)))
+            .collect()
+    }
+
+    /// Lines of `pane`, the newest first, with the number of messages each
+    /// of them stands for.
+    fn pane_lines<'a>(&'a self, pane: &'a Pane) -> Vec<(&'a Notification, usize)> {
+        if pane.compact {
+            self.collapsed_lines(pane)
+        } else {
+            self.shown_notifications(pane).map(|n| (n, 1)).collect()
+        }
+    }
+
+    /// Notifications shown in `pane`, one per line, the newest first.
+    fn pane_notifications<'a>(
+        &'a self,
+        pane: &'a Pane,
+    ) -> Box<dyn Iterator<Item = &'a Notification> + 'a> {
+        if pane.compact {
+            Box::new(self.collapsed_lines(pane).into_iter().map(|(n, _)| n))
+        } else {
+            Box::new(self.shown_notifications(pane))
+        }
+    }
+
+    /// Notifications that will be shown in `pane` when it is resumed.
+    fn new_pane_notifications(&self, pane: &Pane) -> usize {
+        self.notifications
+            .iter()
+            .take(self.unseen(pane))
+            .filter(|notification| pane.is_shown(notification))
+            .count()
+    }
+
+    /// Notifications shown in the focused pane of the stream, the newest
+    /// first.
+    fn stream_notifications(&self) -> impl Iterator<Item = &Notification> {
+        self.pane_notifications(self.panes.focused())
+    }
+
+    /// Forget the notifications of the stream.
+    fn clear_stream(&mut self) {
+        self.notifications.clear();
+        for pane in self.panes.iter_mut() {
+            pane.paused_at = None;
+            pane.collapsed.replace(None);
+        }
+    }
+
+    /// Freeze the focused pane of the stream, or resume it. Notifications
+    /// are still buffered while it is paused.
+    fn toggle_pause(&mut self) {
+        if self.panes.focused().paused_at.is_some() {
+            let before = self.stream_notifications().count();
+            let pane = self.panes.focused_mut();
+            pane.paused_at = None;
+            pane.collapsed.replace(None);
+            let new = self.stream_notifications().count() - before;
+            self.navigation.update(|block_id| match block_id {
+                BlockId::StreamItem(i) => BlockId::StreamItem(i + new),
+                block_id => block_id,
+            });
+        } else {
+            self.panes.focused_mut().paused_at = Some(self.notifications.pushed());
+        }
+    }
+
+    /// Focus the pane at `index` of the stream. The selection was in the
+    /// list of the previous pane: it moves to the filter of the new one.
+    fn focus_pane(&mut self, index: usize) {
+        self.panes.set_focus(index);
+        self.navigation.update(|block_id| match block_id {
+            BlockId::StreamItem(_) => BlockId::FilterInput,
+            block_id => block_id,
+        });
+    }
+
+    fn handle_notification(&mut self, notification: Notification) {
+        self.status.handle_notification(&notification);
+        self.publish.handle_notification(&notification);
+
+        match &notification {
+            Notification::Publish(msg) => {
+                self.stats.update(msg);
+                self.charts.update(msg);
+                self.topic_tree.insert(msg);
+                self.update_retained(msg);
+            }
+            Notification::Published(msg, _) => self.update_retained(msg),
+            Notification::Error(e) => self.last_error = Some(e.clone()),
+            _ => {}
+        }
+
+        // Keep the same notification selected, as it moves up the stream.
+        let pane = self.panes.focused();
+        if pane.paused_at.is_none() && pane.is_shown(&notification) {
+            let selected = self
+                .navigation
+                .iter()
+                .any(|block_id| matches!(block_id, BlockId::StreamItem(_)));
+
+            // A repeat moves the line of the previous one to the bottom.
+            let repeated = match pane.compact && selected {
+                true => self.collapsed(pane).repeated_line(&notification),
+                false => None,
+            };
+
+            self.navigation
+                .update(|block_id| match (block_id, repeated) {
+                    (BlockId::StreamItem(i), Some(line)) if i == line => BlockId::StreamItem(0),
+                    (BlockId::StreamItem(i), Some(line)) if i > line => BlockId::StreamItem(i),
+                    (BlockId::StreamItem(i), _) => BlockId::StreamItem(i + 1),
+                    (block_id, _) => block_id,
+                });
+        }
+
+        let seq = self.notifications.pushed();
+        for pane in self.panes.iter() {
+            if let (true, Some(collapsed)) = (pane.compact, pane.collapsed.borrow_mut().as_mut()) {
+                if pane.has(seq, &notification) {
+                    collapsed.push(seq, &notification);
+                }
+            }
+        }
+
+        let first = self.notifications.first();
+        let dropped = self.notifications.push(notification);
+
+        for pane in self.panes.iter() {
+            if let (true, Some(collapsed)) = (pane.compact, pane.collapsed.borrow_mut().as_mut()) {
+                for (seq, notification) in (first..).zip(&dropped) {
+                    if pane.has(seq, notification) {
+                        collapsed.remove(seq, notification);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Payload format of the current tab, or of the focused pane of the
+    /// stream.
+    fn view_format(&self) -> PayloadFormat {
+        match self.tabs.index {
+            i if i == Mode::Stream as usize => self.panes.focused().format,
+            i => self.view_formats[i],
         }
     }
+
+    fn set_view_format(&mut self, format: PayloadFormat) {
+        match self.tabs.index {
+            i if i == Mode::Stream as usize => self.panes.focused_mut().format = format,
+            i => self.view_formats[i] = format,
+        }
+    }
+
+    fn cycle_view_format(&mut self) {
+        self.set_view_format(self.view_format().next());
+    }
+
+    /// Show `topic` with the next format, until it is back to the format
+    /// of the current tab.
+    fn cycle_topic_format(&mut self, topic: &str) {
+        let view_format = self.view_format();
+        let next = self
+            .topic_formats
+            .get(topic)
+            .map_or(view_format, |format| *format)
+            .next();
+
+        if next == view_format {
+            self.topic_formats.remove(topic);
+        } else {
+            self.topic_formats.insert(topic.to_string(), next);
+        }
+    }
+
+    /// Payload format of `topic` in the current tab.
+    fn payload_format(&self, topic: &str) -> PayloadFormat {
+        self.topic_formats
+            .get(topic)
+            .map_or(self.view_format(), |format| *format)
+    }
+
+    /// Track the messages retained by the broker, from the retain flag of
+    /// the received and published messages.
+    fn update_retained(&mut self, msg: &Message) {
+        if !msg.retain {
+            return;
+        }
+
+        let position = self.retained_messages.range(..msg.topic.clone()).count();
+
+        // Keep the same retained message selected.
+        if msg.payload.is_empty() {
+            if self.retained_messages.remove(&msg.topic).is_some() {
+                self.navigation.update(|block_id| match block_id {
+                    BlockId::RetainItem(i) if i > position => BlockId::RetainItem(i - 1),
+                    block_id => block_id,
+                });
+            }
+        } else {
+            if !self.retained_messages.contains_key(&msg.topic) {
+                self.navigation.update(|block_id| match block_id {
+                    BlockId::RetainItem(i) if i >= position => BlockId::RetainItem(i + 1),
+                    block_id => block_id,
+                });
+            }
+
+            self.retained_messages
+                .insert(msg.topic.clone(), Notification::Publish(msg.clone()));
+        }
+    }
+
+    /// Topics of the retained messages at `topic` or below it.
+    fn retained_under(&self, topic: &str) -> Vec<String> {
+        let filter = format!("{}/#", topic);
+        self.retained_messages
+            .keys()
+            .filter(|t| topic::matches(&filter, t))
+            .cloned()
+            .collect()
+    }
+
+    /// Change the filter text of the focused pane of the stream, applying
+    /// it as soon as it is valid.
+    fn set_filter_input(&mut self, input: String) {
+        self.panes.focused_mut().set_filter_input(input);
+    }
 }
 
 fn draw_tab_nav<B>(f: &mut Frame<B>, area: Rect, app: &App)
@@ -75,6 +513,8 @@ where
 
     let style = get_color(highlight_state);
 
+    add_tabs(&app.hits, area, TabsState::TITLES);
+
     Tabs::default()
         .block(Block::default().borders(Borders::ALL).border_style(style))
         .titles(TabsState::TITLES)
@@ -84,28 +524,68 @@ where
         .render(f, area);
 }
 
-fn handle_input_on_tabs(c: termion::event::Key, app: &mut App) {
+fn handle_input_on_tabs(action: Action, app: &mut App) {
+    use Action::*;
     use BlockId::*;
-    use Key::*;
 
-    match c {
+    match action {
         Right => app.tabs.next(),
         Left => app.tabs.previous(),
-        Down | Key::Char('j') => {
+        Down if app.tabs.index == Mode::Tree as usize => {
+            let has_topics = app.topic_tree.count() > 0;
+            app.navigation
+                .modify_top(if has_topics { TopicTreeItem(0) } else { TabNav });
+        }
+        Down if app.tabs.index == Mode::Stream as usize => {
+            app.navigation.modify_top(FilterInput);
+        }
+        Down if app.tabs.index == Mode::Retained as usize => {
+            let has_messages = !app.retained_messages.is_empty();
+            app.navigation
+                .modify_top(if has_messages { RetainItem(0) } else { TabNav });
+        }
+        Down if app.tabs.index == Mode::Publish as usize => {
+            app.navigation.modify_top(PublishTopic);
+        }
+        Down if app.tabs.index == Mode::Subscriptions as usize => {
             app.navigation.modify_top(SubscribeInput);
         }
+        Down if app.tabs.index == Mode::Dashboard as usize => {
+            let has_pins = !app.config.dashboard.pins.is_empty();
+            app.navigation
+                .modify_top(if has_pins { DashboardItem(0) } else { TabNav });
+        }
+        Down if app.tabs.index == Mode::Stats as usize => {
+            let has_topics = !app.stats.is_empty();
+            app.navigation
+                .modify_top(if has_topics { StatsItem(0) } else { TabNav });
+        }
+        Down if app.tabs.index == Mode::Charts as usize => {
+            app.navigation.modify_top(ChartInput);
+        }
+        ClearFilter => app.set_filter_input(String::new()),
+        CycleFormat
+            if app.tabs.index != Mode::Subscriptions as usize
+                && app.tabs.index != Mode::Stats as usize
+                && app.tabs.index != Mode::Charts as usize =>
+        {
+            app.cycle_view_format()
+        }
+        action if app.tabs.index == Mode::Stream as usize => handle_input_on_stream(action, app),
+        action if app.tabs.index == Mode::Stats as usize => handle_input_on_stats(action, app),
+        action if app.tabs.index == Mode::Charts as usize => handle_input_on_charts(action, app),
         _ => (),
     }
 }
 
-fn handle_input(input: termion::event::Key, app: &mut App) {
+/// Handle `action` on the focused block.
+fn handle_action(action: Action, app: &mut App) {
     use BlockId::*;
-    use Key::*;
 
     let nav = &mut app.navigation;
 
-    match input {
-        Esc => {
+    match action {
+        Action::Back => {
             nav.pop();
 
             if nav.parent() == Root {
@@ -113,25 +593,66 @@ fn handle_input(input: termion::event::Key, app: &mut App) {
                 nav.push(TabNav);
             }
         }
-        c => match nav.peek() {
-            Root => {
-                nav.push(SubscriptionsWindow);
-                nav.push(TabNav);
+        Action::Help => nav.push(BlockId::Help),
+        Action::CommandLine => open_command_line(app),
+        action => match nav.peek() {
+            TabNav => handle_input_on_tabs(action, app),
+            SubscriptionsList => handle_input_on_subscriptions_list(action, app),
+            SubscriptionsListItem(index) => {
+                handle_input_on_subscriptions_list_item(action, app, index)
             }
-            TabNav => handle_input_on_tabs(c, app),
-            SubscribeInput => handle_input_on_subscribe_input(c, app),
-            SubscriptionsList => handle_input_on_subscriptions_list(c, app),
-            SubscriptionsListItem(index) => handle_input_on_subscriptions_list_item(c, app, index),
+            TopicTreeItem(index) => handle_input_on_tree_item(action, app, index),
+            StreamItem(index) => handle_input_on_stream_item(action, app, index),
+            RetainItem(index) => handle_input_on_retain_item(action, app, index),
+            StatsItem(index) => handle_input_on_stats_item(action, app, index),
+            MessageDetail => handle_input_on_message_detail(action, app),
+            Confirm => handle_input_on_confirm(action, app),
+            PublishOptions => handle_input_on_publish_options(action, app),
+            PublishHistoryItem(index) => handle_input_on_publish_history_item(action, app, index),
+            ChartSeriesItem(index) => handle_input_on_chart_series_item(action, app, index),
+            DashboardItem(index) => handle_input_on_dashboard_item(action, app, index),
             _ => (),
         },
     }
 }
 
-pub fn start_tui(
-    engine: Engine,
-    format_options: MessageFormat,
-    mode: Mode,
-) -> Result<(), failure::Error> {
+fn handle_input(input: Key, app: &mut App) {
+    use BlockId::*;
+
+    let block_id = app.navigation.peek();
+    let (contexts, actions) = bindings_of(block_id, app.tabs.index);
+    let action = if contexts.contains(&Context::Input) && is_text(input) {
+        None
+    } else {
+        app.keymap
+            .action(contexts, input)
+            .filter(|action| actions.contains(action))
+    };
+
+    match (block_id, action) {
+        (Root, _) => {
+            app.navigation.push(SubscriptionsWindow);
+            app.navigation.push(TabNav);
+        }
+        // Any key closes the help.
+        (BlockId::Help, _) => app.navigation.pop(),
+        (_, Some(Action::Back)) => handle_action(Action::Back, app),
+        (SubscribeInput, action) => handle_input_on_subscribe_input(input, action, app),
+        (CommandLine, action) => handle_input_on_command_line(input, action, app),
+        (ChartInput, action) => handle_input_on_chart_input(input, action, app),
+        (FilterInput, action) => handle_input_on_filter_input(input, action, app),
+        (SearchInput, action) => handle_input_on_search_input(input, action, app),
+        (PublishTopic, action) => handle_input_on_publish_topic(input, action, app),
+        (PublishPayload, action) => handle_input_on_publish_payload(input, action, app),
+        (_, Some(action)) => handle_action(action, app),
+        (_, None) => {}
+    }
+}
+
+/// Notifications the event loop can fall behind by.
+const MAX_PENDING_NOTIFICATIONS: usize = 1000;
+
+pub fn start_tui(engine: Engine, options: Options) -> Result<(), failure::Error> {
     use Event::*;
     use Key::*;
 
@@ -148,33 +669,73 @@ pub fn start_tui(
     let tx = events.tx();
 
     let notifications = engine.notifications.clone();
-    let mut app = App::new(engine);
+    let mut app = App::new(engine, options);
 
-    app.tabs.index = mode as usize;
+    // Notifications sent to the event loop and not handled yet. The bridge
+    // waits while there are too many of them, so that the engine channel
+    // fills up and the engine drops, and counts, the messages the TUI can't
+    // keep up with.
+    let pending = Arc::new(AtomicUsize::new(0));
 
-    thread::spawn(move || {
-        for notification in notifications {
-            tx.send(Event::MqttNotification(notification)).unwrap();
-        }
-    });
+    {
+        let pending = pending.clone();
+        thread::spawn(move || {
+            for notification in notifications.iter() {
+                while pending.load(Ordering::Relaxed) >= MAX_PENDING_NOTIFICATIONS {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                pending.fetch_add(1, Ordering::Relaxed);
+                if tx.send(Event::MqttNotification(notification)).is_err() {
+                    return;
+                }
+            }
+        });
+    }
 
     loop {
         terminal.draw(|mut f| {
             let size = f.size();
 
+            app.hits.clear();
+
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+                .constraints(
+                    [
+                        Constraint::Length(3),
+                        Constraint::Min(0),
+                        Constraint::Length(1),
+                    ]
+                    .as_ref(),
+                )
                 .split(size);
 
             draw_tab_nav(&mut f, chunks[0], &app);
+            match app.command_line.report() {
+                _ if app.navigation.peek() == BlockId::CommandLine => {
+                    draw_command_line(&mut f, chunks[2], &app)
+                }
+                _ if app.navigation.peek() == BlockId::SearchInput => {
+                    draw_search_input(&mut f, chunks[2], &app)
+                }
+                Some(report) => draw_report(&mut f, chunks[2], report),
+                None => draw_status_bar(&mut f, chunks[2], &app),
+            }
 
             match app.tabs.index {
                 0 => draw_subscriptions_tab(&mut f, chunks[1], &app),
-                1 => draw_stream_tab(&mut f, chunks[1], &app, format_options),
-                2 => draw_retain_tab(&mut f, chunks[1], &app, format_options),
+                1 => draw_stream_tab(&mut f, chunks[1], &app),
+                2 => draw_retain_tab(&mut f, chunks[1], &app),
+                3 => draw_stats_tab(&mut f, chunks[1], &app),
+                4 => draw_tree_tab(&mut f, chunks[1], &app),
+                5 => draw_publish_tab(&mut f, chunks[1], &app),
+                6 => draw_charts_tab(&mut f, chunks[1], &app),
+                7 => draw_dashboard_tab(&mut f, chunks[1], &app),
                 _ => {}
             }
+
+            draw_confirmation(&mut f, chunks[1], &app);
+            draw_help(&mut f, chunks[1], &app);
         })?;
 
         match events.next()? {
@@ -182,18 +743,22 @@ pub fn start_tui(
                 break;
             }
             Input(input) => handle_input(input, &mut app),
+            Mouse(event) => handle_mouse(event, &mut app),
             MqttNotification(notification) => {
-                if let Notification::Publish(msg) = notification {
-                    let msg = msg.clone();
-                    app.retained_messages
-                        .insert(msg.topic_name.clone(), Notification::Publish(msg.clone()));
-                    app.notifications.push(Notification::Publish(msg));
-                } else {
-                    app.notifications.push(notification);
-                }
+                pending.fetch_sub(1, Ordering::Relaxed);
+                app.handle_notification(notification)
             }
             _ => {}
         }
+
+        if app.quit {
+            break;
+        }
+    }
+
+    // Best effort: failing to save the session must not fail the exit.
+    if let (Some(path), Ok(session)) = (&app.session_path, session::capture(&app)) {
+        let _ = session.save(path);
     }
 
     Ok(())
@@ -0,0 +1,292 @@
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use termion::event::{MouseButton, MouseEvent};
+use tui::layout::{Corner, Rect};
+
+use crate::tui::detail::handle_input_on_message_detail;
+use crate::tui::handle_action;
+use crate::tui::keymap::Action;
+use crate::tui::navigation::{BlockId, Navigation};
+use crate::tui::App;
+
+/// Lines scrolled by a notch of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+/// Two clicks on the same target within this delay make a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// What is below the mouse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hit {
+    Tab(usize),
+    /// A block focused by clicking it, as if it was reached with the keys.
+    Block(BlockId),
+    /// A pane of the stream, focused by clicking it.
+    Pane(usize),
+    /// A list scrolled by the mouse wheel.
+    Scroll(Scrollable),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scrollable {
+    Stream,
+    Retain,
+    Stats,
+    Tree,
+    Detail,
+}
+
+/// The areas drawn in the last frame, with what is in them.
+#[derive(Default)]
+pub struct HitMap {
+    areas: RefCell<Vec<(Rect, Hit)>>,
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    x >= area.left() && x < area.right() && y >= area.top() && y < area.bottom()
+}
+
+/// The area inside the borders of a block.
+fn inner(area: Rect) -> Rect {
+    if area.width < 2 || area.height < 2 {
+        return Rect::new(area.x, area.y, 0, 0);
+    }
+
+    Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2)
+}
+
+impl HitMap {
+    /// Forget the areas of the previous frame.
+    pub fn clear(&self) {
+        self.areas.borrow_mut().clear();
+    }
+
+    pub fn add(&self, area: Rect, hit: Hit) {
+        self.areas.borrow_mut().push((area, hit));
+    }
+
+    /// Add the rows of a list with `len` items, drawn with borders in `area`
+    /// and scrolled to show `selected`, as the list widgets do.
+    pub fn add_rows<F>(
+        &self,
+        area: Rect,
+        len: usize,
+        selected: Option<usize>,
+        corner: Corner,
+        block_id: F,
+    ) where
+        F: Fn(usize) -> BlockId,
+    {
+        let list = inner(area);
+        let height = list.height as usize;
+        let offset = match selected {
+            Some(selected) if selected >= height => selected - height + 1,
+            _ => 0,
+        };
+
+        for row in 0..height.min(len.saturating_sub(offset)) {
+            let y = match corner {
+                Corner::BottomLeft => list.bottom() - 1 - row as u16,
+                _ => list.top() + row as u16,
+            };
+            let area = Rect::new(list.x, y, list.width, 1);
+            self.add(area, Hit::Block(block_id(offset + row)));
+        }
+    }
+
+    /// The topmost area at `x`, `y` whose hit matches `predicate`.
+    fn find<P>(&self, x: u16, y: u16, predicate: P) -> Option<Hit>
+    where
+        P: Fn(&Hit) -> bool,
+    {
+        self.areas
+            .borrow()
+            .iter()
+            .rev()
+            .find(|(area, hit)| contains(*area, x, y) && predicate(hit))
+            .map(|(_, hit)| *hit)
+    }
+}
+
+/// Areas of the titles of the tabs, drawn with borders in `area`.
+pub fn add_tabs(hits: &HitMap, area: Rect, titles: &[&str]) {
+    let tabs = inner(area);
+    let mut x = tabs.left();
+
+    for (i, title) in titles.iter().enumerate() {
+        let width = title.chars().count() as u16 + 2;
+        if x >= tabs.right() {
+            break;
+        }
+        let width = width.min(tabs.right() - x);
+        hits.add(Rect::new(x, tabs.top(), width, 1), Hit::Tab(i));
+        x += width + 1;
+    }
+}
+
+fn focus(app: &mut App, block_id: BlockId) {
+    app.navigation = Navigation::default();
+
+    match block_id {
+        BlockId::SubscriptionsListItem(_) => {
+            app.navigation.modify_top(BlockId::SubscriptionsList);
+            app.navigation.push(block_id);
+        }
+        block_id => app.navigation.modify_top(block_id),
+    }
+}
+
+fn click(app: &mut App, hit: Hit) {
+    match hit {
+        Hit::Tab(index) => {
+            app.tabs.index = index;
+            app.navigation = Navigation::default();
+        }
+        Hit::Block(block_id) => focus(app, block_id),
+        Hit::Pane(index) => {
+            app.navigation = Navigation::default();
+            app.focus_pane(index);
+        }
+        Hit::Scroll(_) => {}
+    }
+}
+
+/// Double-clicking a message opens it, as `enter` does.
+fn double_click(app: &mut App, hit: Hit) {
+    use BlockId::*;
+
+    if let Hit::Block(StreamItem(_)) | Hit::Block(RetainItem(_)) | Hit::Block(TopicTreeItem(_)) =
+        hit
+    {
+        handle_action(Action::Select, app);
+    }
+}
+
+/// The item `WHEEL_LINES` away from `current`, towards the end of a list
+/// with `len` items when `forward` is set.
+fn scroll_index(current: Option<usize>, len: usize, forward: bool) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+
+    let index = match (current, forward) {
+        (Some(i), true) => i + WHEEL_LINES,
+        (Some(i), false) => i.saturating_sub(WHEEL_LINES),
+        (None, true) => WHEEL_LINES,
+        (None, false) => 0,
+    };
+
+    Some(index.min(len - 1))
+}
+
+/// Select the item `WHEEL_LINES` away in a list, keeping the detail pane
+/// open when the list already has a selection.
+fn scroll_list<F, G>(app: &mut App, len: usize, forward: bool, index_of: F, block_id: G)
+where
+    F: Fn(BlockId) -> Option<usize>,
+    G: Fn(usize) -> BlockId,
+{
+    let current = app.navigation.iter().find_map(&index_of);
+
+    if let Some(index) = scroll_index(current, len, forward) {
+        match current {
+            Some(_) => app.navigation.update(|id| match index_of(id) {
+                Some(_) => block_id(index),
+                None => id,
+            }),
+            None => focus(app, block_id(index)),
+        }
+    }
+}
+
+fn scroll(app: &mut App, scrollable: Scrollable, up: bool) {
+    use BlockId::*;
+
+    match scrollable {
+        // The newest message of the stream is at the bottom, with index 0.
+        Scrollable::Stream => {
+            let len = app.stream_notifications().count();
+            let index_of = |id| match id {
+                StreamItem(i) => Some(i),
+                _ => None,
+            };
+            scroll_list(app, len, up, index_of, StreamItem);
+        }
+        Scrollable::Retain => {
+            let len = app.retained_messages.len();
+            let index_of = |id| match id {
+                RetainItem(i) => Some(i),
+                _ => None,
+            };
+            scroll_list(app, len, !up, index_of, RetainItem);
+        }
+        Scrollable::Stats => {
+            let len = app.stats.len();
+            let index_of = |id| match id {
+                StatsItem(i) => Some(i),
+                _ => None,
+            };
+            scroll_list(app, len, !up, index_of, StatsItem);
+        }
+        Scrollable::Tree => {
+            let len = app.topic_tree.visible(&app.tree_expanded).len();
+            let index_of = |id| match id {
+                TopicTreeItem(i) => Some(i),
+                _ => None,
+            };
+            scroll_list(app, len, !up, index_of, TopicTreeItem);
+        }
+        Scrollable::Detail => {
+            let action = if up { Action::Up } else { Action::Down };
+            for _ in 0..WHEEL_LINES {
+                handle_input_on_message_detail(action, app);
+            }
+        }
+    }
+}
+
+pub fn handle_mouse(event: MouseEvent, app: &mut App) {
+    // Popups only answer to the keys.
+    if let BlockId::Confirm | BlockId::Help = app.navigation.peek() {
+        return;
+    }
+
+    match event {
+        MouseEvent::Press(button, x, y) => {
+            // Terminal coordinates start at 1.
+            let (x, y) = (x.saturating_sub(1), y.saturating_sub(1));
+
+            match button {
+                MouseButton::Left => {
+                    let hit = app.hits.find(x, y, |hit| !matches!(hit, Hit::Scroll(_)));
+
+                    if let Some(hit) = hit {
+                        let now = Instant::now();
+                        let double = app.last_click.is_some_and(|(time, last)| {
+                            last == hit && now.duration_since(time) < DOUBLE_CLICK
+                        });
+
+                        click(app, hit);
+
+                        if double {
+                            app.last_click = None;
+                            double_click(app, hit);
+                        } else {
+                            app.last_click = Some((now, hit));
+                        }
+                    }
+                }
+                MouseButton::WheelUp | MouseButton::WheelDown => {
+                    let hit = app.hits.find(x, y, |hit| matches!(hit, Hit::Scroll(_)));
+
+                    if let Some(Hit::Scroll(scrollable)) = hit {
+                        scroll(app, scrollable, button == MouseButton::WheelUp);
+                    }
+                }
+                _ => {}
+            }
+        }
+        MouseEvent::Release(_, _) | MouseEvent::Hold(_, _) => {}
+    }
+}
@@ -6,17 +6,34 @@ pub enum BlockId {
     SubscribeInput,
     SubscriptionsList,
     SubscriptionsListItem(usize),
+    TopicTreeItem(usize),
+    FilterInput,
+    SearchInput,
+    StreamItem(usize),
+    RetainItem(usize),
+    StatsItem(usize),
+    MessageDetail,
+    PublishTopic,
+    PublishPayload,
+    PublishOptions,
+    PublishHistoryItem(usize),
+    ChartInput,
+    ChartSeriesItem(usize),
+    DashboardItem(usize),
+    Confirm,
+    Help,
+    CommandLine,
 }
 
 pub struct Navigation(Vec<BlockId>);
 
 impl Navigation {
     pub fn default() -> Navigation {
-        let mut nav = Vec::new();
-        nav.push(BlockId::Root);
-        nav.push(BlockId::SubscriptionsWindow);
-        nav.push(BlockId::TabNav);
-        Navigation(nav)
+        Navigation(vec![
+            BlockId::Root,
+            BlockId::SubscriptionsWindow,
+            BlockId::TabNav,
+        ])
     }
 
     pub fn push(&mut self, block_id: BlockId) {
@@ -45,4 +62,19 @@ impl Navigation {
     pub fn modify_top(&mut self, new_value: BlockId) {
         *self.0.last_mut().unwrap() = new_value
     }
+
+    /// Replace every block of the stack with `f(block)`.
+    pub fn update<F: Fn(BlockId) -> BlockId>(&mut self, f: F) {
+        for block_id in self.0.iter_mut() {
+            *block_id = f(*block_id);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BlockId> + '_ {
+        self.0.iter().cloned()
+    }
+
+    pub fn contains(&self, block_id: BlockId) -> bool {
+        self.0.contains(&block_id)
+    }
 }
@@ -1,19 +1,31 @@
 use std::iter::Iterator;
+use std::time::SystemTime;
 
+use regex::Regex;
 use tui::buffer::Buffer;
 use tui::layout::{Corner, Rect};
-use tui::style::Style;
+use tui::style::{Color, Modifier, Style};
 use tui::widgets::{Block, Widget};
+use unicode_width::UnicodeWidthStr;
 
-use crate::format::*;
+use mqtt_analyzer::format::*;
+use mqtt_analyzer::message::{self, Message};
+
+use crate::tui::TopicFormats;
 
 pub struct Notification<'b> {
-    content: &'b rumqtt::client::Notification,
+    content: &'b message::Notification,
+    /// Messages collapsed in the line, `content` being the latest one.
+    repeats: usize,
 }
 
 impl Notification<'_> {
-    pub fn new(content: &rumqtt::client::Notification) -> Notification {
-        Notification { content }
+    pub fn new(content: &message::Notification) -> Notification<'_> {
+        Notification::repeated(content, 1)
+    }
+
+    pub fn repeated(content: &message::Notification, repeats: usize) -> Notification<'_> {
+        Notification { content, repeats }
     }
 }
 
@@ -25,6 +37,12 @@ where
     items: L,
     start_corner: Corner,
     format: PayloadFormat,
+    topic_formats: Option<&'b TopicFormats>,
+    /// Show the age and the size of the messages, at this time.
+    age_at: Option<SystemTime>,
+    selected: Option<usize>,
+    /// Pattern highlighted in the topics and the payloads.
+    search: Option<&'b Regex>,
 }
 
 impl<'b, L> Default for NotificationsList<'b, L>
@@ -37,6 +55,10 @@ where
             items: L::default(),
             start_corner: Corner::TopLeft,
             format: PayloadFormat::Hex,
+            topic_formats: None,
+            age_at: None,
+            selected: None,
+            search: None,
         }
     }
 }
@@ -51,14 +73,42 @@ where
             items,
             start_corner: Corner::TopLeft,
             format: PayloadFormat::Hex,
+            topic_formats: None,
+            age_at: None,
+            selected: None,
+            search: None,
         }
     }
 
+    /// Highlight the item at `index`, scrolling to make it visible.
+    pub fn select(mut self, index: Option<usize>) -> NotificationsList<'b, L> {
+        self.selected = index;
+        self
+    }
+
     pub fn format(mut self, format: PayloadFormat) -> NotificationsList<'b, L> {
         self.format = format;
         self
     }
 
+    /// Formats used for some topics, instead of `format`.
+    pub fn topic_formats(mut self, formats: &'b TopicFormats) -> NotificationsList<'b, L> {
+        self.topic_formats = Some(formats);
+        self
+    }
+
+    /// Show the age of the messages at `now`, and their size.
+    pub fn age_and_size(mut self, now: SystemTime) -> NotificationsList<'b, L> {
+        self.age_at = Some(now);
+        self
+    }
+
+    /// Highlight the matches of `regex` in the topics and the payloads.
+    pub fn search(mut self, regex: Option<&'b Regex>) -> NotificationsList<'b, L> {
+        self.search = regex;
+        self
+    }
+
     pub fn block(mut self, block: Block<'b>) -> NotificationsList<'b, L> {
         self.block = Some(block);
         self
@@ -70,54 +120,145 @@ where
     }
 }
 
-fn draw_generic_notification(
-    notification: &rumqtt::Notification,
-    buf: &mut Buffer,
-    x: u16,
-    y: u16,
-    width: usize,
-) {
-    buf.set_stringn(x, y, format!("{:?}", notification), width, Style::default());
+/// The payload of `msg` as shown in a list with `format`, or with the format
+/// of its topic in `topic_formats`.
+fn shown_payload(
+    msg: &Message,
+    format: PayloadFormat,
+    topic_formats: Option<&TopicFormats>,
+) -> (PayloadFormat, String) {
+    let format = topic_formats
+        .and_then(|formats| formats.get(&msg.topic))
+        .map_or(format, |format| *format);
+
+    format_payload(format, &msg.payload)
 }
 
-fn draw_publish_notification(
-    msg: &mqtt311::Publish,
-    buf: &mut Buffer,
-    x: u16,
-    y: u16,
-    width: usize,
+/// Whether `regex` matches the topic or the payload of `notification`, as
+/// shown in a list with `format` and `topic_formats`: the text searched is
+/// the text highlighted.
+pub fn is_match(
+    regex: &Regex,
+    notification: &message::Notification,
     format: PayloadFormat,
-) {
-    let payload = msg.payload.as_slice();
-    let (format, formatted_payload) = format_payload(format, payload);
-    let format_str = format.to_string();
-    let topic = msg.topic_name.as_str();
-
-    let mut offset: u16 = 0;
-    buf.set_stringn(x + offset, y, &format_str, width, FORMAT_STYLE.into());
-    offset += (format_str.len() + 1) as u16;
-    buf.set_stringn(x + offset, y, topic, width, TOPIC_STYLE.into());
-    offset += (topic.len() + 1) as u16;
-    buf.set_stringn(
-        x + offset,
-        y,
-        formatted_payload,
-        width,
-        PAYLOAD_STYLE.into(),
-    );
+    topic_formats: Option<&TopicFormats>,
+) -> bool {
+    match notification {
+        message::Notification::Publish(msg) => {
+            let (_, payload) = shown_payload(msg, format, topic_formats);
+            regex.is_match(&msg.topic) || regex.is_match(&payload)
+        }
+        _ => false,
+    }
 }
 
-fn draw_notification(
-    notification: &Notification,
+fn draw_generic_notification(
+    notification: &message::Notification,
     buf: &mut Buffer,
     x: u16,
     y: u16,
     width: usize,
-    format: PayloadFormat,
 ) {
-    match notification.content {
-        rumqtt::Notification::Publish(a) => draw_publish_notification(a, buf, x, y, width, format),
-        a => draw_generic_notification(a, buf, x, y, width),
+    let style = match notification {
+        message::Notification::Error(_) => NOTIFICATION_STYLE.into(),
+        _ => Style::default(),
+    };
+
+    buf.set_stringn(x, y, notification.to_string(), width, style);
+}
+
+impl<'b, L> NotificationsList<'b, L>
+where
+    L: Iterator<Item = Notification<'b>>,
+{
+    fn draw_notification(
+        &self,
+        notification: &Notification,
+        buf: &mut Buffer,
+        x: u16,
+        y: u16,
+        width: usize,
+    ) {
+        match notification.content {
+            message::Notification::Publish(a) => {
+                self.draw_publish_notification(a, notification.repeats, buf, x, y, width)
+            }
+            a => draw_generic_notification(a, buf, x, y, width),
+        }
+    }
+
+    fn draw_publish_notification(
+        &self,
+        msg: &Message,
+        repeats: usize,
+        buf: &mut Buffer,
+        x: u16,
+        y: u16,
+        width: usize,
+    ) {
+        let payload = msg.payload.as_slice();
+        let (format, formatted_payload) = shown_payload(msg, self.format, self.topic_formats);
+        let format_str = format.to_string();
+        let topic = msg.topic.as_str();
+        let metadata = self.age_at.map(|now| {
+            format!(
+                "{:>4} {:>6}",
+                format_age(now, msg.timestamp),
+                format_size(payload.len())
+            )
+        });
+        let repeated = match repeats {
+            1 => None,
+            n => Some(format!("×{} {}", n, format_time(msg.timestamp))),
+        };
+
+        // The tokens of the line, and whether the search applies to them.
+        let mut tokens = vec![];
+        if let Some(metadata) = &metadata {
+            tokens.push((metadata.as_str(), NOTIFICATION_STYLE, false));
+        }
+        tokens.push((format_str.as_str(), FORMAT_STYLE, false));
+        if let Some(repeated) = &repeated {
+            tokens.push((repeated.as_str(), NOTIFICATION_STYLE, false));
+        }
+        tokens.push((topic, TOPIC_STYLE, true));
+        tokens.push((formatted_payload.as_str(), PAYLOAD_STYLE, true));
+
+        let mut offset = 0;
+        for (text, style, searched) in tokens.iter() {
+            if offset >= width {
+                break;
+            }
+            buf.set_stringn(
+                x + offset as u16,
+                y,
+                text,
+                width - offset,
+                style.clone().into(),
+            );
+
+            if let (Some(regex), true) = (self.search, searched) {
+                highlight_matches(regex, text, buf, x + offset as u16, y, width - offset);
+            }
+
+            offset += text.width() + 1;
+        }
+    }
+}
+
+/// Highlight the matches of `regex` in `text`, drawn at `x`, `y`.
+fn highlight_matches(regex: &Regex, text: &str, buf: &mut Buffer, x: u16, y: u16, width: usize) {
+    let style = Style::default().fg(Color::Black).bg(Color::Yellow);
+
+    for found in regex
+        .find_iter(text)
+        .filter(|found| !found.as_str().is_empty())
+    {
+        let start = text[..found.start()].width();
+        if start >= width {
+            break;
+        }
+        buf.set_stringn(x + start as u16, y, found.as_str(), width - start, style);
     }
 }
 
@@ -138,18 +279,29 @@ where
             return;
         }
 
-        for (i, item) in self
-            .items
-            .by_ref()
-            .enumerate()
-            .take(list_area.height as usize)
-        {
+        let height = list_area.height as usize;
+        let offset = match self.selected {
+            Some(selected) if selected >= height => selected - height + 1,
+            _ => 0,
+        };
+
+        let items: Vec<_> = self.items.by_ref().skip(offset).take(height).collect();
+
+        for (i, item) in items.iter().enumerate() {
             let (x, y) = match self.start_corner {
                 Corner::TopLeft => (list_area.left(), list_area.top() + i as u16),
                 Corner::BottomLeft => (list_area.left(), list_area.bottom() - (i + 1) as u16),
                 _ => (list_area.left(), list_area.top() + i as u16),
             };
-            draw_notification(&item, buf, x, y, list_area.width as usize, self.format);
+            self.draw_notification(item, buf, x, y, list_area.width as usize);
+
+            if self.selected == Some(offset + i) {
+                for x in list_area.left()..list_area.right() {
+                    let cell = buf.get_mut(x, y);
+                    let modifier = cell.style.modifier | Modifier::REVERSED;
+                    cell.set_modifier(modifier);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,193 @@
+use std::cell::RefCell;
+
+use tui::layout::{Constraint, Direction, Layout, Rect};
+
+use mqtt_analyzer::filter::{Filter, ParseError};
+use mqtt_analyzer::format::PayloadFormat;
+use mqtt_analyzer::history::Collapsed;
+use mqtt_analyzer::message::Notification;
+
+/// Panes the stream area can be split into.
+pub const MAX_PANES: usize = 4;
+
+/// A list of the stream tab, with its own filter, format, pause state and
+/// compact mode.
+#[derive(Clone)]
+pub struct Pane {
+    pub filter_input: String,
+    pub filter_error: Option<ParseError>,
+    /// Only the messages matching this filter are shown in the pane.
+    pub filter: Option<Filter>,
+    pub format: PayloadFormat,
+    /// Notifications pushed to the history when the pane was paused.
+    pub paused_at: Option<u64>,
+    /// Collapse the repeated messages.
+    pub compact: bool,
+    /// Lines of the compact mode, kept up to date as notifications come and
+    /// go. `None` when they must be collapsed again from the history.
+    pub collapsed: RefCell<Option<Collapsed>>,
+}
+
+impl Pane {
+    pub fn new(filter: Option<Filter>, format: PayloadFormat) -> Pane {
+        Pane {
+            filter_input: filter.as_ref().map(Filter::to_string).unwrap_or_default(),
+            filter_error: None,
+            filter,
+            format,
+            paused_at: None,
+            compact: false,
+            collapsed: RefCell::new(None),
+        }
+    }
+
+    pub fn is_shown(&self, notification: &Notification) -> bool {
+        self.filter.as_ref().is_none_or(|f| f.selects(notification))
+    }
+
+    /// Whether the notification with the sequence number `seq` is in the
+    /// lines of the pane, paused or not.
+    pub fn has(&self, seq: u64, notification: &Notification) -> bool {
+        self.paused_at.is_none_or(|paused_at| seq < paused_at) && self.is_shown(notification)
+    }
+
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+        self.collapsed.replace(None);
+    }
+
+    /// Change the filter text, applying it as soon as it is valid.
+    pub fn set_filter_input(&mut self, input: String) {
+        self.filter_input = input;
+        self.collapsed.replace(None);
+
+        if self.filter_input.trim().is_empty() {
+            self.filter = None;
+            self.filter_error = None;
+            return;
+        }
+
+        match self.filter_input.parse::<Filter>() {
+            Ok(filter) => {
+                self.filter = Some(filter);
+                self.filter_error = None;
+            }
+            Err(e) => self.filter_error = Some(e),
+        }
+    }
+}
+
+/// How the panes share the stream area.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Split {
+    /// Side by side.
+    Horizontal,
+    /// One above the other.
+    Vertical,
+}
+
+/// The panes of the stream tab, one of them focused. The keys act on the
+/// focused pane.
+pub struct Panes {
+    panes: Vec<Pane>,
+    focus: usize,
+    /// Set by the last split, for all the panes.
+    pub split: Split,
+}
+
+impl Panes {
+    pub fn new(pane: Pane) -> Panes {
+        Panes {
+            panes: vec![pane],
+            focus: 0,
+            split: Split::Horizontal,
+        }
+    }
+
+    /// Replace the panes by `panes`, unless there is none.
+    pub fn replace(&mut self, mut panes: Vec<Pane>, focus: usize, split: Split) {
+        if panes.is_empty() {
+            return;
+        }
+
+        panes.truncate(MAX_PANES);
+        self.panes = panes;
+        self.split = split;
+        self.set_focus(focus);
+    }
+
+    pub fn len(&self) -> usize {
+        self.panes.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Pane> {
+        self.panes.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Pane> {
+        self.panes.iter_mut()
+    }
+
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    pub fn focused(&self) -> &Pane {
+        &self.panes[self.focus]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Pane {
+        &mut self.panes[self.focus]
+    }
+
+    pub fn set_focus(&mut self, index: usize) {
+        self.focus = index.min(self.panes.len() - 1);
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focus = (self.focus + 1) % self.panes.len();
+    }
+
+    /// Add a copy of the focused pane after it, and focus it.
+    pub fn split(&mut self, split: Split) -> Result<String, String> {
+        if self.panes.len() >= MAX_PANES {
+            return Err(format!("At most {} panes", MAX_PANES));
+        }
+
+        let pane = self.focused().clone();
+        self.panes.insert(self.focus + 1, pane);
+        self.focus += 1;
+        self.split = split;
+
+        Ok(format!("{} panes", self.panes.len()))
+    }
+
+    /// Close the focused pane, unless it is the last one, and focus the
+    /// next one.
+    pub fn close(&mut self) -> Result<String, String> {
+        if self.panes.len() == 1 {
+            return Err("Can't close the last pane".to_string());
+        }
+
+        self.panes.remove(self.focus);
+        self.focus = self.focus.min(self.panes.len() - 1);
+
+        match self.panes.len() {
+            1 => Ok("1 pane".to_string()),
+            n => Ok(format!("{} panes", n)),
+        }
+    }
+
+    /// The areas of the panes in `area`.
+    pub fn areas(&self, area: Rect) -> Vec<Rect> {
+        let n = self.panes.len() as u32;
+
+        Layout::default()
+            .direction(match self.split {
+                Split::Horizontal => Direction::Horizontal,
+                Split::Vertical => Direction::Vertical,
+            })
+            .constraints(vec![Constraint::Ratio(1, n); self.panes.len()])
+            .split(area)
+    }
+}
@@ -0,0 +1,382 @@
+use std::fmt;
+use std::time::SystemTime;
+
+use termion::event::Key;
+use tui::backend::Backend;
+use tui::layout::{Constraint, Corner, Direction, Layout, Rect};
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
+use tui::Frame;
+
+use crate::tui::keymap::Action;
+use crate::tui::line_editor::LineEditor;
+use crate::tui::mouse::Hit;
+use crate::tui::navigation::BlockId;
+use crate::tui::notification_list::{Notification, NotificationsList};
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::engine;
+use mqtt_analyzer::format::NOTIFICATION_STYLE;
+use mqtt_analyzer::message::{self, Message, QoS};
+
+/// Number of sent messages kept in the history.
+const HISTORY_CAPACITY: usize = 100;
+
+/// What happened to the last message sent from the publish tab.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PublishStatus {
+    Sending,
+    Sent,
+    WaitingForAck,
+    Received,
+    Acknowledged,
+    Failed(String),
+}
+
+impl fmt::Display for PublishStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublishStatus::Sending => write!(f, "Sending..."),
+            PublishStatus::Sent => write!(f, "Published"),
+            PublishStatus::WaitingForAck => write!(f, "Waiting for acknowledgment..."),
+            PublishStatus::Received => write!(f, "Received by the broker"),
+            PublishStatus::Acknowledged => write!(f, "Acknowledged by the broker"),
+            PublishStatus::Failed(e) => write!(f, "Failed: {}", e),
+        }
+    }
+}
+
+pub struct PublishForm {
+    topic: LineEditor,
+    payload: LineEditor,
+    /// The payload is written as hex digits, instead of text.
+    hex: bool,
+    qos: QoS,
+    retain: bool,
+    status: Option<PublishStatus>,
+    /// The last message sent from the form, until the engine hands it to the
+    /// client. The status only follows this one, not the other messages
+    /// published, like the ones clearing retained messages.
+    sending: Option<Message>,
+    /// Packet identifier of the last message sent from the form, for QoS 1
+    /// and 2.
+    pkid: Option<u16>,
+    /// Sent messages, the newest first.
+    sent: Vec<message::Notification>,
+}
+
+impl PublishForm {
+    pub fn new() -> PublishForm {
+        PublishForm {
+            topic: LineEditor::default(),
+            payload: LineEditor::multiline(),
+            hex: false,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            status: None,
+            sending: None,
+            pkid: None,
+            sent: Vec::new(),
+        }
+    }
+
+    fn message(&self) -> Result<Message, String> {
+        let payload = if self.hex {
+            let digits: String = self.payload.text().split_whitespace().collect();
+            hex::decode(digits).map_err(|e| format!("Invalid hex payload: {}", e))?
+        } else {
+            self.payload.text().as_bytes().to_vec()
+        };
+
+        let mut msg = Message::new(self.topic.text(), payload);
+        msg.qos = self.qos;
+        msg.retain = self.retain;
+
+        Ok(msg)
+    }
+
+    /// Fill the form with a sent message.
+    fn edit(&mut self, msg: &Message) {
+        self.topic.set(msg.topic.clone());
+        self.hex = std::str::from_utf8(&msg.payload).is_err();
+        self.payload.set(if self.hex {
+            hex::encode(msg.payload.as_slice())
+        } else {
+            String::from_utf8_lossy(&msg.payload).into_owned()
+        });
+        self.qos = msg.qos;
+        self.retain = msg.retain;
+    }
+
+    /// Follow the last message sent, up to its acknowledgment.
+    pub fn handle_notification(&mut self, notification: &message::Notification) {
+        use message::Notification::*;
+        use PublishStatus::*;
+
+        if let Published(msg, _) = notification {
+            self.sent.insert(0, Publish(msg.clone()));
+            self.sent.truncate(HISTORY_CAPACITY);
+        }
+
+        let status = match (notification, &self.status) {
+            (Published(msg, pkid), _) if self.sending.as_ref() == Some(msg) => {
+                self.sending = None;
+                self.pkid = *pkid;
+                match pkid {
+                    None => Sent,
+                    Some(_) => WaitingForAck,
+                }
+            }
+            (PubAck(pkid), Some(WaitingForAck)) if self.pkid == Some(*pkid) => Acknowledged,
+            (PubRec(pkid), Some(WaitingForAck)) if self.pkid == Some(*pkid) => Received,
+            (PubComp(pkid), Some(WaitingForAck)) | (PubComp(pkid), Some(Received))
+                if self.pkid == Some(*pkid) =>
+            {
+                Acknowledged
+            }
+            (Error(engine::Error::Publish(msg, e)), Some(Sending))
+                if self.sending.as_ref() == Some(msg) =>
+            {
+                self.sending = None;
+                Failed(e.to_string())
+            }
+            _ => return,
+        };
+
+        self.status = Some(status);
+    }
+}
+
+pub fn send(app: &mut App, msg: Message) {
+    app.publish.status = Some(PublishStatus::Sending);
+    app.publish.sending = Some(msg.clone());
+    app.publish.pkid = None;
+    if let Err(e) = app.engine.send(engine::Event::Publish(msg)) {
+        app.publish.status = Some(PublishStatus::Failed(e.to_string()));
+        app.publish.sending = None;
+    }
+}
+
+fn publish(app: &mut App) {
+    match app.publish.message() {
+        Ok(msg) => send(app, msg),
+        Err(e) => app.publish.status = Some(PublishStatus::Failed(e)),
+    }
+}
+
+fn draw_input<B>(
+    f: &mut Frame<B>,
+    area: Rect,
+    app: &App,
+    title: &str,
+    editor: &LineEditor,
+    id: BlockId,
+) where
+    B: Backend,
+{
+    let focused = app.navigation.peek() == id;
+    let highlight_state = (focused, focused);
+
+    app.hits.add(area, Hit::Block(id));
+
+    Paragraph::new(editor.spans(focused).iter())
+        .style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_style(get_color(highlight_state))
+                .border_style(get_color(highlight_state)),
+        )
+        .wrap(true)
+        .render(f, area);
+}
+
+fn draw_options<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let form = &app.publish;
+    let highlight_state = (
+        app.navigation.peek() == BlockId::PublishOptions,
+        app.navigation.peek() == BlockId::PublishOptions,
+    );
+
+    app.hits.add(area, Hit::Block(BlockId::PublishOptions));
+
+    let (title, title_style) = match &form.status {
+        Some(PublishStatus::Failed(e)) => (format!("Publish - {}", e), NOTIFICATION_STYLE.into()),
+        Some(status) => (format!("Publish - {}", status), get_color(highlight_state)),
+        None => ("Publish".to_string(), get_color(highlight_state)),
+    };
+
+    let options = format!(
+        "QoS: {}  Retain: {}  Payload: {}",
+        form.qos,
+        if form.retain { "yes" } else { "no" },
+        if form.hex { "hex" } else { "text" },
+    );
+
+    Paragraph::new([Text::raw(options)].iter())
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(title_style)
+                .border_style(get_color(highlight_state)),
+        )
+        .render(f, area);
+}
+
+pub fn draw_publish_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
+        .split(columns[0]);
+
+    let form = &app.publish;
+    let payload_title = if form.hex { "Payload (hex)" } else { "Payload" };
+
+    draw_input(f, rows[0], app, "Topic", &form.topic, BlockId::PublishTopic);
+    draw_input(
+        f,
+        rows[1],
+        app,
+        payload_title,
+        &form.payload,
+        BlockId::PublishPayload,
+    );
+    draw_options(f, rows[2], app);
+
+    let selected = match app.navigation.peek() {
+        BlockId::PublishHistoryItem(i) => Some(i),
+        _ => None,
+    };
+    let highlight_state = (selected.is_some(), selected.is_some());
+    let title = format!("Sent - {}", app.view_format().name());
+
+    app.hits.add_rows(
+        columns[1],
+        form.sent.len(),
+        selected,
+        Corner::TopLeft,
+        BlockId::PublishHistoryItem,
+    );
+
+    NotificationsList::new(form.sent.iter().map(Notification::new))
+        .format(app.view_format())
+        .topic_formats(&app.topic_formats)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(get_color(highlight_state))
+                .border_style(get_color(highlight_state)),
+        )
+        .start_corner(Corner::TopLeft)
+        .select(selected)
+        .render(f, columns[1]);
+}
+
+pub fn handle_input_on_publish_topic(input: Key, action: Option<Action>, app: &mut App) {
+    use Action::*;
+
+    match (input, action) {
+        (_, Some(Up)) => app.navigation.modify_top(BlockId::TabNav),
+        (_, Some(Down)) | (_, Some(Select)) | (_, Some(NextField)) => {
+            app.navigation.modify_top(BlockId::PublishPayload)
+        }
+        (_, Some(Publish)) => publish(app),
+        _ => {
+            app.publish.topic.handle(input, action);
+        }
+    }
+}
+
+pub fn handle_input_on_publish_payload(input: Key, action: Option<Action>, app: &mut App) {
+    use Action::*;
+
+    // Enter is part of the payload, so leave with the arrows or tab.
+    match (input, action) {
+        (Key::Char('\n'), _) => {
+            app.publish.payload.handle(input, action);
+        }
+        (_, Some(Up)) => app.navigation.modify_top(BlockId::PublishTopic),
+        (_, Some(Down)) | (_, Some(NextField)) => {
+            app.navigation.modify_top(BlockId::PublishOptions)
+        }
+        (_, Some(Publish)) => publish(app),
+        _ => {
+            app.publish.payload.handle(input, action);
+        }
+    }
+}
+
+pub fn handle_input_on_publish_options(action: Action, app: &mut App) {
+    use Action::*;
+
+    let form = &mut app.publish;
+
+    match action {
+        Up => app.navigation.modify_top(BlockId::PublishPayload),
+        Down | NextField if !form.sent.is_empty() => {
+            app.navigation.modify_top(BlockId::PublishHistoryItem(0))
+        }
+        CycleQos => form.qos = QoS::from_u8((form.qos.to_u8() + 1) % 3).unwrap(),
+        ToggleRetain => form.retain = !form.retain,
+        ToggleHex => form.hex = !form.hex,
+        Publish => publish(app),
+        _ => {}
+    }
+}
+
+pub fn handle_input_on_publish_history_item(action: Action, app: &mut App, index: usize) {
+    use Action::*;
+
+    let msg = match app.publish.sent.get(index) {
+        Some(message::Notification::Publish(msg)) => msg.clone(),
+        _ => {
+            app.navigation.modify_top(BlockId::PublishOptions);
+            return;
+        }
+    };
+
+    match action {
+        Up if index == 0 => app.navigation.modify_top(BlockId::PublishOptions),
+        Up => app
+            .navigation
+            .modify_top(BlockId::PublishHistoryItem(index - 1)),
+        Down if index + 1 < app.publish.sent.len() => app
+            .navigation
+            .modify_top(BlockId::PublishHistoryItem(index + 1)),
+        Publish => send(
+            app,
+            Message {
+                timestamp: SystemTime::now(),
+                ..msg
+            },
+        ),
+        Edit => {
+            app.publish.edit(&msg);
+            app.navigation.modify_top(BlockId::PublishTopic);
+        }
+        CycleFormat => app.cycle_view_format(),
+        CycleTopicFormat => app.cycle_topic_format(&msg.topic),
+        _ => {}
+    }
+}
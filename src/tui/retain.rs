@@ -1,24 +1,129 @@
 use tui::backend::Backend;
-use tui::Frame;
 use tui::layout::{Corner, Rect};
 use tui::widgets::{Block, Borders, Widget};
+use tui::Frame;
 
-use crate::format::MessageFormat;
-use crate::tui::App;
+use crate::tui::confirm::{self, ask};
+use crate::tui::detail::{draw_message_detail, open_detail, split_for_detail};
+use crate::tui::keymap::{bindings_of, Action};
+use crate::tui::mouse::{Hit, Scrollable};
+use crate::tui::navigation::BlockId;
 use crate::tui::notification_list::{Notification, NotificationsList};
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::message;
 
-pub fn draw_retain_tab<B>(f: &mut Frame<B>, area: Rect, app: &App, format: MessageFormat)
-    where
-        B: Backend,
+pub fn draw_retain_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
 {
-    let retained_messages = app
-        .retained_messages
-        .iter()
-        .map(|(_, notification)| Notification::new(notification));
+    let retained_messages = app.retained_messages.values().map(Notification::new);
+
+    let (list_area, detail_area) = split_for_detail(area, app);
+
+    let selected = app.navigation.iter().find_map(|block_id| match block_id {
+        BlockId::RetainItem(i) => Some(i),
+        _ => None,
+    });
+
+    let highlight_state = (selected.is_some(), selected.is_some());
+
+    let (contexts, _) = bindings_of(BlockId::RetainItem(0), app.tabs.index);
+    let hints = app.keymap.hints(
+        contexts,
+        &[
+            (Action::ClearRetained, "clear"),
+            (Action::ClearRetainedSubtree, "clear subtree"),
+        ],
+    );
+    let title = match hints.as_str() {
+        "" => format!("Retained - {}", app.view_format().name()),
+        hints => format!("Retained - {} - {}", app.view_format().name(), hints),
+    };
+
+    app.hits.add(list_area, Hit::Scroll(Scrollable::Retain));
+    app.hits.add_rows(
+        list_area,
+        app.retained_messages.len(),
+        selected,
+        Corner::TopLeft,
+        BlockId::RetainItem,
+    );
 
     NotificationsList::new(retained_messages)
-        .format(format.payload_format)
-        .block(Block::default().borders(Borders::ALL))
+        .age_and_size(app.stats.now())
+        .format(app.view_format())
+        .topic_formats(&app.topic_formats)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(get_color(highlight_state))
+                .border_style(get_color(highlight_state)),
+        )
         .start_corner(Corner::TopLeft)
-        .render(f, area);
+        .select(selected)
+        .render(f, list_area);
+
+    if let Some(area) = detail_area {
+        draw_message_detail(f, area, app);
+    }
+}
+
+pub fn handle_input_on_retain_item(action: Action, app: &mut App, index: usize) {
+    use Action::*;
+
+    let len = app.retained_messages.len();
+
+    if index >= len {
+        app.navigation.modify_top(if len > 0 {
+            BlockId::RetainItem(len - 1)
+        } else {
+            BlockId::TabNav
+        });
+        return;
+    }
+
+    let topic = app.retained_messages.keys().nth(index).cloned().unwrap();
+
+    match action {
+        Up if index == 0 => app.navigation.modify_top(BlockId::TabNav),
+        Up => app.navigation.modify_top(BlockId::RetainItem(index - 1)),
+        Down if index + 1 < len => app.navigation.modify_top(BlockId::RetainItem(index + 1)),
+        Select => {
+            let msg = match app.retained_messages.values().nth(index) {
+                Some(message::Notification::Publish(msg)) => Some(msg.clone()),
+                _ => None,
+            };
+            if let Some(msg) = msg {
+                open_detail(app, msg);
+            }
+        }
+        CycleFormat => app.cycle_view_format(),
+        CycleTopicFormat => app.cycle_topic_format(&topic),
+        ClearRetained => ask(
+            app,
+            format!("Clear the retained message of {}?", topic),
+            confirm::Action::ClearRetained(vec![topic]),
+        ),
+        ClearRetainedSubtree => clear_subtree(app, &topic),
+        _ => {}
+    }
+}
+
+/// Ask to clear every retained message at `topic` or below it.
+pub fn clear_subtree(app: &mut App, topic: &str) {
+    let topics = app.retained_under(topic);
+
+    if topics.is_empty() {
+        return;
+    }
+
+    let prompt = format!(
+        "Clear {} retained message{} at {} and below it?",
+        topics.len(),
+        if topics.len() == 1 { "" } else { "s" },
+        topic
+    );
+    ask(app, prompt, confirm::Action::ClearRetained(topics));
 }
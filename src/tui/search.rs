@@ -0,0 +1,126 @@
+use regex::{Regex, RegexBuilder};
+use termion::event::Key;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::widgets::{Paragraph, Text, Widget};
+use tui::Frame;
+
+use crate::tui::keymap::Action;
+use crate::tui::line_editor::LineEditor;
+use crate::tui::navigation::BlockId;
+use crate::tui::notification_list::is_match;
+use crate::tui::App;
+use mqtt_analyzer::message::Notification;
+
+/// Pattern searched in the stream, typed after `/`.
+#[derive(Default)]
+pub struct Search {
+    input: LineEditor,
+    /// The pattern of the last search, highlighted in the stream.
+    pub regex: Option<Regex>,
+}
+
+/// Compile `pattern`, ignoring the case unless it has upper case letters.
+fn compile(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!pattern.chars().any(char::is_uppercase))
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+/// Select the next match of the search in the stream, older than the
+/// notification at `from` or newer than it. Without `from`, the newest
+/// notification is the first candidate. Return what happened.
+fn jump(app: &mut App, from: Option<usize>, older: bool) -> Result<String, String> {
+    let regex = match &app.search.regex {
+        Some(regex) => regex.clone(),
+        None => return Err("No search, type / first".to_string()),
+    };
+
+    // Matched in the focused pane, with its format, as it is highlighted.
+    let format = app.panes.focused().format;
+    let matches = |notification: &Notification| {
+        is_match(&regex, notification, format, Some(&app.topic_formats))
+    };
+
+    // The newest notification has index 0.
+    let notifications: Vec<&Notification> = app.stream_notifications().collect();
+    let found = if older {
+        let start = from.map_or(0, |i| i + 1);
+        (start..notifications.len()).find(|i| matches(notifications[*i]))
+    } else {
+        (0..from.unwrap_or(0))
+            .rev()
+            .find(|i| matches(notifications[*i]))
+    };
+
+    match found {
+        Some(i) => {
+            app.navigation.modify_top(BlockId::StreamItem(i));
+            Ok(format!("/{}", regex))
+        }
+        None if older => Err(format!("No older match for /{}", regex)),
+        None => Err(format!("No newer match for /{}", regex)),
+    }
+}
+
+fn selected(app: &App) -> Option<usize> {
+    match app.navigation.peek() {
+        BlockId::StreamItem(i) => Some(i),
+        _ => None,
+    }
+}
+
+/// Handle the search keys of the stream tab.
+pub fn handle_search_action(action: Action, app: &mut App) {
+    let report = match action {
+        Action::Search => {
+            app.search.input.clear();
+            app.navigation.push(BlockId::SearchInput);
+            return;
+        }
+        Action::NextMatch => jump(app, selected(app), true),
+        Action::PreviousMatch => jump(app, selected(app), false),
+        _ => return,
+    };
+
+    app.command_line.set_report(report);
+}
+
+fn submit(app: &mut App) {
+    app.navigation.pop();
+
+    let pattern = app.search.input.text().to_string();
+    if pattern.is_empty() {
+        app.search.regex = None;
+        return;
+    }
+
+    let report = compile(&pattern).and_then(|regex| {
+        app.search.regex = Some(regex);
+        jump(app, None, true)
+    });
+
+    app.command_line.set_report(report);
+}
+
+pub fn handle_input_on_search_input(input: Key, action: Option<Action>, app: &mut App) {
+    match (input, action) {
+        (_, Some(Action::Select)) => submit(app),
+        (Key::Backspace, None) if app.search.input.text().is_empty() => app.navigation.pop(),
+        _ => {
+            app.search.input.handle(input, action);
+        }
+    }
+}
+
+/// The search prompt, in place of the status bar.
+pub fn draw_search_input<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let mut text = vec![Text::raw("/")];
+    text.extend(app.search.input.spans(true));
+
+    Paragraph::new(text.iter()).render(f, area);
+}
@@ -0,0 +1,117 @@
+use mqtt_analyzer::engine;
+use mqtt_analyzer::format::PayloadFormat;
+use mqtt_analyzer::message::QoS;
+use mqtt_analyzer::session::{self, Session};
+use mqtt_analyzer::topic;
+
+use crate::cli::Mode;
+use crate::tui::panes::{Pane, Split};
+use crate::tui::App;
+
+/// The state of `app` to restore in the next session.
+pub fn capture(app: &App) -> Result<Session, engine::Error> {
+    let subscriptions = app
+        .engine
+        .subscriptions
+        .read()
+        .map_err(|_| engine::Error::LockPoisoned)?
+        .iter()
+        .map(|(sub, qos)| (sub.clone(), qos.to_u8()))
+        .collect();
+
+    // The formats of the stream are the ones of its panes.
+    let formats = Mode::ALL
+        .iter()
+        .filter(|mode| **mode != Mode::Stream)
+        .map(|mode| {
+            let format = app.view_formats[*mode as usize];
+            (mode.name().to_string(), format.name().to_string())
+        })
+        .collect();
+
+    let topic_formats = app
+        .topic_formats
+        .iter()
+        .map(|(topic, format)| (topic.clone(), format.name().to_string()))
+        .collect();
+
+    let stream = session::Stream {
+        split: match app.panes.split {
+            Split::Horizontal => "horizontal",
+            Split::Vertical => "vertical",
+        }
+        .to_string(),
+        focus: app.panes.focus(),
+        panes: app
+            .panes
+            .iter()
+            .map(|pane| session::Pane {
+                filter: pane.filter_input.clone(),
+                format: pane.format.name().to_string(),
+                compact: pane.compact,
+            })
+            .collect(),
+    };
+
+    Ok(Session {
+        tab: Mode::ALL
+            .get(app.tabs.index)
+            .map(|mode| mode.name().to_string()),
+        subscriptions,
+        formats,
+        topic_formats,
+        stream,
+    })
+}
+
+/// Restore the state of `session` in `app`. What is no longer valid, like
+/// an unknown format, is left out.
+pub fn restore(app: &mut App, session: &Session) {
+    for (sub, qos) in &session.subscriptions {
+        if let (true, Some(qos)) = (topic::is_valid_filter(sub), QoS::from_u8(*qos)) {
+            if let Err(e) = app.engine.send(engine::Event::Subscribe(sub.clone(), qos)) {
+                app.last_error = Some(e);
+                break;
+            }
+        }
+    }
+
+    if let Some(mode) = session
+        .tab
+        .as_ref()
+        .and_then(|tab| tab.parse::<Mode>().ok())
+    {
+        app.tabs.index = mode as usize;
+    }
+
+    for (mode, format) in &session.formats {
+        if let (Ok(mode), Ok(format)) = (mode.parse::<Mode>(), format.parse::<PayloadFormat>()) {
+            app.view_formats[mode as usize] = format;
+        }
+    }
+
+    for (topic, format) in &session.topic_formats {
+        if let Ok(format) = format.parse::<PayloadFormat>() {
+            app.topic_formats.insert(topic.clone(), format);
+        }
+    }
+
+    let default_format = app.panes.focused().format;
+    let panes = session
+        .stream
+        .panes
+        .iter()
+        .map(|saved| {
+            let format = saved.format.parse().unwrap_or(default_format);
+            let mut pane = Pane::new(None, format);
+            pane.set_filter_input(saved.filter.clone());
+            pane.set_compact(saved.compact);
+            pane
+        })
+        .collect();
+    let split = match session.stream.split.as_str() {
+        "vertical" => Split::Vertical,
+        _ => Split::Horizontal,
+    };
+    app.panes.replace(panes, session.stream.focus, split);
+}
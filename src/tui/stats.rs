@@ -0,0 +1,142 @@
+use tui::backend::Backend;
+use tui::layout::{Corner, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, Row, Table, Widget};
+use tui::Frame;
+
+use crate::tui::keymap::Action;
+use crate::tui::mouse::{Hit, Scrollable};
+use crate::tui::navigation::BlockId;
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::stats;
+
+/// Width of every column but the topic, which takes the remaining space.
+const WIDTHS: &[u16] = &[7, 4, 6, 6, 9, 6, 6, 6, 5, 5, 1];
+
+/// Lines of the table above its rows: the header and a blank line.
+const HEADER_HEIGHT: u16 = 2;
+
+const PAGE: usize = 10;
+
+pub fn draw_stats_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let selected = match app.navigation.peek() {
+        BlockId::StatsItem(i) => Some(i),
+        _ => None,
+    };
+    let highlight_state = (selected.is_some(), selected.is_some());
+
+    // Keep the selected row in view, as the lists do.
+    let height = area.height.saturating_sub(2 + HEADER_HEIGHT) as usize;
+    let offset = match selected {
+        Some(selected) if selected >= height => selected - height + 1,
+        _ => 0,
+    };
+
+    let now = app.stats.now();
+    let rows: Vec<_> = app
+        .stats
+        .sorted(app.stats_sort, app.stats_reverse)
+        .into_iter()
+        .skip(offset)
+        .take(height)
+        .map(|(topic, topic_stats)| stats::row(topic, topic_stats, now))
+        .collect();
+
+    let spacing = 1;
+    let fixed: u16 = WIDTHS.iter().map(|w| w + spacing).sum();
+    let topic_width = area.width.saturating_sub(fixed + 2 + spacing).max(5);
+    let widths: Vec<u16> = std::iter::once(topic_width)
+        .chain(WIDTHS.iter().cloned())
+        .collect();
+
+    let title = format!(
+        "Stats - {} topics, sorted by {}{}",
+        app.stats.len(),
+        app.stats_sort,
+        if app.stats_reverse { " (reversed)" } else { "" }
+    );
+
+    let rows_area = Rect::new(
+        area.x,
+        area.y + HEADER_HEIGHT.min(area.height),
+        area.width,
+        area.height.saturating_sub(HEADER_HEIGHT),
+    );
+    app.hits.add(area, Hit::Scroll(Scrollable::Stats));
+    app.hits.add_rows(
+        rows_area,
+        app.stats.len(),
+        selected,
+        Corner::TopLeft,
+        BlockId::StatsItem,
+    );
+
+    let selected_style = Style::default().modifier(Modifier::REVERSED);
+    Table::new(
+        stats::COLUMNS.iter(),
+        rows.iter().enumerate().map(|(i, row)| {
+            if selected == Some(offset + i) {
+                Row::StyledData(row.iter(), selected_style)
+            } else {
+                Row::Data(row.iter())
+            }
+        }),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(&title)
+            .title_style(get_color(highlight_state))
+            .border_style(get_color(highlight_state)),
+    )
+    .header_style(Style::default().fg(Color::Yellow))
+    .widths(&widths)
+    .column_spacing(spacing)
+    .render(f, area);
+}
+
+pub fn handle_input_on_stats(action: Action, app: &mut App) {
+    match action {
+        Action::Sort => app.stats_sort = app.stats_sort.next(),
+        Action::Reverse => app.stats_reverse = !app.stats_reverse,
+        Action::ClearStats => app.stats.clear(),
+        _ => {}
+    }
+}
+
+pub fn handle_input_on_stats_item(action: Action, app: &mut App, index: usize) {
+    use Action::*;
+
+    let len = app.stats.len();
+
+    if index >= len {
+        app.navigation.modify_top(match len {
+            0 => BlockId::TabNav,
+            _ => BlockId::StatsItem(len - 1),
+        });
+        return;
+    }
+
+    match action {
+        Up if index == 0 => app.navigation.modify_top(BlockId::TabNav),
+        Up => app.navigation.modify_top(BlockId::StatsItem(index - 1)),
+        Down if index + 1 < len => app.navigation.modify_top(BlockId::StatsItem(index + 1)),
+        PageUp => app
+            .navigation
+            .modify_top(BlockId::StatsItem(index.saturating_sub(PAGE))),
+        PageDown => app
+            .navigation
+            .modify_top(BlockId::StatsItem((index + PAGE).min(len - 1))),
+        Home => app.navigation.modify_top(BlockId::StatsItem(0)),
+        End => app.navigation.modify_top(BlockId::StatsItem(len - 1)),
+        ClearStats => {
+            app.stats.clear();
+            app.navigation.modify_top(BlockId::TabNav);
+        }
+        action => handle_input_on_stats(action, app),
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Paragraph, Text, Widget};
+use tui::Frame;
+
+use crate::tui::App;
+use mqtt_analyzer::format::{format_age, format_size};
+use mqtt_analyzer::message::Notification;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ConnectionState {
+    Connected(SystemTime),
+    Disconnected(SystemTime),
+    Offline,
+    EndOfInput,
+}
+
+/// Connection and throughput shown in the status bar.
+pub struct Status {
+    /// Where the notifications come from, like the broker address.
+    source: String,
+    client_id: Option<String>,
+    state: ConnectionState,
+    messages: u64,
+    /// Messages and bytes received during the current second, which started
+    /// at `second`, and during the previous one.
+    current: (u64, u64),
+    previous: (u64, u64),
+    second: Instant,
+}
+
+impl Status {
+    /// Without a client id, the notifications are read from an offline
+    /// input.
+    pub fn new(source: String, client_id: Option<String>) -> Status {
+        let state = match client_id {
+            Some(_) => ConnectionState::Connected(SystemTime::now()),
+            None => ConnectionState::Offline,
+        };
+
+        Status {
+            source,
+            client_id,
+            state,
+            messages: 0,
+            current: (0, 0),
+            previous: (0, 0),
+            second: Instant::now(),
+        }
+    }
+
+    fn roll(&mut self) {
+        let elapsed = self.second.elapsed();
+
+        if elapsed >= Duration::from_secs(1) {
+            self.previous = if elapsed < Duration::from_secs(2) {
+                self.current
+            } else {
+                (0, 0)
+            };
+            self.current = (0, 0);
+            self.second += Duration::from_secs(elapsed.as_secs());
+        }
+    }
+
+    pub fn handle_notification(&mut self, notification: &Notification) {
+        self.roll();
+
+        match notification {
+            Notification::Publish(msg) => {
+                self.messages += 1;
+                self.current.0 += 1;
+                self.current.1 += msg.payload.len() as u64;
+            }
+            Notification::Reconnection => {
+                self.state = ConnectionState::Connected(SystemTime::now())
+            }
+            Notification::Disconnection => {
+                self.state = ConnectionState::Disconnected(SystemTime::now())
+            }
+            Notification::EndOfInput => self.state = ConnectionState::EndOfInput,
+            _ => {}
+        }
+    }
+
+    /// Messages and bytes received during the last complete second.
+    fn rates(&self) -> (u64, u64) {
+        match self.second.elapsed().as_secs() {
+            0 => self.previous,
+            1 => self.current,
+            _ => (0, 0),
+        }
+    }
+}
+
+pub fn draw_status_bar<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let status = &app.status;
+    let now = SystemTime::now();
+    let bar = Style::default().fg(Color::Black).bg(Color::Gray);
+
+    let (state, state_style) = match status.state {
+        ConnectionState::Connected(since) => (
+            format!("connected {}", format_age(now, since)),
+            bar.fg(Color::Green),
+        ),
+        ConnectionState::Disconnected(since) => (
+            format!("disconnected {}", format_age(now, since)),
+            bar.fg(Color::Red).modifier(Modifier::BOLD),
+        ),
+        ConnectionState::Offline => ("reading".to_string(), bar),
+        ConnectionState::EndOfInput => ("end of input".to_string(), bar),
+    };
+
+    let history = &app.notifications;
+    let capacity = history.capacity();
+    let mut buffer = match capacity.count {
+        Some(count) => format!("buffer {}/{}", history.len(), count),
+        None => format!("buffer {}", history.len()),
+    };
+    if let Some(bytes) = capacity.bytes {
+        buffer += &format!(" {}/{}", format_size(history.bytes()), format_size(bytes));
+    }
+
+    let (messages_per_second, bytes_per_second) = status.rates();
+
+    let mut text = vec![Text::styled(format!(" {} ", status.source), bar)];
+    if let Some(client_id) = &status.client_id {
+        text.push(Text::styled(format!("| id {} ", client_id), bar));
+    }
+    text.push(Text::styled("| ", bar));
+    text.push(Text::styled(state, state_style));
+    text.push(Text::styled(
+        format!(
+            " | {} msgs {}/s {}/s | {} | dropped {} ",
+            status.messages,
+            messages_per_second,
+            format_size(bytes_per_second as usize),
+            buffer,
+            app.engine.dropped()
+        ),
+        bar,
+    ));
+    if let Some(path) = app.engine.recording() {
+        text.push(Text::styled(
+            format!("| rec {} ", path.display()),
+            bar.fg(Color::Red),
+        ));
+    }
+
+    Paragraph::new(text.iter()).style(bar).render(f, area);
+}
@@ -1,24 +1,289 @@
+use termion::event::Key;
 use tui::backend::Backend;
+use tui::layout::{Constraint, Corner, Direction, Layout, Rect};
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
 use tui::Frame;
-use tui::layout::{Corner, Rect};
-use tui::widgets::{Block, Borders, Widget};
 
-use crate::format::MessageFormat;
-use crate::tui::App;
+use crate::tui::detail::{draw_message_detail, open_detail, split_for_detail};
+use crate::tui::keymap::Action;
+use crate::tui::mouse::{Hit, Scrollable};
+use crate::tui::navigation::BlockId;
 use crate::tui::notification_list::{Notification, NotificationsList};
+use crate::tui::panes::{Pane, Split};
+use crate::tui::search::handle_search_action;
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::format::NOTIFICATION_STYLE;
+use mqtt_analyzer::message;
+
+const PAGE: usize = 10;
 
-pub fn draw_stream_tab<B>(f: &mut Frame<B>, area: Rect, app: &App, format: MessageFormat)
-    where
-        B: Backend,
+fn draw_filter_input<B>(f: &mut Frame<B>, area: Rect, app: &App, pane: &Pane, focused: bool)
+where
+    B: Backend,
 {
-    let notifications = app
-        .notifications
-        .iter()
-        .map(|notification| Notification::new(notification));
+    let highlight_state = (
+        focused && app.navigation.peek() == BlockId::FilterInput,
+        focused && app.panes.len() > 1,
+    );
+
+    let (title, title_style) = match &pane.filter_error {
+        Some(e) => (format!("Filter - {}", e), NOTIFICATION_STYLE.into()),
+        None => ("Filter".to_string(), get_color(highlight_state)),
+    };
+
+    if focused {
+        app.hits.add(area, Hit::Block(BlockId::FilterInput));
+    }
 
-    NotificationsList::new(notifications)
-        .format(format.payload_format)
-        .block(Block::default().borders(Borders::ALL))
-        .start_corner(Corner::BottomLeft)
+    Paragraph::new([Text::raw(&pane.filter_input)].iter())
+        .style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(title_style)
+                .border_style(get_color(highlight_state)),
+        )
         .render(f, area);
 }
+
+/// Draw the pane at `index` of the stream. Only the focused pane has a
+/// selection; clicking another one focuses it.
+fn draw_pane<B>(f: &mut Frame<B>, area: Rect, app: &App, index: usize)
+where
+    B: Backend,
+{
+    let pane = app.panes.iter().nth(index).unwrap();
+    let focused = index == app.panes.focus();
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    draw_filter_input(f, chunks[0], app, pane, focused);
+
+    let list_area = chunks[1];
+
+    let selected = app
+        .navigation
+        .iter()
+        .find_map(|block_id| match block_id {
+            BlockId::StreamItem(i) => Some(i),
+            _ => None,
+        })
+        .filter(|_| focused);
+
+    let highlight_state = (selected.is_some(), focused && app.panes.len() > 1);
+
+    let name = match app.panes.len() {
+        1 => "Stream".to_string(),
+        _ => format!("Stream {}", index + 1),
+    };
+    let format = match &app.search.regex {
+        Some(regex) => format!("{} - /{}", pane.format.name(), regex),
+        None => pane.format.name().to_string(),
+    };
+    let format = match pane.compact {
+        true => format!("{} - Compact", format),
+        false => format,
+    };
+    let (title, title_style) = match app.new_pane_notifications(pane) {
+        _ if pane.paused_at.is_none() => {
+            (format!("{} - {}", name, format), get_color(highlight_state))
+        }
+        0 => (
+            format!("{} - {} - Paused", name, format),
+            NOTIFICATION_STYLE.into(),
+        ),
+        1 => (
+            format!("{} - {} - Paused - 1 new message", name, format),
+            NOTIFICATION_STYLE.into(),
+        ),
+        n => (
+            format!("{} - {} - Paused - {} new messages", name, format, n),
+            NOTIFICATION_STYLE.into(),
+        ),
+    };
+
+    let lines = app.pane_lines(pane);
+
+    if focused {
+        app.hits.add(list_area, Hit::Scroll(Scrollable::Stream));
+        app.hits.add_rows(
+            list_area,
+            lines.len(),
+            selected,
+            Corner::BottomLeft,
+            BlockId::StreamItem,
+        );
+    } else {
+        app.hits.add(area, Hit::Pane(index));
+    }
+
+    NotificationsList::new(
+        lines
+            .into_iter()
+            .map(|(n, repeats)| Notification::repeated(n, repeats)),
+    )
+    .format(pane.format)
+    .topic_formats(&app.topic_formats)
+    .search(app.search.regex.as_ref())
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(&title)
+            .title_style(title_style)
+            .border_style(get_color(highlight_state)),
+    )
+    .start_corner(Corner::BottomLeft)
+    .select(selected)
+    .render(f, list_area);
+}
+
+pub fn draw_stream_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let (panes_area, detail_area) = split_for_detail(area, app);
+
+    for (index, area) in app.panes.areas(panes_area).into_iter().enumerate() {
+        draw_pane(f, area, app, index);
+    }
+
+    if let Some(area) = detail_area {
+        draw_message_detail(f, area, app);
+    }
+}
+
+/// Collapse the repeated messages of the focused pane, or expand them. The
+/// selection goes back to the newest line, as the lines change.
+fn toggle_compact(app: &mut App) {
+    let pane = app.panes.focused_mut();
+    pane.set_compact(!pane.compact);
+
+    app.navigation.update(|block_id| match block_id {
+        BlockId::StreamItem(_) => BlockId::StreamItem(0),
+        block_id => block_id,
+    });
+}
+
+/// Keys acting on the panes of the stream, wherever the focus is in it.
+fn handle_pane_action(action: Action, app: &mut App) {
+    let report = match action {
+        Action::SplitHorizontal => app.panes.split(Split::Horizontal),
+        Action::SplitVertical => app.panes.split(Split::Vertical),
+        Action::ClosePane => app.panes.close(),
+        Action::NextPane if app.panes.len() == 1 => Err("No other pane".to_string()),
+        Action::NextPane => {
+            app.panes.focus_next();
+            Ok(format!("Pane {}", app.panes.focus() + 1))
+        }
+        _ => return,
+    };
+
+    if report.is_ok() {
+        app.focus_pane(app.panes.focus());
+    }
+
+    app.command_line.set_report(report);
+}
+
+pub fn handle_input_on_stream_item(action: Action, app: &mut App, index: usize) {
+    use Action::*;
+
+    let len = app.stream_notifications().count();
+
+    if index >= len {
+        app.navigation
+            .modify_top(BlockId::StreamItem(len.saturating_sub(1)));
+        if len == 0 {
+            app.navigation.modify_top(BlockId::FilterInput);
+        }
+        return;
+    }
+
+    // The newest notification is at the bottom, with index 0.
+    match action {
+        Up if index + 1 == len => app.navigation.modify_top(BlockId::FilterInput),
+        Up => app.navigation.modify_top(BlockId::StreamItem(index + 1)),
+        Down if index > 0 => app.navigation.modify_top(BlockId::StreamItem(index - 1)),
+        PageUp => app
+            .navigation
+            .modify_top(BlockId::StreamItem((index + PAGE).min(len - 1))),
+        PageDown => app
+            .navigation
+            .modify_top(BlockId::StreamItem(index.saturating_sub(PAGE))),
+        Home => app.navigation.modify_top(BlockId::StreamItem(len - 1)),
+        End => app.navigation.modify_top(BlockId::StreamItem(0)),
+        Pause => app.toggle_pause(),
+        Compact => toggle_compact(app),
+        Search | NextMatch | PreviousMatch => handle_search_action(action, app),
+        SplitHorizontal | SplitVertical | ClosePane | NextPane => handle_pane_action(action, app),
+        CycleFormat => app.cycle_view_format(),
+        CycleTopicFormat => {
+            let topic = match app.stream_notifications().nth(index) {
+                Some(message::Notification::Publish(msg)) => Some(msg.topic.clone()),
+                _ => None,
+            };
+            if let Some(topic) = topic {
+                app.cycle_topic_format(&topic);
+            }
+        }
+        Select => {
+            let msg = match app.stream_notifications().nth(index) {
+                Some(message::Notification::Publish(msg)) => Some(msg.clone()),
+                _ => None,
+            };
+            if let Some(msg) = msg {
+                open_detail(app, msg);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Keys of the stream tab, when no notification is selected.
+pub fn handle_input_on_stream(action: Action, app: &mut App) {
+    use Action::*;
+
+    let len = app.stream_notifications().count();
+
+    match action {
+        Pause => app.toggle_pause(),
+        Compact => toggle_compact(app),
+        Search | NextMatch | PreviousMatch => handle_search_action(action, app),
+        SplitHorizontal | SplitVertical | ClosePane | NextPane => handle_pane_action(action, app),
+        PageUp if len > 0 => app
+            .navigation
+            .modify_top(BlockId::StreamItem(PAGE.min(len - 1))),
+        Home if len > 0 => app.navigation.modify_top(BlockId::StreamItem(len - 1)),
+        End if len > 0 => app.navigation.modify_top(BlockId::StreamItem(0)),
+        _ => {}
+    }
+}
+
+pub fn handle_input_on_filter_input(input: Key, action: Option<Action>, app: &mut App) {
+    match (input, action) {
+        (_, Some(Action::Up)) | (_, Some(Action::Select)) => {
+            app.navigation.modify_top(BlockId::TabNav)
+        }
+        (_, Some(Action::Down)) if app.stream_notifications().next().is_some() => {
+            app.navigation.modify_top(BlockId::StreamItem(0))
+        }
+        (_, Some(Action::NextPane)) => handle_pane_action(Action::NextPane, app),
+        (Key::Backspace, None) => {
+            let mut input = app.panes.focused().filter_input.clone();
+            input.pop();
+            app.set_filter_input(input);
+        }
+        (Key::Char(c), None) => {
+            let mut input = app.panes.focused().filter_input.clone();
+            input.push(c);
+            app.set_filter_input(input);
+        }
+        _ => {}
+    }
+}
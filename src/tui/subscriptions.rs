@@ -1,14 +1,126 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
 use termion::event::Key;
 use tui::backend::Backend;
-use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::layout::{Constraint, Corner, Direction, Layout, Rect};
 use tui::style::{Color, Style};
-use tui::widgets::{Block, Borders, Paragraph, SelectableList, Text, Widget};
+use tui::widgets::{Block, Borders, Paragraph, SelectableList, Widget};
 use tui::Frame;
 
-use crate::engine;
+use crate::tui::keymap::Action;
+use crate::tui::line_editor::LineEditor;
+use crate::tui::mouse::Hit;
 use crate::tui::navigation::BlockId;
 use crate::tui::style::get_color;
 use crate::tui::App;
+use mqtt_analyzer::config;
+use mqtt_analyzer::engine;
+use mqtt_analyzer::format::NOTIFICATION_STYLE;
+use mqtt_analyzer::message::QoS;
+use mqtt_analyzer::topic;
+
+const HISTORY_CAPACITY: usize = 100;
+
+/// The filter being typed, with the filters subscribed to before.
+#[derive(Default)]
+pub struct SubscribeInput {
+    editor: LineEditor,
+    /// Oldest first, kept across sessions.
+    history: Vec<String>,
+    /// Entry of the history shown in the input, from the newest.
+    browsing: Option<usize>,
+    /// Completions of the level being typed, when there are more than one.
+    candidates: Vec<String>,
+}
+
+impl SubscribeInput {
+    fn history_path() -> Option<PathBuf> {
+        config::state_dir().map(|dir| dir.join("filters"))
+    }
+
+    /// An empty input, with the history of the previous sessions.
+    pub fn load() -> SubscribeInput {
+        let history = SubscribeInput::history_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        SubscribeInput {
+            history,
+            ..SubscribeInput::default()
+        }
+    }
+
+    fn save_history(&self) -> io::Result<()> {
+        let path = match SubscribeInput::history_path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut text = self.history.join("\n");
+        text.push('\n');
+        fs::write(path, text)
+    }
+
+    fn clear(&mut self) {
+        self.editor.clear();
+        self.browsing = None;
+        self.candidates.clear();
+    }
+
+    /// Show an older or a newer filter of the history. Return false when
+    /// there is none.
+    fn browse(&mut self, older: bool) -> bool {
+        let len = self.history.len();
+        let browsing = match (self.browsing, older) {
+            (None, true) if len > 0 => Some(0),
+            (Some(i), true) if i + 1 < len => Some(i + 1),
+            (Some(0), false) => None,
+            (Some(i), false) => Some(i - 1),
+            _ => return false,
+        };
+
+        self.browsing = browsing;
+        self.candidates.clear();
+        match browsing {
+            Some(i) => self.editor.set(self.history[len - 1 - i].clone()),
+            None => self.editor.clear(),
+        }
+
+        true
+    }
+
+    fn remember(&mut self, filter: String) {
+        self.history.retain(|entry| *entry != filter);
+        self.history.push(filter);
+        if self.history.len() > HISTORY_CAPACITY {
+            self.history.remove(0);
+        }
+
+        // The history is only a convenience, it isn't worth an error.
+        let _ = self.save_history();
+    }
+
+    fn complete(&mut self, candidates: Vec<String>) {
+        self.editor.complete(&candidates);
+        self.candidates = if candidates.len() > 1 {
+            candidates
+        } else {
+            vec![]
+        };
+    }
+}
 
 fn draw_subscribe_input<B>(f: &mut Frame<B>, area: Rect, app: &App)
 where
@@ -19,13 +131,25 @@ where
         app.navigation.peek() == BlockId::SubscribeInput,
     );
 
-    Paragraph::new([Text::raw(&app.subscribe_input)].iter())
+    let input = &app.subscribe_input;
+    let (title, title_style) = match &app.last_error {
+        Some(e) => (format!("Subscribe - {}", e), NOTIFICATION_STYLE.into()),
+        None if !input.candidates.is_empty() => (
+            format!("Subscribe - {}", input.candidates.join(" ")),
+            get_color(highlight_state),
+        ),
+        None => ("Subscribe".to_string(), get_color(highlight_state)),
+    };
+
+    app.hits.add(area, Hit::Block(BlockId::SubscribeInput));
+
+    Paragraph::new(input.editor.spans(highlight_state.0).iter())
         .style(Style::default().fg(Color::Yellow))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Subscribe")
-                .title_style(get_color(highlight_state))
+                .title(&title)
+                .title_style(title_style)
                 .border_style(get_color(highlight_state)),
         )
         .render(f, area);
@@ -39,7 +163,12 @@ where
         .engine
         .subscriptions
         .read()
-        .map(|subscriptions| subscriptions.clone().into_iter().collect())
+        .map(|subscriptions| {
+            subscriptions
+                .iter()
+                .map(|(sub, qos)| format!("{} (QoS {})", sub, qos))
+                .collect()
+        })
         .unwrap();
 
     let highlight_state = (
@@ -54,6 +183,14 @@ where
             0
         };
 
+    app.hits.add_rows(
+        area,
+        subscriptions.len(),
+        Some(selected_subscription_index),
+        Corner::TopLeft,
+        BlockId::SubscriptionsListItem,
+    );
+
     SelectableList::default()
         .block(
             Block::default()
@@ -81,25 +218,19 @@ where
     draw_subscriptions_list(f, chunks[1], app);
 }
 
-pub fn handle_input_on_subscriptions_list(c: Key, app: &mut App) {
-    use Key::*;
-
-    match c {
-        Up => {
+pub fn handle_input_on_subscriptions_list(action: Action, app: &mut App) {
+    match action {
+        Action::Up => {
             app.navigation.modify_top(BlockId::SubscribeInput);
         }
-        Char('\n') => {
-            if app.engine.subscriptions.read().unwrap().len() > 0 {
-                app.navigation.push(BlockId::SubscriptionsListItem(0));
-            }
+        Action::Select if !app.engine.subscriptions.read().unwrap().is_empty() => {
+            app.navigation.push(BlockId::SubscriptionsListItem(0));
         }
         _ => {}
     }
 }
 
-pub fn handle_input_on_subscriptions_list_item(c: Key, app: &mut App, index: usize) {
-    use Key::*;
-
+pub fn handle_input_on_subscriptions_list_item(action: Action, app: &mut App, index: usize) {
     let subscriptions_len = app.engine.subscriptions.read().unwrap().len();
 
     if subscriptions_len == 0 {
@@ -111,58 +242,72 @@ pub fn handle_input_on_subscriptions_list_item(c: Key, app: &mut App, index: usi
     let prev_index = index - (if index > 0 { 1 } else { 0 });
     let next_index = index + (if index < max { 1 } else { 0 });
 
-    match c {
-        Up => app
+    match action {
+        Action::Up => app
             .navigation
             .modify_top(BlockId::SubscriptionsListItem(prev_index)),
-        Down => app
+        Action::Down => app
             .navigation
             .modify_top(BlockId::SubscriptionsListItem(next_index)),
-        Char('d') | Char('x') | Backspace | Delete => {
+        Action::Delete => {
             let sub = app
                 .engine
                 .subscriptions
                 .read()
-                .map(|x| x.iter().nth(index).cloned());
+                .map(|x| x.keys().nth(index).cloned());
 
             if let Ok(Some(sub)) = sub {
-                app.engine
-                    .tx()
-                    .send(engine::Event::Unsubscribe(sub))
-                    .unwrap();
+                if let Err(e) = app.engine.send(engine::Event::Unsubscribe(sub)) {
+                    app.last_error = Some(e);
+                }
             }
         }
         _ => (),
     }
 }
 
-pub fn handle_input_on_subscribe_input(c: Key, app: &mut App) {
-    use Key::*;
+pub fn handle_input_on_subscribe_input(input: Key, action: Option<Action>, app: &mut App) {
+    let subscribe_input = &mut app.subscribe_input;
 
-    match c {
-        Up => {
-            app.subscribe_input.clear();
-            app.navigation.modify_top(BlockId::TabNav);
-        }
-        Down => {
-            app.subscribe_input.clear();
-            app.navigation.modify_top(BlockId::SubscriptionsList);
+    match (input, action) {
+        (_, Some(Action::Up)) => {
+            if !subscribe_input.browse(true) {
+                subscribe_input.clear();
+                app.navigation.modify_top(BlockId::TabNav);
+            }
         }
-        Char('\n') => {
-            let sub: String = app.subscribe_input.drain(..).collect();
-            if !sub.is_empty() {
-                app.engine
-                    .tx()
-                    .send(crate::engine::Event::Subscribe(sub.clone()))
-                    .unwrap();
+        (_, Some(Action::Down)) => {
+            if !subscribe_input.browse(false) {
+                subscribe_input.clear();
+                app.navigation.modify_top(BlockId::SubscriptionsList);
             }
         }
-        Backspace => {
-            app.subscribe_input.pop();
+        (_, Some(Action::Complete)) => {
+            let candidates = app.topic_tree.complete(app.subscribe_input.editor.head());
+            app.subscribe_input.complete(candidates);
+        }
+        (_, Some(Action::Select)) => {
+            let sub = subscribe_input.editor.text().trim().to_string();
+            if sub.is_empty() {
+                return;
+            }
+            if !topic::is_valid_filter(&sub) {
+                app.last_error = Some(engine::Error::InvalidFilter(sub));
+                return;
+            }
+
+            subscribe_input.clear();
+            subscribe_input.remember(sub.clone());
+            app.last_error = app
+                .engine
+                .send(engine::Event::Subscribe(sub, QoS::AtLeastOnce))
+                .err();
         }
-        Char(c) => {
-            app.subscribe_input.push(c);
+        _ => {
+            if subscribe_input.editor.handle(input, action) {
+                subscribe_input.candidates.clear();
+                app.last_error = None;
+            }
         }
-        _ => {}
     }
 }
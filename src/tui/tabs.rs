@@ -3,11 +3,17 @@ pub struct TabsState {
 }
 
 impl TabsState {
-    pub const TITLES: &'static [&'static str] = &["Subscriptions", "Stream", "Retain"];
+    pub const TITLES: &'static [&'static str] = &[
+        "Subscriptions",
+        "Stream",
+        "Retain",
+        "Stats",
+        "Topics",
+        "Publish",
+        "Charts",
+        "Dashboard",
+    ];
 
-    pub fn default() -> TabsState {
-        TabsState { index: 0 }
-    }
     pub fn next(&mut self) {
         self.index = (self.index + 1) % Self::TITLES.len();
     }
@@ -0,0 +1,165 @@
+use tui::backend::Backend;
+use tui::layout::{Corner, Rect};
+use tui::style::{Color, Style};
+use tui::widgets::{Block, Borders, SelectableList, Widget};
+use tui::Frame;
+
+use crate::tui::dashboard::toggle_pin;
+use crate::tui::keymap::{bindings_of, Action};
+use crate::tui::mouse::{Hit, Scrollable};
+use crate::tui::navigation::BlockId;
+use crate::tui::retain::clear_subtree;
+use crate::tui::style::get_color;
+use crate::tui::App;
+use mqtt_analyzer::engine;
+use mqtt_analyzer::filter;
+use mqtt_analyzer::format::format_payload;
+use mqtt_analyzer::message::QoS;
+use mqtt_analyzer::tree::Entry;
+
+use crate::cli::Mode;
+
+fn format_entry(entry: &Entry, app: &App) -> String {
+    let marker = match (entry.has_children, entry.expanded) {
+        (false, _) => " ",
+        (true, false) => "+",
+        (true, true) => "-",
+    };
+
+    let mut line = format!(
+        "{}{} {} ({})",
+        "  ".repeat(entry.depth),
+        marker,
+        entry.name,
+        entry.count
+    );
+
+    if let Some(msg) = entry.last {
+        let (_, payload) = format_payload(app.payload_format(&msg.topic), &msg.payload);
+        line += " = ";
+        line += &payload;
+    }
+
+    line
+}
+
+pub fn draw_tree_tab<B>(f: &mut Frame<B>, area: Rect, app: &App)
+where
+    B: Backend,
+{
+    let items: Vec<_> = app
+        .topic_tree
+        .visible(&app.tree_expanded)
+        .iter()
+        .map(|entry| format_entry(entry, app))
+        .collect();
+
+    let selected = match app.navigation.peek() {
+        BlockId::TopicTreeItem(i) => Some(i),
+        _ => None,
+    };
+
+    let highlight_state = (selected.is_some(), selected.is_some());
+
+    let (contexts, _) = bindings_of(BlockId::TopicTreeItem(0), app.tabs.index);
+    let hints = app.keymap.hints(
+        contexts,
+        &[
+            (Action::Select, "expand"),
+            (Action::Subscribe, "subscribe"),
+            (Action::Unsubscribe, "unsubscribe"),
+            (Action::FilterStream, "filter stream"),
+            (Action::ClearRetainedSubtree, "clear retained"),
+        ],
+    );
+    let title = match hints.as_str() {
+        "" => format!("Topics - {}", app.view_format().name()),
+        hints => format!("Topics - {} - {}", app.view_format().name(), hints),
+    };
+
+    app.hits.add(area, Hit::Scroll(Scrollable::Tree));
+    app.hits.add_rows(
+        area,
+        items.len(),
+        selected,
+        Corner::TopLeft,
+        BlockId::TopicTreeItem,
+    );
+
+    SelectableList::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(&title)
+                .title_style(get_color(highlight_state))
+                .border_style(get_color(highlight_state)),
+        )
+        .items(items.as_slice())
+        .select(selected)
+        .highlight_style(Style::default().fg(Color::LightCyan))
+        .render(f, area);
+}
+
+pub fn handle_input_on_tree_item(action: Action, app: &mut App, index: usize) {
+    use Action::*;
+
+    let entries = app.topic_tree.visible(&app.tree_expanded);
+
+    let entry = match entries.get(index) {
+        Some(entry) => entry,
+        None => {
+            app.navigation.modify_top(BlockId::TabNav);
+            return;
+        }
+    };
+
+    let path = entry.path.clone();
+    let filter = format!("{}/#", path);
+
+    match action {
+        Up if index == 0 => app.navigation.modify_top(BlockId::TabNav),
+        Up => app.navigation.modify_top(BlockId::TopicTreeItem(index - 1)),
+        Down if index + 1 < entries.len() => {
+            app.navigation.modify_top(BlockId::TopicTreeItem(index + 1))
+        }
+        Select | Left if app.tree_expanded.contains(&path) => {
+            app.tree_expanded.remove(&path);
+        }
+        Select | Right => {
+            app.tree_expanded.insert(path);
+        }
+        Left => {
+            // Already collapsed: select the parent instead.
+            let parent = entries[..index]
+                .iter()
+                .rposition(|e| e.depth + 1 == entry.depth);
+            if let Some(parent) = parent {
+                app.navigation.modify_top(BlockId::TopicTreeItem(parent));
+            }
+        }
+        Subscribe => {
+            app.last_error = app
+                .engine
+                .send(engine::Event::Subscribe(filter, QoS::AtLeastOnce))
+                .err();
+        }
+        Unsubscribe => {
+            if let Err(e) = app.engine.send(engine::Event::Unsubscribe(filter)) {
+                app.last_error = Some(e);
+            }
+        }
+        FilterStream => {
+            app.set_filter_input(format!("topic:{}", filter::quote(&filter)));
+            app.tabs.index = Mode::Stream as usize;
+            app.navigation.modify_top(BlockId::TabNav);
+        }
+        Pin => {
+            let report = toggle_pin(app, &path);
+            app.command_line.set_report(report);
+        }
+        CycleFormat => app.cycle_view_format(),
+        CycleTopicFormat => app.cycle_topic_format(&path),
+        ClearRetainedSubtree => clear_subtree(app, &path),
+        _ => {}
+    }
+}
@@ -3,12 +3,13 @@ use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
-use rumqtt::Notification;
-use termion::event::Key;
+use mqtt_analyzer::message::Notification;
+use termion::event::{self, Key, MouseEvent};
 use termion::input::TermRead;
 
 pub enum Event {
     Input(Key),
+    Mouse(MouseEvent),
     Tick,
     MqttNotification(Notification),
 }
@@ -37,11 +38,14 @@ impl Events {
             let tx = tx.clone();
             thread::spawn(move || {
                 let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    if let Ok(key) = evt {
-                        if tx.send(Event::Input(key)).is_err() {
-                            return;
-                        }
+                for event in stdin.events().flatten() {
+                    let event = match event {
+                        event::Event::Key(key) => Event::Input(key),
+                        event::Event::Mouse(mouse) => Event::Mouse(mouse),
+                        event::Event::Unsupported(_) => continue,
+                    };
+                    if tx.send(event).is_err() {
+                        return;
                     }
                 }
             })